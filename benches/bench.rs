@@ -9,11 +9,12 @@ use std::fs;
 use std::io::Read;
 use test::Bencher;
 use csv::Csv;
+use rustc_serialize::{Decodable, Decoder};
 
 static CSV_DATA: &'static str = "./examples/data/bench.csv";
 
 fn ordie<T, E: Debug+Display>(r: Result<T, E>) -> T {
-    r.or_else(|e: E| -> Result<T, E> { panic!(format!("{:?}", e)) }).unwrap()
+    r.or_else(|e: E| -> Result<T, E> { panic!("{:?}", e) }).unwrap()
 }
 
 fn file_to_mem(fp: &str) -> Vec<u8> {
@@ -72,7 +73,6 @@ fn bytes_records(b: &mut Bencher) {
 //}
 
 #[allow(dead_code)]
-#[derive(RustcDecodable)]
 struct Play {
     gameid: String,
     qtr: i32,
@@ -89,6 +89,26 @@ struct Play {
     season: i32,
 }
 
+impl Decodable for Play {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Play, D::Error> {
+        d.read_struct("Play", 13, |d| Ok(Play {
+            gameid: d.read_struct_field("gameid", 0, Decodable::decode)?,
+            qtr: d.read_struct_field("qtr", 1, Decodable::decode)?,
+            min: d.read_struct_field("min", 2, Decodable::decode)?,
+            sec: d.read_struct_field("sec", 3, Decodable::decode)?,
+            team_off: d.read_struct_field("team_off", 4, Decodable::decode)?,
+            team_def: d.read_struct_field("team_def", 5, Decodable::decode)?,
+            down: d.read_struct_field("down", 6, Decodable::decode)?,
+            togo: d.read_struct_field("togo", 7, Decodable::decode)?,
+            ydline: d.read_struct_field("ydline", 8, Decodable::decode)?,
+            description: d.read_struct_field("description", 9, Decodable::decode)?,
+            offscore: d.read_struct_field("offscore", 10, Decodable::decode)?,
+            defscore: d.read_struct_field("defscore", 11, Decodable::decode)?,
+            season: d.read_struct_field("season", 12, Decodable::decode)?,
+        }))
+    }
+}
+
 #[bench]
 fn decoded_records(b: &mut Bencher) {
     let data = file_to_mem(CSV_DATA);
@@ -0,0 +1,289 @@
+//! Derive macro for `quick_csv::record::CsvRecord`
+//!
+//! Enabled in quick-csv with its `derive` feature, which re-exports
+//! `CsvRecord`. See the `quick_csv::record` module for the attributes.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{parenthesized, parse_macro_input, parse_quote, token, Data, DeriveInput, Expr, Fields,
+          GenericArgument, LitInt, LitStr, Path, PathArguments, Token, Type};
+
+/// Derives `CsvRecord` for a struct with named fields
+///
+/// Also honors `#[serde(rename = "...")]` and `#[serde(alias = "...")]` so
+/// structs deriving `Deserialize` need no duplicate `csv` attributes.
+#[proc_macro_derive(CsvRecord, attributes(csv, serde))]
+pub fn derive_csv_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Attributes of a field
+#[derive(Default)]
+struct FieldAttrs {
+    name: Option<String>,
+    /// `serde(rename = "...")`, used if there is no `csv(name = "...")`
+    rename: Option<String>,
+    aliases: Vec<String>,
+    index: Option<usize>,
+    default: Option<FieldDefault>,
+    date_format: Option<String>,
+    /// `Vec` of the columns after all those of other fields
+    rest: bool,
+}
+
+/// Value of an empty or missing field
+enum FieldDefault {
+    /// `Default::default()`, or the field of the struct default with a
+    /// struct level `default`
+    Trait,
+    /// `default = "path"`, a function returning the value
+    Function(Path),
+}
+
+/// Parses a `default` attribute, with or without a function path
+fn parse_default(meta: &ParseNestedMeta) -> syn::Result<FieldDefault> {
+    if meta.input.peek(Token![=]) {
+        Ok(FieldDefault::Function(meta.value()?.parse::<LitStr>()?.parse()?))
+    } else {
+        Ok(FieldDefault::Trait)
+    }
+}
+
+fn field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("csv")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                attrs.name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("alias") {
+                attrs.aliases.push(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("index") {
+                attrs.index = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("default") {
+                attrs.default = Some(parse_default(&meta)?);
+            } else if meta.path.is_ident("rest") {
+                attrs.rest = true;
+            } else if meta.path.is_ident("date_format") {
+                attrs.date_format = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("unknown csv field attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(token::Paren) {
+                    // `rename(deserialize = "...", serialize = "...")`
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("deserialize") {
+                            attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                            Ok(())
+                        } else {
+                            skip(&meta)
+                        }
+                    })
+                } else {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                }
+            } else if meta.path.is_ident("alias") {
+                attrs.aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                // `csv(default)` takes precedence
+                let default = parse_default(&meta)?;
+                attrs.default.get_or_insert(default);
+                Ok(())
+            } else {
+                skip(&meta)
+            }
+        })?;
+    }
+    Ok(attrs)
+}
+
+/// Ignores a serde attribute unrelated to headers, along with its value
+fn skip(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(token::Paren) {
+        let content;
+        parenthesized!(content in meta.input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
+
+/// Gets `T` out of an `Option<T>` type
+fn option_inner(ty: &Type) -> Option<&Type> {
+    generic_inner(ty, "Option")
+}
+
+/// Gets `T` out of a `wrapper<T>` type
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(p) if p.qself.is_none() => &p.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut krate: Path = parse_quote!(::quick_csv);
+    // fields default to those of `Self::default()`
+    let mut struct_default = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("csv")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                struct_default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown csv attribute"))
+            }
+        })?;
+    }
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") && !meta.input.peek(Token![=]) {
+                struct_default = true;
+                Ok(())
+            } else {
+                skip(&meta)
+            }
+        })?;
+    }
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "CsvRecord requires named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "CsvRecord can only be derived for structs")),
+    };
+
+    let mut columns = Vec::new();
+    let mut inits = Vec::new();
+    for (position, field) in fields.iter().enumerate() {
+        let attrs = field_attrs(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        if attrs.rest {
+            if position + 1 != fields.len() {
+                return Err(syn::Error::new_spanned(ident, "csv(rest) must be the last field"));
+            }
+            let inner = generic_inner(&field.ty, "Vec")
+                .ok_or_else(|| syn::Error::new_spanned(&field.ty, "csv(rest) requires a Vec"))?;
+            let name = ident.to_string();
+            columns.push(quote!(::std::option::Option::None));
+            inits.push(quote! {
+                #ident: #krate::record::rest(&cols, &columns[..#position]).into_iter()
+                    .map(|v| #krate::record::parse::<#inner>(v, #name))
+                    .collect::<#krate::error::Result<_>>()?
+            });
+            continue;
+        }
+        let name = attrs.name.clone().or_else(|| attrs.rename.clone())
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let aliases = &attrs.aliases;
+        let inner = option_inner(&field.ty);
+        let default = match attrs.default {
+            Some(FieldDefault::Function(ref path)) => Some(quote!(#path())),
+            Some(FieldDefault::Trait) | None if struct_default => Some(quote!(__default.#ident)),
+            Some(FieldDefault::Trait) => Some(quote!(::std::default::Default::default())),
+            None => None,
+        };
+        let optional = default.is_some() || inner.is_some();
+        let index = match attrs.index {
+            Some(i) => quote!(::std::option::Option::Some(#i)),
+            None => quote!(::std::option::Option::None),
+        };
+        columns.push(quote! {
+            #krate::record::column(headers, &[#name #(, #aliases)*], #position, #index, #optional)?
+        });
+
+        let parse = |ty: &Type| match attrs.date_format {
+            Some(ref format) => quote!(#krate::record::parse_format::<#ty>(v, #format, #name)?),
+            None => quote!(#krate::record::parse::<#ty>(v, #name)?),
+        };
+        let ty = &field.ty;
+        let init = match (inner, default) {
+            (Some(inner), None) => {
+                let parse = parse(inner);
+                quote! {
+                    match value {
+                        ::std::option::Option::Some(v) if !v.is_empty() => ::std::option::Option::Some(#parse),
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            },
+            (_, Some(default)) => {
+                let parse = parse(ty);
+                quote! {
+                    match value {
+                        ::std::option::Option::Some(v) if !v.is_empty() => #parse,
+                        _ => #default,
+                    }
+                }
+            },
+            (None, None) => {
+                let parse = parse(ty);
+                quote! {{
+                    let v = #krate::record::required(value, #name)?;
+                    #parse
+                }}
+            },
+        };
+        inits.push(quote! {
+            #ident: {
+                let value = #krate::record::value(&cols, columns[#position]);
+                #init
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let struct_default = if struct_default {
+        quote! {
+            #[allow(unused_variables)]
+            let __default: Self = ::std::default::Default::default();
+        }
+    } else {
+        quote!()
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::record::CsvRecord for #ident #ty_generics #where_clause {
+            fn columns(headers: &#krate::record::Headers)
+                -> #krate::error::Result<::std::vec::Vec<::std::option::Option<usize>>>
+            {
+                ::std::result::Result::Ok(::std::vec![#(#columns),*])
+            }
+
+            fn from_row(row: &#krate::Row, columns: &[::std::option::Option<usize>])
+                -> #krate::error::Result<Self>
+            {
+                let cols = row.columns()?;
+                #struct_default
+                ::std::result::Result::Ok(#ident { #(#inits),* })
+            }
+        }
+    })
+}
@@ -0,0 +1,207 @@
+//! Bulk parsing module
+//!
+//! Reads batches of rows into a single reusable `Arena` instead of
+//! allocating buffers for every `Row`. All rows of a batch are freed at once
+//! when the next batch is read, which avoids per-row malloc/free.
+
+use std::io::BufRead;
+use std::sync::Arc;
+
+use rustc_serialize::Decodable;
+
+use columns::{BytesColumns, Columns};
+use decode::DecodeOptions;
+use error::{Error, Result};
+use Csv;
+
+/// Buffers shared by a batch of rows
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::arena::Arena;
+///
+/// let mut csv = quick_csv::Csv::from_string("1,2\n3,4\n5,6");
+/// let mut arena = Arena::new();
+/// let mut sum = 0;
+/// while csv.read_batch(&mut arena, 2).unwrap() > 0 {
+///     for row in arena.rows() {
+///         let (a, b) = row.decode::<(u32, u32)>().unwrap();
+///         sum += a * b;
+///     }
+/// }
+/// assert_eq!(sum, 44);
+/// ```
+#[derive(Default)]
+pub struct Arena {
+    /// content of all rows
+    line: Vec<u8>,
+    /// column positions of all rows, relative to their row
+    cols: Vec<usize>,
+    /// line and cols start of every row
+    rows: Vec<(usize, usize)>,
+    /// decoding options of the rows
+    decode_options: Option<Arc<DecodeOptions>>,
+    /// rows keep their quotes and escapes
+    raw: bool,
+}
+
+impl Arena {
+
+    /// Creates a new empty arena
+    pub fn new() -> Arena {
+        Arena::default()
+    }
+
+    /// Creates a new arena able to hold `bytes` bytes of row content
+    /// and `rows` rows without reallocating
+    pub fn with_capacity(bytes: usize, rows: usize) -> Arena {
+        Arena {
+            line: Vec::with_capacity(bytes),
+            cols: Vec::with_capacity(rows),
+            rows: Vec::with_capacity(rows),
+            decode_options: None,
+            raw: false,
+        }
+    }
+
+    /// Removes all rows, keeping allocated memory
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cols.clear();
+        self.rows.clear();
+    }
+
+    /// Gets rows count
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// `Arena` is empty if there is no rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Gets the `i`th row
+    pub fn get(&self, i: usize) -> Option<ArenaRow<'_>> {
+        let &(line_start, cols_start) = self.rows.get(i)?;
+        let (line_end, cols_end) = self.rows.get(i + 1).cloned()
+            .unwrap_or((self.line.len(), self.cols.len()));
+        Some(ArenaRow {
+            line: &self.line[line_start..line_end],
+            cols: &self.cols[cols_start..cols_end],
+            decode_options: self.decode_options.as_deref(),
+            raw: self.raw,
+        })
+    }
+
+    /// Gets an iterator over all rows
+    pub fn rows(&self) -> Rows<'_> {
+        Rows {
+            arena: self,
+            pos: 0,
+        }
+    }
+
+}
+
+/// Iterator over the rows of an `Arena`
+pub struct Rows<'a> {
+    arena: &'a Arena,
+    pos: usize,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = ArenaRow<'a>;
+
+    fn next(&mut self) -> Option<ArenaRow<'a>> {
+        let row = self.arena.get(self.pos)?;
+        self.pos += 1;
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.arena.len() - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Rows<'a> {}
+
+/// A row borrowed from an `Arena`
+///
+/// Provides the same accessors as `Row`
+pub struct ArenaRow<'a> {
+    line: &'a [u8],
+    cols: &'a [usize],
+    decode_options: Option<&'a DecodeOptions>,
+    raw: bool,
+}
+
+impl<'a> ArenaRow<'a> {
+
+    /// Gets an iterator over columns
+    pub fn columns(&self) -> Result<Columns<'a>> {
+        self.as_str().map(|s| Columns::new(s, self.cols).raw(self.raw))
+    }
+
+    /// Creates a new BytesColumns iterator over &[u8]
+    pub fn bytes_columns(&self) -> BytesColumns<'a> {
+        BytesColumns::new(self.line, self.cols).raw(self.raw)
+    }
+
+    /// Decode row into custom decodable type
+    pub fn decode<T: Decodable>(&self) -> Result<T> {
+        let mut columns = self.columns()?.with_options(self.decode_options);
+        Decodable::decode(&mut columns)
+    }
+
+    /// Gets the entire row content, see `Row::as_bytes`
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.line
+    }
+
+    /// Gets the entire row content as a `&str`, see `Row::as_bytes`
+    pub fn as_str(&self) -> Result<&'a str> {
+        ::std::str::from_utf8(self.line).map_err(|_|
+            Error::Io(::std::io::Error::new(::std::io::ErrorKind::InvalidData,
+                                            "stream did not contain valid UTF-8")))
+    }
+
+    /// Gets columns count
+    pub fn len(&self) -> usize {
+        self.cols.len()
+    }
+
+    /// `ArenaRow` is empty if there is no columns
+    pub fn is_empty(&self) -> bool {
+        self.cols.is_empty()
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Clears `arena` and reads up to `max_rows` rows into it
+    ///
+    /// Returns the number of rows read, 0 once the csv is exhausted.
+    /// Rows are always validated for UTF-8 on access.
+    ///
+    /// Fewer rows are read once they reach the `memory_budget`.
+    pub fn read_batch(&mut self, arena: &mut Arena, max_rows: usize) -> Result<usize> {
+        arena.clear();
+        arena.decode_options = self.decode_options.clone();
+        arena.raw = self.raw;
+        let budget = self.budget();
+        while arena.len() < max_rows && budget.fits(arena.line.len()) {
+            let start = (arena.line.len(), arena.cols.len());
+            match self.read_record(&mut arena.line, &mut arena.cols) {
+                Some(Ok(())) => arena.rows.push(start),
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+        Ok(arena.len())
+    }
+
+}
@@ -0,0 +1,131 @@
+//! Binary columns
+//!
+//! Files embedding binary payloads, e.g. hashes, thumbnails or keys, carry
+//! them as base64 or hex text. Columns registered with `Csv::binary_column`
+//! are decoded back into bytes by `Row::decode`, a `Vec<u8>` field reading a
+//! single column, and `Writer::binary_column` encodes them on write.
+//!
+//! Base64 uses the standard alphabet (`+` and `/`), padding being optional
+//! when decoding. Hex digits are case insensitive and written in lowercase.
+//! Surrounding spaces are ignored.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::binary::Encoding;
+//! use quick_csv::writer::Writer;
+//!
+//! let mut csv = Csv::from_string("id,digest\n1,3q2+7w==").has_header(true)
+//!     .binary_column("digest", Encoding::Base64);
+//! let (id, digest) = csv.next().unwrap().unwrap().decode::<(u32, Vec<u8>)>().unwrap();
+//! assert_eq!(digest, vec![0xde, 0xad, 0xbe, 0xef]);
+//!
+//! let mut writer = Writer::from_writer(Vec::new()).binary_column(1, Encoding::Hex);
+//! writer.write([id.to_string().as_bytes(), &digest]).unwrap();
+//! assert_eq!(writer.into_inner().unwrap(), b"1,deadbeef\n");
+//! ```
+
+use std::fmt;
+
+use writer::RecordBuilder;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// How bytes are written as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Standard base64, `3q2+7w==`
+    Base64,
+    /// Two hex digits per byte, `deadbeef`
+    Hex,
+}
+
+impl Encoding {
+
+    /// Encodes `bytes` as text
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match *self {
+            Encoding::Base64 => {
+                let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+                for chunk in bytes.chunks(3) {
+                    let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+                    for i in 0..4 {
+                        s.push(if i <= chunk.len() {
+                            BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char
+                        } else {
+                            '='
+                        });
+                    }
+                }
+                s
+            },
+            Encoding::Hex => {
+                let mut s = String::with_capacity(bytes.len() * 2);
+                for &b in bytes {
+                    s.push(HEX[usize::from(b >> 4)] as char);
+                    s.push(HEX[usize::from(b & 0xf)] as char);
+                }
+                s
+            },
+        }
+    }
+
+    /// Decodes `text`, surrounding spaces aside
+    ///
+    /// Fails with a description of the problem on invalid characters or
+    /// length.
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text = text.trim();
+        match *self {
+            Encoding::Base64 => {
+                let digits = text.trim_end_matches('=');
+                if digits.len() % 4 == 1 || (digits.len() != text.len() && !text.len().is_multiple_of(4)) {
+                    return Err(format!("invalid base64 length {}", text.len()));
+                }
+                let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+                let (mut acc, mut bits) = (0u32, 0);
+                for (i, c) in digits.char_indices() {
+                    let value = BASE64.iter().position(|&b| b as char == c)
+                        .ok_or_else(|| format!("invalid base64 character '{}' at {}", c, i))?;
+                    acc = acc << 6 | value as u32;
+                    bits += 6;
+                    if bits >= 8 {
+                        bits -= 8;
+                        bytes.push((acc >> bits) as u8);
+                        acc &= (1 << bits) - 1;
+                    }
+                }
+                Ok(bytes)
+            },
+            Encoding::Hex => {
+                if !text.len().is_multiple_of(2) {
+                    return Err(format!("odd hex length {}", text.len()));
+                }
+                let digit = |i: usize| char::from(text.as_bytes()[i]).to_digit(16)
+                    .ok_or_else(|| format!("invalid hex digit at {}", i));
+                (0..text.len()).step_by(2).map(|i| Ok((digit(i)? << 4 | digit(i + 1)?) as u8)).collect()
+            },
+        }
+    }
+
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hex",
+        })
+    }
+}
+
+impl RecordBuilder {
+
+    /// Appends `bytes` written with `encoding`
+    pub fn binary(self, bytes: &[u8], encoding: Encoding) -> RecordBuilder {
+        self.field(encoding.encode(bytes))
+    }
+
+}
@@ -0,0 +1,35 @@
+//! Accounting of the memory used by buffering operations
+//!
+//! Sizes are estimates made of the buffered bytes, not allocator figures.
+
+use error::{Error, Result};
+
+/// Bytes an operation may still buffer, out of `Csv::memory_budget`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    limit: usize,
+    used: usize,
+}
+
+impl Budget {
+
+    pub fn new(limit: usize) -> Budget {
+        Budget { limit, used: 0 }
+    }
+
+    /// Whether `bytes` more bytes fit within the budget
+    pub fn fits(&self, bytes: usize) -> bool {
+        bytes <= self.limit - self.used
+    }
+
+    /// Accounts for `bytes` more bytes, failing with `Error::MemoryBudget`
+    /// if they exceed the budget
+    pub fn charge(&mut self, bytes: usize) -> Result<()> {
+        if !self.fits(bytes) {
+            return Err(Error::MemoryBudget(self.limit));
+        }
+        self.used += bytes;
+        Ok(())
+    }
+
+}
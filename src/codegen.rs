@@ -0,0 +1,175 @@
+//! Rust struct generation
+//!
+//! Infers the type of every column from the remaining rows and writes the
+//! source of a `serde::Deserialize` struct with one field per column, ready
+//! to be pasted and deserialized, e.g. from the `csv::StringRecord`s of the
+//! `compat` module.
+//!
+//! Columns are `bool`, `i64`, `f64` or `String`, the first type all their
+//! values parse as. Columns with empty values are `Option`s. Field names are
+//! the headers in snake case, or `column_<i>` without headers, renamed back
+//! to their header with `#[serde(rename = "...")]`.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//!
+//! let mut csv = Csv::from_string("Cust. ID,Total\n1,2.5\n2,").has_header(true);
+//! let source = csv.codegen("Order").generate().unwrap();
+//! assert_eq!(source, "\
+//! #[derive(Debug, Deserialize)]
+//! pub struct Order {
+//!     #[serde(rename = \"Cust. ID\")]
+//!     pub cust_id: i64,
+//!     #[serde(rename = \"Total\")]
+//!     pub total: Option<f64>,
+//! }
+//! ");
+//! ```
+
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use coerce::{self, Counts, Type};
+use error::Result;
+use headers::snake_case;
+use Csv;
+
+/// Keywords which cannot be field names
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Struct generator
+///
+/// Created with `Csv::codegen`.
+pub struct Codegen<'a, B: 'a + BufRead> {
+    csv: &'a mut Csv<B>,
+    name: String,
+    derives: Vec<String>,
+    rows: usize,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Generates the source of a struct named `name` matching the remaining rows
+    pub fn codegen(&mut self, name: &str) -> Codegen<'_, B> {
+        Codegen {
+            csv: self,
+            name: name.to_string(),
+            derives: vec!["Debug".to_string(), "Deserialize".to_string()],
+            rows: usize::MAX,
+        }
+    }
+
+}
+
+impl<'a, B: BufRead> Codegen<'a, B> {
+
+    /// Sets the derived traits
+    ///
+    /// With `Deserialize`, fields whose name differs from their header get
+    /// a `#[serde(rename = "...")]` attribute.
+    ///
+    /// Note: default = `Debug, Deserialize`
+    pub fn derive<S: AsRef<str>>(mut self, derives: &[S]) -> Codegen<'a, B> {
+        self.derives = derives.iter().map(|d| d.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Infers types from the first `rows` rows only
+    ///
+    /// Note: default = all remaining rows
+    pub fn sample(mut self, rows: usize) -> Codegen<'a, B> {
+        self.rows = rows;
+        self
+    }
+
+    /// Reads the rows and returns the struct source
+    pub fn generate(self) -> Result<String> {
+        let headers = self.csv.headers();
+        let mut counts: Vec<Counts> = vec![Counts::default(); headers.len()];
+        let mut rows = 0;
+        for row in self.csv.take(self.rows) {
+            let row = row?;
+            rows += 1;
+            for (i, col) in row.columns()?.enumerate() {
+                if counts.len() <= i {
+                    // previous rows miss the column
+                    counts.push(Counts { null: rows - 1, ..Counts::default() });
+                }
+                counts[i].add(&coerce::infer(&row, col));
+            }
+            for c in counts.iter_mut().skip(row.len()) {
+                c.null += 1;
+            }
+        }
+
+        let serde = self.derives.iter().any(|d| d == "Deserialize" || d.ends_with("::Deserialize"));
+        let mut source = String::new();
+        if !self.derives.is_empty() {
+            source.push_str(&format!("#[derive({})]\n", self.derives.join(", ")));
+        }
+        source.push_str(&format!("pub struct {} {{\n", self.name));
+        let mut names = HashSet::new();
+        for (i, c) in counts.iter().enumerate() {
+            let header = headers.get(i);
+            let name = unique(field_name(header.map(|h| &**h), i), &mut names);
+            if serde {
+                if let Some(header) = header.filter(|h| **h != name) {
+                    source.push_str(&format!("    #[serde(rename = {:?})]\n", header));
+                }
+            }
+            source.push_str(&format!("    pub {}: {},\n", name, rust_type(c)));
+        }
+        source.push_str("}\n");
+        Ok(source)
+    }
+
+}
+
+/// Gets the type of a column out of its value counts
+fn rust_type(counts: &Counts) -> String {
+    let kind = match counts.common() {
+        Some(Type::Bool) => "bool",
+        Some(Type::Int) => "i64",
+        Some(Type::Float) => "f64",
+        Some(Type::Str) | None => "String",
+    };
+    if counts.null > 0 {
+        format!("Option<{}>", kind)
+    } else {
+        kind.to_string()
+    }
+}
+
+/// Converts a header into a snake case identifier
+fn field_name(header: Option<&str>, i: usize) -> String {
+    let mut name = snake_case(header.unwrap_or(""));
+    if name.is_empty() {
+        return format!("column_{}", i);
+    }
+    if name.starts_with(|c: char| c.is_numeric()) {
+        name.insert(0, '_');
+    } else if KEYWORDS.contains(&&*name) {
+        name.push('_');
+    }
+    name
+}
+
+/// Suffixes `name` with a number if already taken
+fn unique(name: String, names: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut n = 1;
+    while names.contains(&unique) {
+        n += 1;
+        unique = format!("{}_{}", name, n);
+    }
+    names.insert(unique.clone());
+    unique
+}
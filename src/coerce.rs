@@ -0,0 +1,262 @@
+//! Type coercion with fallback chains
+//!
+//! Converts every column into the first type of its chain which its value
+//! parses as, e.g. `Int`, then `Float`, then `Str`, and counts per column
+//! which type won. This suits messy files where a numeric column holds the
+//! occasional text value.
+//!
+//! Field hooks apply, and numbers are normalized as for `Row::decode`
+//! (`thousands_separator`, `currency_symbols`...). Empty values are nulls.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::coerce::{Type, Value};
+//!
+//! let csv = Csv::from_string("id,size\n1,2.5\nx,3\n2,0.5").has_header(true);
+//! let mut rows = csv.coerce(&[Type::Int, Type::Float, Type::Str]).column("id", &[Type::Int, Type::Str]);
+//! let values = rows.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+//! assert_eq!(values[1], vec![Value::Str("x".to_string()), Value::Int(3)]);
+//! assert_eq!(rows.counts()[1].winner(), Some(Type::Float));
+//! ```
+
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use decode::Column;
+use error::{Error, Result};
+use {Csv, Row};
+
+/// A type values may be coerced into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// `true` or `false`
+    Bool,
+    /// 64 bits signed integer
+    Int,
+    /// 64 bits float
+    Float,
+    /// Any text
+    Str,
+}
+
+/// A coerced value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Empty value
+    Null,
+    /// Boolean value
+    Bool(bool),
+    /// Integer value
+    Int(i64),
+    /// Float value
+    Float(f64),
+    /// Text value
+    Str(String),
+}
+
+impl Value {
+    /// Gets the type of the value, `None` if null
+    pub fn kind(&self) -> Option<Type> {
+        match *self {
+            Value::Null => None,
+            Value::Bool(_) => Some(Type::Bool),
+            Value::Int(_) => Some(Type::Int),
+            Value::Float(_) => Some(Type::Float),
+            Value::Str(_) => Some(Type::Str),
+        }
+    }
+}
+
+/// Number of values of a column per type they were coerced into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// empty values
+    pub null: usize,
+    /// values coerced into booleans
+    pub bool: usize,
+    /// values coerced into integers
+    pub int: usize,
+    /// values coerced into floats
+    pub float: usize,
+    /// values kept as text
+    pub str: usize,
+}
+
+impl Counts {
+
+    /// Gets the number of values coerced into `kind`
+    pub fn get(&self, kind: Type) -> usize {
+        match kind {
+            Type::Bool => self.bool,
+            Type::Int => self.int,
+            Type::Float => self.float,
+            Type::Str => self.str,
+        }
+    }
+
+    /// Gets the type most values were coerced into, `None` if all are null
+    ///
+    /// Ties go to the most specific type.
+    pub fn winner(&self) -> Option<Type> {
+        let types = [Type::Bool, Type::Int, Type::Float, Type::Str];
+        let best = types.iter().cloned().rev().max_by_key(|&t| self.get(t))?;
+        if self.get(best) == 0 { None } else { Some(best) }
+    }
+
+    /// Gets the type all values can be read as, `None` if all are null
+    ///
+    /// Integers widen into floats, and booleans mixed with numbers, like
+    /// text, give a text column.
+    pub fn common(&self) -> Option<Type> {
+        if self.str > 0 || self.bool > 0 && self.int + self.float > 0 {
+            Some(Type::Str)
+        } else if self.float > 0 {
+            Some(Type::Float)
+        } else if self.int > 0 {
+            Some(Type::Int)
+        } else if self.bool > 0 {
+            Some(Type::Bool)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn add(&mut self, value: &Value) {
+        match value.kind() {
+            None => self.null += 1,
+            Some(Type::Bool) => self.bool += 1,
+            Some(Type::Int) => self.int += 1,
+            Some(Type::Float) => self.float += 1,
+            Some(Type::Str) => self.str += 1,
+        }
+    }
+
+}
+
+/// Iterator over rows of coerced values
+///
+/// Created with `Csv::coerce`.
+pub struct Coerced<B: BufRead> {
+    csv: Csv<B>,
+    headers: Vec<String>,
+    /// chain of columns without a chain of their own
+    default: Vec<Type>,
+    /// chains as defined by the user, and by column position
+    chains: Vec<(Column, Vec<Type>)>,
+    resolved: Vec<Option<Vec<Type>>>,
+    counts: Vec<Counts>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining rows, each column coerced into the first type
+    /// of `chain` its value parses as
+    ///
+    /// A value parsing as no type of its chain is an `Error::Decode`.
+    pub fn coerce(mut self, chain: &[Type]) -> Coerced<B> {
+        let headers = self.headers();
+        Coerced {
+            csv: self,
+            headers,
+            default: chain.to_vec(),
+            chains: Vec::new(),
+            resolved: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+}
+
+impl<B: BufRead> Coerced<B> {
+
+    /// Sets the chain of a column, by position or header name
+    pub fn column<C: Into<Column>>(mut self, column: C, chain: &[Type]) -> Coerced<B> {
+        self.chains.push((column.into(), chain.to_vec()));
+        self.resolved.clear();
+        for (column, chain) in &self.chains {
+            for i in column.indices(&self.headers, self.csv.duplicates, self.csv.ignore_case) {
+                if self.resolved.len() <= i {
+                    self.resolved.resize(i + 1, None);
+                }
+                self.resolved[i] = Some(chain.clone());
+            }
+        }
+        self
+    }
+
+    /// Gets, per column, the number of values coerced into each type so far
+    pub fn counts(&self) -> &[Counts] {
+        &self.counts
+    }
+
+    fn values(&mut self, row: &Row) -> Result<Vec<Value>> {
+        let mut values = Vec::with_capacity(row.len());
+        for (i, col) in row.columns()?.enumerate() {
+            let col = hooked(row, i, col);
+            let chain = match self.resolved.get(i) {
+                Some(Some(chain)) => chain,
+                _ => &self.default,
+            };
+            let value = if col.is_empty() {
+                Value::Null
+            } else {
+                chain.iter().filter_map(|&t| coerce(row, &col, t)).next().ok_or_else(|| {
+                    Error::Decode(format!("Cannot coerce '{}' in column {} into any of {:?}", col, i, chain))
+                })?
+            };
+            values.push(value);
+        }
+        if self.counts.len() < values.len() {
+            self.counts.resize(values.len(), Counts::default());
+        }
+        for (counts, value) in self.counts.iter_mut().zip(&values) {
+            counts.add(value);
+        }
+        Ok(values)
+    }
+
+}
+
+/// Applies the field hook of the `i`th column, if any, to `col`
+pub(crate) fn hooked<'a>(row: &Row, i: usize, col: &'a str) -> Cow<'a, str> {
+    match row.decode_options {
+        Some(ref options) => options.apply(i, col),
+        None => Cow::Borrowed(col),
+    }
+}
+
+/// Parses `col` as the most specific type, booleans first, then integers,
+/// floats and text, empty values being nulls
+pub(crate) fn infer(row: &Row, col: &str) -> Value {
+    if col.is_empty() {
+        return Value::Null;
+    }
+    [Type::Bool, Type::Int, Type::Float].iter().filter_map(|&t| coerce(row, col, t)).next()
+        .unwrap_or_else(|| Value::Str(col.to_string()))
+}
+
+/// Parses `col` as `kind`
+pub(crate) fn coerce(row: &Row, col: &str, kind: Type) -> Option<Value> {
+    let number = || match row.decode_options {
+        Some(ref options) => options.number(Cow::Borrowed(col)),
+        None => Cow::Borrowed(col),
+    };
+    match kind {
+        Type::Bool if col == "true" => Some(Value::Bool(true)),
+        Type::Bool if col == "false" => Some(Value::Bool(false)),
+        Type::Bool => None,
+        Type::Int => number().trim().parse().ok().map(Value::Int),
+        Type::Float => number().trim().parse().ok().map(Value::Float),
+        Type::Str => Some(Value::Str(col.to_string())),
+    }
+}
+
+impl<B: BufRead> Iterator for Coerced<B> {
+    type Item = Result<Vec<Value>>;
+
+    fn next(&mut self) -> Option<Result<Vec<Value>>> {
+        self.csv.next().map(|row| row.and_then(|row| self.values(&row)))
+    }
+}
@@ -3,38 +3,72 @@
 //! Provides convenient or fast column conversions
 //! Deserialize columns into a `Decodable` type
 
+use std::borrow::Cow;
+use std::slice;
 use std::str::FromStr;
 use rustc_serialize as serialize;
+use decode::DecodeOptions;
+use intern;
 use error::{Result, Error};
-use std::slice::Iter;
+
+/// Returns the byte range of the `i`th column, without surrounding quotes
+pub(crate) fn column_range(line: &[u8], cols: &[usize], i: usize) -> (usize, usize) {
+    let (start, end) = column_span(cols, i);
+    if end - start > 1 && line[start] == b'"' { (start + 1, end - 1) } else { (start, end) }
+}
+
+/// Returns the byte range of the `i`th column, including surrounding quotes
+pub(crate) fn column_span(cols: &[usize], i: usize) -> (usize, usize) {
+    (if i == 0 { 0 } else { cols[i - 1] + 1 }, cols[i])
+}
+
+/// Returns the byte range of a column either raw or without its quotes
+fn range(line: &[u8], cols: &[usize], i: usize, raw: bool) -> (usize, usize) {
+    if raw { column_span(cols, i) } else { column_range(line, cols, i) }
+}
 
 /// Iterator over bytes slice of columns
 pub struct BytesColumns<'a> {
-    pos: usize,
     line: &'a [u8],
-    iter: Iter<'a, usize>,
+    cols: &'a [usize],
+    front: usize,
+    back: usize,
+    /// columns keep their quotes
+    raw: bool,
 }
 
 impl<'a> Iterator for BytesColumns<'a> {
     type Item = &'a [u8];
 
     fn next(&mut self) -> Option<&'a [u8]> {
-        self.iter.next().map(|p| {
-            let s = &self.line[self.pos..*p];
-            self.pos = *p + 1;
-            if s.starts_with(&[b'\"']) { &s[1..s.len() - 1] } else { s }
-        })
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        let (start, end) = range(self.line, self.cols, self.front - 1, self.raw);
+        Some(&self.line[start..end])
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+        (self.len(), Some(self.len()))
     }
 
 }
 
+impl<'a> DoubleEndedIterator for BytesColumns<'a> {
+    fn next_back(&mut self) -> Option<&'a [u8]> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        let (start, end) = range(self.line, self.cols, self.back, self.raw);
+        Some(&self.line[start..end])
+    }
+}
+
 impl<'a> ExactSizeIterator for BytesColumns<'a> {
     fn len(&self) -> usize {
-        self.iter.len()
+        self.back - self.front
     }
 }
 
@@ -43,41 +77,107 @@ impl<'a> BytesColumns<'a> {
     /// Creates a new BytesColumns iterator
     pub fn new(line: &'a [u8], cols: &'a [usize]) -> BytesColumns<'a> {
         BytesColumns {
-            pos: 0,
-            line: line,
-            iter: cols.iter(),
+            line,
+            cols,
+            front: 0,
+            back: cols.len(),
+            raw: false,
         }
     }
 
+    /// Defines whether columns are returned with their surrounding quotes
+    pub(crate) fn raw(mut self, raw: bool) -> BytesColumns<'a> {
+        self.raw = raw;
+        self
+    }
+
+    /// Returns the `i`th column of the row, regardless of how far the
+    /// iterator has advanced
+    pub fn get(&self, i: usize) -> Option<&'a [u8]> {
+        if i >= self.cols.len() {
+            return None;
+        }
+        let (start, end) = range(self.line, self.cols, i, self.raw);
+        Some(&self.line[start..end])
+    }
+
+}
+
+/// Iterator over `(header, column)` pairs
+///
+/// Created with `Row::named_columns`, stops at the end of the shorter of the
+/// headers and the row.
+pub struct NamedColumns<'a> {
+    headers: slice::Iter<'a, String>,
+    columns: Columns<'a>,
+}
+
+impl<'a> NamedColumns<'a> {
+    pub(crate) fn new(headers: &'a [String], columns: Columns<'a>) -> NamedColumns<'a> {
+        NamedColumns {
+            headers: headers.iter(),
+            columns,
+        }
+    }
+}
+
+impl<'a> Iterator for NamedColumns<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        let header = self.headers.next()?;
+        self.columns.next().map(|col| (&**header, col))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.headers.len().min(self.columns.len());
+        (len, Some(len))
+    }
 }
 
 /// &str iterator on columns
 pub struct Columns<'a> {
-    pos: usize,
     line: &'a str,
-    iter: Iter<'a, usize>,
+    cols: &'a [usize],
+    front: usize,
+    back: usize,
+    options: Option<&'a DecodeOptions>,
+    /// columns keep their quotes
+    raw: bool,
+    /// bytes of the binary column being decoded, and the next one to read
+    bytes: Option<(Vec<u8>, usize)>,
 }
 
 impl<'a> Iterator for Columns<'a> {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<&'a str> {
-        self.iter.next().map(|p| {
-            let s = &self.line[self.pos..*p];
-            self.pos = *p + 1;
-            if s.starts_with('\"') { &s[1..s.len() - 1] } else { s }
-        })
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.column(self.front - 1))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+        (self.len(), Some(self.len()))
     }
 
 }
 
+impl<'a> DoubleEndedIterator for Columns<'a> {
+    fn next_back(&mut self) -> Option<&'a str> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.column(self.back))
+    }
+}
+
 impl<'a> ExactSizeIterator for Columns<'a> {
     fn len(&self) -> usize {
-        self.iter.len()
+        self.back - self.front
     }
 }
 
@@ -86,27 +186,92 @@ impl<'a> Columns<'a> {
     /// Creates a new Columns iterator
     pub fn new(line: &'a str, cols: &'a [usize]) -> Columns<'a> {
         Columns {
-            pos: 0,
-            line: line,
-            iter: cols.iter(),
+            line,
+            cols,
+            front: 0,
+            back: cols.len(),
+            options: None,
+            raw: false,
+            bytes: None,
         }
     }
 
-    fn peek(&self) -> Option<&'a str> {
-        self.iter.clone().next().map(|p| {
-            let s = &self.line[self.pos..*p];
-            if s.starts_with('\"') { &s[1..s.len() - 1] } else { s }
-        })
+    /// Defines whether columns are returned with their surrounding quotes
+    pub(crate) fn raw(mut self, raw: bool) -> Columns<'a> {
+        self.raw = raw;
+        self
+    }
+
+    /// Sets the options applied when decoding
+    pub(crate) fn with_options(mut self, options: Option<&'a DecodeOptions>) -> Columns<'a> {
+        self.options = options;
+        self
+    }
+
+    /// Returns the `i`th column of the row, regardless of how far the
+    /// iterator has advanced
+    pub fn get(&self, i: usize) -> Option<&'a str> {
+        if i >= self.cols.len() {
+            return None;
+        }
+        Some(self.column(i))
+    }
+
+    fn column(&self, i: usize) -> &'a str {
+        let (start, end) = range(self.line.as_bytes(), self.cols, i, self.raw);
+        &self.line[start..end]
+    }
+
+    /// Applies decoding options on the column at position `i`
+    fn field(&self, i: usize, col: &'a str) -> Cow<'a, str> {
+        match self.options {
+            Some(options) => options.apply(i, col),
+            None => Cow::Borrowed(col),
+        }
+    }
+
+    fn peek(&self) -> Option<Cow<'a, str>> {
+        if self.front == self.back {
+            return None;
+        }
+        Some(self.field(self.front, self.column(self.front)))
+    }
+
+    fn next_field(&mut self) -> Option<Cow<'a, str>> {
+        let i = self.front;
+        self.next().map(|col| self.field(i, col))
     }
 
     fn next_str<T>(&mut self) -> Result<T>
         where T: FromStr + ::std::fmt::Debug, 
               T::Err: ::std::fmt::Debug
     {
-        self.next().ok_or(Error::EOL).and_then(|col|
-            FromStr::from_str(col).map_err(|e|
-                Error::Decode(format!("Failed converting {}th column (\'{}\'):\n\t{:?}", 
-                    self.len(), col, e))))
+        let i = self.front;
+        let col = self.next_field().ok_or(Error::EOL)?;
+        match self.options {
+            Some(options) => self.parse(&options.date(i, col)?),
+            None => self.parse(&col),
+        }
+    }
+
+    fn next_number<T>(&mut self) -> Result<T>
+        where T: FromStr + ::std::fmt::Debug,
+              T::Err: ::std::fmt::Debug
+    {
+        let col = self.next_field().ok_or(Error::EOL)?;
+        match self.options {
+            Some(options) => self.parse(&options.number(col)),
+            None => self.parse(&col),
+        }
+    }
+
+    fn parse<T>(&self, col: &str) -> Result<T>
+        where T: FromStr + ::std::fmt::Debug,
+              T::Err: ::std::fmt::Debug
+    {
+        FromStr::from_str(col).map_err(|e|
+            Error::Decode(format!("Failed converting {}th column (\'{}\'):\n\t{:?}", 
+                self.len(), col, e)))
     }
 
     /// Deserializes a Columns iterator into any Decodable type
@@ -123,28 +288,49 @@ impl<'a> serialize::Decoder for Columns<'a> {
         Error::Decode(err.into())
     }
     fn read_nil(&mut self) -> Result<()> { unimplemented!() }
-    fn read_usize(&mut self) -> Result<usize> { self.next_str() }
-    fn read_u64(&mut self) -> Result<u64> { self.next_str() }
-    fn read_u32(&mut self) -> Result<u32> { self.next_str() }
-    fn read_u16(&mut self) -> Result<u16> { self.next_str() }
-    fn read_u8(&mut self) -> Result<u8> { self.next_str() }
-    fn read_isize(&mut self) -> Result<isize> { self.next_str() }
-    fn read_i64(&mut self) -> Result<i64> { self.next_str() }
-    fn read_i32(&mut self) -> Result<i32> { self.next_str() }
-    fn read_i16(&mut self) -> Result<i16> { self.next_str() }
-    fn read_i8(&mut self) -> Result<i8> { self.next_str() }
+    fn read_usize(&mut self) -> Result<usize> { self.next_number() }
+    fn read_u64(&mut self) -> Result<u64> { self.next_number() }
+    fn read_u32(&mut self) -> Result<u32> { self.next_number() }
+    fn read_u16(&mut self) -> Result<u16> { self.next_number() }
+    fn read_u8(&mut self) -> Result<u8> {
+        match self.bytes {
+            Some((ref bytes, ref mut next)) => {
+                let b = *bytes.get(*next).ok_or(Error::EOL)?;
+                *next += 1;
+                Ok(b)
+            },
+            None => self.next_number(),
+        }
+    }
+    fn read_isize(&mut self) -> Result<isize> { self.next_number() }
+    fn read_i64(&mut self) -> Result<i64> { self.next_number() }
+    fn read_i32(&mut self) -> Result<i32> { self.next_number() }
+    fn read_i16(&mut self) -> Result<i16> { self.next_number() }
+    fn read_i8(&mut self) -> Result<i8> { self.next_number() }
     fn read_bool(&mut self) -> Result<bool> { self.next_str() }
-    fn read_f64(&mut self) -> Result<f64> { self.next_str() }
-    fn read_f32(&mut self) -> Result<f32> { self.next_str() }
+    fn read_f64(&mut self) -> Result<f64> { self.next_number() }
+    fn read_f32(&mut self) -> Result<f32> { self.next_number() }
     fn read_char(&mut self) -> Result<char> {
-        let col = try!(self.next().ok_or(Error::EOL));
+        let col = self.next_field().ok_or(Error::EOL)?;
         if col.len() != 1 {
             return Err(Error::Decode(format!(
                 "Expected a single char, found {} chars", col.len())));
         }
         Ok(col.chars().next().unwrap())
     }
-    fn read_str(&mut self) -> Result<String> { self.next_str() }
+    fn read_str(&mut self) -> Result<String> {
+        if !intern::requested() {
+            return self.next_str();
+        }
+        let i = self.front;
+        let col = self.next_field().ok_or(Error::EOL)?;
+        let col = match self.options {
+            Some(options) => options.date(i, col)?,
+            None => col,
+        };
+        intern::fill(&col);
+        Ok(String::new())
+    }
     fn read_enum<T, F>(&mut self, _: &str, f: F) -> Result<T>
             where F: FnOnce(&mut Columns<'a>) -> Result<T> {
         f(self)
@@ -152,11 +338,11 @@ impl<'a> serialize::Decoder for Columns<'a> {
 
     fn read_enum_variant<T, F>(&mut self, names: &[&str], mut f: F) -> Result<T>
             where F: FnMut(&mut Columns<'a>, usize) -> Result<T> {
-        let i = try!(self
+        let i = self
             .peek()
             .and_then(|name| names.iter().position(|&n| n == name))
             .ok_or(Error::Decode(format!(
-                "Could not load value into any variant in {:?}", names))));
+                "Could not load value into any variant in {:?}", names)))?;
         let _ = self.next();
         f(self, i)
     }
@@ -178,9 +364,24 @@ impl<'a> serialize::Decoder for Columns<'a> {
             where F: FnOnce(&mut Columns<'a>) -> Result<T> {
         f(self)
     }
-    fn read_struct_field<T, F>(&mut self, _: &str, _: usize, f: F) -> Result<T>
+    fn read_struct_field<T, F>(&mut self, name: &str, _: usize, f: F) -> Result<T>
             where F: FnOnce(&mut Columns<'a>) -> Result<T> {
-        f(self)
+        let result = match self.options.and_then(|o| o.field_column(name)) {
+            Some(i) => {
+                // bound fields do not move the positional fields
+                let i = i?.min(self.back);
+                let front = ::std::mem::replace(&mut self.front, i);
+                let result = f(self);
+                self.front = front;
+                result
+            },
+            None => f(self),
+        };
+        // names the offending field
+        result.map_err(|e| match e {
+            Error::Decode(msg) => Error::Decode(format!("Field '{}': {}", name, msg)),
+            e => e,
+        })
     }
     fn read_tuple<T, F>(&mut self, _: usize, f: F) -> Result<T>
             where F: FnOnce(&mut Columns<'a>) -> Result<T> {
@@ -201,10 +402,13 @@ impl<'a> serialize::Decoder for Columns<'a> {
 
     fn read_option<T, F>(&mut self, mut f: F) -> Result<T>
             where F: FnMut(&mut Columns<'a>, bool) -> Result<T> {
-        let col = try!(self.peek().ok_or(Error::EOL));
+        let col = self.peek().ok_or(Error::EOL)?;
         if col.is_empty() {
-            let _ = self.iter.next();
+            self.front += 1;
             f(self, false)
+        } else if self.options.is_some_and(|o| o.binary(self.front).is_some()) {
+            // invalid binary content is an error, not a missing value
+            f(self, true)
         } else {
             f(self, true).or_else(|_| f(self, false))
         }
@@ -212,8 +416,24 @@ impl<'a> serialize::Decoder for Columns<'a> {
 
     fn read_seq<T, F>(&mut self, f: F) -> Result<T>
             where F: FnOnce(&mut Columns<'a>, usize) -> Result<T> {
-        let len = self.iter.clone().count();
-        f(self, len)
+        let i = self.front;
+        match self.options.and_then(|o| o.binary(i)) {
+            // a binary column is a whole sequence of bytes
+            Some(encoding) if self.bytes.is_none() => {
+                let col = self.next_field().ok_or(Error::EOL)?;
+                let bytes = encoding.decode(&col).map_err(|e| Error::Decode(format!(
+                    "Cannot decode '{}' as {} in column {}: {}", col, encoding, i, e)))?;
+                let len = bytes.len();
+                self.bytes = Some((bytes, 0));
+                let result = f(self, len);
+                self.bytes = None;
+                result
+            },
+            _ => {
+                let len = self.len();
+                f(self, len)
+            },
+        }
     }
     fn read_seq_elt<T, F>(&mut self, _: usize, f: F) -> Result<T>
             where F: FnOnce(&mut Columns<'a>) -> Result<T> {
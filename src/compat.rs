@@ -0,0 +1,68 @@
+//! Conversions with the records of the `csv` crate
+//!
+//! Requires the `csv` feature. Rows convert into `csv::ByteRecord`s, or
+//! `csv::StringRecord`s if valid UTF-8, and back, so that both crates can
+//! be used side by side.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate csv;
+//! extern crate quick_csv;
+//!
+//! use std::convert::TryFrom;
+//! use quick_csv::{Csv, Row};
+//!
+//! # fn main() {
+//! let row = Csv::from_string("a,\"b,c\"").next().unwrap().unwrap();
+//! let record = csv::StringRecord::try_from(&row).unwrap();
+//! assert_eq!(&record[1], "b,c");
+//! assert_eq!(Row::from(&record), row);
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+
+use csv::{ByteRecord, StringRecord};
+
+use error::{Error, Result};
+use string_record;
+use Row;
+
+impl<'a> From<&'a Row> for ByteRecord {
+    fn from(row: &'a Row) -> ByteRecord {
+        row.bytes_columns().collect()
+    }
+}
+
+impl<'a> TryFrom<&'a Row> for StringRecord {
+    type Error = Error;
+
+    fn try_from(row: &'a Row) -> Result<StringRecord> {
+        Ok(row.columns()?.collect())
+    }
+}
+
+impl<'a> From<&'a ByteRecord> for Row {
+    fn from(record: &'a ByteRecord) -> Row {
+        Row::from_fields(record)
+    }
+}
+
+impl<'a> From<&'a StringRecord> for Row {
+    fn from(record: &'a StringRecord) -> Row {
+        Row::from_fields(record)
+    }
+}
+
+impl From<string_record::StringRecord> for StringRecord {
+    fn from(record: string_record::StringRecord) -> StringRecord {
+        record.into_vec().into()
+    }
+}
+
+impl From<StringRecord> for string_record::StringRecord {
+    fn from(record: StringRecord) -> string_record::StringRecord {
+        record.iter().collect()
+    }
+}
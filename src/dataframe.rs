@@ -0,0 +1,97 @@
+//! Polars DataFrame conversion
+//!
+//! Materializes the remaining rows into a `polars::frame::DataFrame`.
+//! Requires the `polars` feature.
+//!
+//! Column types are inferred from their values as with `Csv::coerce`, field
+//! hooks included: booleans (`true`/`false`), then integers, then floats,
+//! else strings. Integer columns with floats become float columns, and
+//! columns mixing types string columns. Empty values are nulls.
+//!
+//! # Example
+//!
+//! ```
+//! let mut csv = quick_csv::Csv::from_string("name,age\nbob,42\nalice,").has_header(true);
+//! let df = csv.to_dataframe().unwrap();
+//! assert_eq!(df.shape(), (2, 2));
+//! ```
+
+use std::io::BufRead;
+
+use polars::prelude::{Column, DataFrame, NamedFrom, Series};
+
+use coerce::{self, Counts, Type, Value};
+use error::{Error, Result};
+use Csv;
+
+/// Values of a column, along with their text
+#[derive(Default)]
+struct Values {
+    values: Vec<(String, Value)>,
+    counts: Counts,
+}
+
+impl Values {
+
+    fn push(&mut self, text: String, value: Value) {
+        self.counts.add(&value);
+        self.values.push((text, value));
+    }
+
+    fn series(self, name: &str) -> Series {
+        let name = name.into();
+        let values = self.values.into_iter();
+        match self.counts.common() {
+            Some(Type::Bool) => Series::new(name, values
+                .map(|(_, v)| if let Value::Bool(b) = v { Some(b) } else { None }).collect::<Vec<_>>()),
+            Some(Type::Int) => Series::new(name, values
+                .map(|(_, v)| if let Value::Int(i) = v { Some(i) } else { None }).collect::<Vec<_>>()),
+            Some(Type::Float) => Series::new(name, values.map(|(_, v)| match v {
+                Value::Int(i) => Some(i as f64),
+                Value::Float(f) => Some(f),
+                _ => None,
+            }).collect::<Vec<_>>()),
+            Some(Type::Str) | None => Series::new(name, values
+                .map(|(text, v)| if v == Value::Null { None } else { Some(text) }).collect::<Vec<_>>()),
+        }
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Reads all remaining rows into a DataFrame
+    ///
+    /// Columns are named after the headers, or `column_<i>` without headers.
+    /// Rows shorter than the widest one are padded with nulls.
+    pub fn to_dataframe(&mut self) -> Result<DataFrame> {
+        let headers = self.headers();
+        let mut columns: Vec<Values> = (0..headers.len()).map(|_| Values::default()).collect();
+        let mut height = 0;
+        for row in &mut *self {
+            let row = row?;
+            for (i, col) in row.columns()?.enumerate() {
+                if columns.len() <= i {
+                    let mut values = Values::default();
+                    for _ in 0..height {
+                        values.push(String::new(), Value::Null);
+                    }
+                    columns.push(values);
+                }
+                let col = coerce::hooked(&row, i, col);
+                columns[i].push(col.to_string(), coerce::infer(&row, &col));
+            }
+            height += 1;
+            for c in columns.iter_mut().filter(|c| c.values.len() < height) {
+                c.push(String::new(), Value::Null);
+            }
+        }
+
+        let columns = columns.into_iter().enumerate().map(|(i, values)| {
+            let name = headers.get(i).cloned().unwrap_or_else(|| format!("column_{}", i));
+            Column::from(values.series(&name))
+        }).collect();
+        DataFrame::new(height, columns).map_err(|e| Error::Decode(e.to_string()))
+    }
+
+}
@@ -0,0 +1,181 @@
+//! Date formats
+//!
+//! Parses dates and times laid out with a `strftime` like format, such as
+//! `%d/%m/%Y %H:%M`, without any dependency. Formats are registered per
+//! column with `Csv::date_format`: dates are then rewritten in ISO 8601
+//! before being decoded, and checked by `Csv::validate`.
+//!
+//! Supported specifiers:
+//! - `%Y`: year, 4 digits, `%y`: year, 2 digits (`70` to `99` are 19xx)
+//! - `%m`: month, `%b` or `%h`: month abbreviation, `%B`: month name
+//! - `%d` or `%e`: day of the month
+//! - `%H`: hour (0 to 23), `%I`: hour (1 to 12) with `%p`: `AM` or `PM`
+//! - `%M`: minute, `%S`: second
+//! - `%%`: a literal `%`
+//!
+//! Numbers have 1 or 2 digits (`%Y` has 4), names are case insensitive,
+//! other characters must match exactly.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::date;
+//!
+//! let date = date::parse("3 Feb 2024 5:07 pm", "%d %b %Y %I:%M %p").unwrap();
+//! assert_eq!(date.to_string(), "2024-02-03T17:07:00");
+//! assert_eq!(date::parse("31/04/2024", "%d/%m/%Y").unwrap_err(), "day 31 out of range");
+//! ```
+
+use std::fmt;
+
+const MONTHS: &[&str] = &["january", "february", "march", "april", "may", "june", "july",
+                          "august", "september", "october", "november", "december"];
+
+/// A date, a time or both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    /// year, month (1 to 12) and day (1 to 31), if the format has a date
+    pub date: Option<(i32, u32, u32)>,
+    /// hour (0 to 23), minute and second, if the format has a time
+    pub time: Option<(u32, u32, u32)>,
+}
+
+impl fmt::Display for DateTime {
+    /// Formats in ISO 8601: `2024-02-03`, `17:07:00` or `2024-02-03T17:07:00`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((year, month, day)) = self.date {
+            write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+            if self.time.is_some() {
+                write!(f, "T")?;
+            }
+        }
+        if let Some((hour, minute, second)) = self.time {
+            write!(f, "{:02}:{:02}:{:02}", hour, minute, second)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a number of `min` to `max` digits
+fn number(s: &mut &str, min: usize, max: usize, what: &str) -> Result<u32, String> {
+    let len = s.bytes().take(max).take_while(u8::is_ascii_digit).count();
+    if len < min {
+        return Err(format!("expected {} at '{}'", what, s));
+    }
+    let n = s[..len].parse().unwrap();
+    *s = &s[len..];
+    Ok(n)
+}
+
+/// Reads a month name, or its 3 letter abbreviation
+fn month_name(s: &mut &str, full: bool) -> Result<u32, String> {
+    for (i, name) in MONTHS.iter().enumerate() {
+        let name = if full { name } else { &name[..3] };
+        if s.get(..name.len()).is_some_and(|p| p.eq_ignore_ascii_case(name)) {
+            *s = &s[name.len()..];
+            return Ok(i as u32 + 1);
+        }
+    }
+    Err(format!("expected a month name at '{}'", s))
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses `value` laid out as `format`
+///
+/// Fails with a description of the problem if `value` does not match or is
+/// not a valid date, e.g. a 31st of April.
+pub fn parse(value: &str, format: &str) -> Result<DateTime, String> {
+    let (mut year, mut month, mut day) = (None, None, None);
+    let (mut hour, mut hour12, mut pm, mut minute, mut second) = (None, None, None, None, None);
+    let mut s = value;
+    let mut spec = format.chars();
+    while let Some(c) = spec.next() {
+        if c != '%' {
+            match s.strip_prefix(c) {
+                Some(rest) => s = rest,
+                None => return Err(format!("expected '{}' at '{}'", c, s)),
+            }
+            continue;
+        }
+        match spec.next() {
+            Some('Y') => year = Some(number(&mut s, 4, 4, "a year")? as i32),
+            Some('y') => year = Some(match number(&mut s, 2, 2, "a year")? as i32 {
+                y if y < 70 => 2000 + y,
+                y => 1900 + y,
+            }),
+            Some('m') => month = Some(number(&mut s, 1, 2, "a month")?),
+            Some('b') | Some('h') => month = Some(month_name(&mut s, false)?),
+            Some('B') => month = Some(month_name(&mut s, true)?),
+            Some('d') | Some('e') => day = Some(number(&mut s, 1, 2, "a day")?),
+            Some('H') => hour = Some(number(&mut s, 1, 2, "an hour")?),
+            Some('I') => hour12 = Some(number(&mut s, 1, 2, "an hour")?),
+            Some('M') => minute = Some(number(&mut s, 1, 2, "a minute")?),
+            Some('S') => second = Some(number(&mut s, 1, 2, "a second")?),
+            Some('p') => {
+                let p = s.get(..2).map(str::to_ascii_uppercase);
+                pm = match p.as_deref() {
+                    Some("AM") => Some(false),
+                    Some("PM") => Some(true),
+                    _ => return Err(format!("expected AM or PM at '{}'", s)),
+                };
+                s = &s[2..];
+            },
+            Some('%') => match s.strip_prefix('%') {
+                Some(rest) => s = rest,
+                None => return Err(format!("expected '%' at '{}'", s)),
+            },
+            Some(other) => return Err(format!("unsupported specifier '%{}'", other)),
+            None => return Err("format ends with '%'".to_string()),
+        }
+    }
+    if !s.is_empty() {
+        return Err(format!("unexpected '{}'", s));
+    }
+
+    let date = match (year, month, day) {
+        (None, None, None) => None,
+        (Some(year), Some(month), Some(day)) => {
+            if !(1..=12).contains(&month) {
+                return Err(format!("month {} out of range", month));
+            }
+            if day == 0 || day > days_in_month(year, month) {
+                return Err(format!("day {} out of range", day));
+            }
+            Some((year, month, day))
+        },
+        _ => return Err("incomplete date, a year, month and day are needed".to_string()),
+    };
+    let hour = match (hour, hour12, pm) {
+        (Some(h), None, None) => Some(h),
+        (None, Some(h), Some(pm)) if (1..=12).contains(&h) => Some(h % 12 + if pm { 12 } else { 0 }),
+        (None, Some(h), Some(_)) => return Err(format!("hour {} out of range", h)),
+        (None, None, None) => None,
+        _ => return Err("ambiguous hour, %I needs %p and %H excludes them".to_string()),
+    };
+    let time = match (hour, minute, second) {
+        (None, None, None) => None,
+        (Some(hour), minute, second) => {
+            let (minute, second) = (minute.unwrap_or(0), second.unwrap_or(0));
+            if hour > 23 {
+                return Err(format!("hour {} out of range", hour));
+            }
+            if minute > 59 || second > 59 {
+                return Err(format!("time {}:{}:{} out of range", hour, minute, second));
+            }
+            Some((hour, minute, second))
+        },
+        _ => return Err("incomplete time, an hour is needed".to_string()),
+    };
+    if date.is_none() && time.is_none() {
+        return Err("format has neither a date nor a time".to_string());
+    }
+    Ok(DateTime { date, time })
+}
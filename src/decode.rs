@@ -0,0 +1,277 @@
+//! Decoding options module
+//!
+//! Options shared by all rows of a `Csv`, applied when rows are decoded
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use binary::Encoding;
+use date;
+use error::{Error, Result};
+use headers::{self, Duplicates};
+
+/// Refers to a column either by position or by header name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// Column position, starting at 0
+    Index(usize),
+    /// Column header name
+    Name(String),
+}
+
+impl From<usize> for Column {
+    fn from(i: usize) -> Column {
+        Column::Index(i)
+    }
+}
+
+impl<'a> From<&'a str> for Column {
+    fn from(name: &'a str) -> Column {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Column {
+        Column::Name(name)
+    }
+}
+
+impl Column {
+    /// Gets the column position given the headers, compared ignoring case or not
+    pub fn index(&self, headers: &[String], ignore_case: bool) -> Option<usize> {
+        match *self {
+            Column::Index(i) => Some(i),
+            Column::Name(ref name) => headers.iter().position(|h| headers::matches(h, name, ignore_case)),
+        }
+    }
+
+    /// Gets the column positions given the headers, compared ignoring case or
+    /// not, and the duplicate policy
+    pub fn indices(&self, headers: &[String], duplicates: Duplicates, ignore_case: bool) -> Vec<usize> {
+        match *self {
+            Column::Index(i) => vec![i],
+            Column::Name(ref name) => duplicates.find(headers, name, ignore_case),
+        }
+    }
+}
+
+/// Defines how percentages such as `"12.5%"` are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Percent {
+    /// Decodes into a fraction: `0.125` (floats only)
+    Fraction,
+    /// Decodes into the number preceding `%`: `12.5`
+    Number,
+}
+
+/// A function transforming a column content before it is decoded
+pub type Hook = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Options applied by `Row::decode`
+#[derive(Clone, Default)]
+pub(crate) struct DecodeOptions {
+    /// hooks as defined by the user
+    hooks: Vec<(Column, Hook)>,
+    /// hooks by column position, once headers are known
+    resolved: Vec<Option<Hook>>,
+    /// struct fields bound to a column instead of their position
+    fields: Vec<(String, Column)>,
+    /// column positions of bound fields, once headers are known
+    resolved_fields: Vec<Option<usize>>,
+    /// date formats as defined by the user
+    date_formats: Vec<(Column, String)>,
+    /// date formats by column position, once headers are known
+    resolved_dates: Vec<Option<Arc<str>>>,
+    /// binary encodings as defined by the user
+    binary: Vec<(Column, Encoding)>,
+    /// binary encodings by column position, once headers are known
+    resolved_binary: Vec<Option<Encoding>>,
+    /// digit group separator ignored in numbers
+    pub thousands_separator: Option<char>,
+    /// decimal separator, if not '.'
+    pub decimal_separator: Option<char>,
+    /// currency symbols stripped from numbers
+    pub currency_symbols: Vec<String>,
+    /// how numbers ending with '%' are decoded
+    pub percent: Option<Percent>,
+}
+
+impl DecodeOptions {
+
+    /// Adds a new hook
+    pub fn add_hook(&mut self, column: Column, hook: Hook,
+                    headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.hooks.push((column, hook));
+        self.resolve(headers, duplicates, ignore_case);
+    }
+
+    /// Adds a date format, replacing any previous format of the column
+    pub fn add_date_format(&mut self, column: Column, format: &str,
+                           headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.date_formats.retain(|d| d.0 != column);
+        self.date_formats.push((column, format.to_string()));
+        self.resolve(headers, duplicates, ignore_case);
+    }
+
+    /// Adds a binary encoding, replacing any previous encoding of the column
+    pub fn add_binary(&mut self, column: Column, encoding: Encoding,
+                      headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.binary.retain(|b| b.0 != column);
+        self.binary.push((column, encoding));
+        self.resolve(headers, duplicates, ignore_case);
+    }
+
+    /// Binds a struct field to a column
+    pub fn bind_field(&mut self, field: &str, column: Column,
+                      headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.fields.retain(|f| f.0 != field);
+        self.fields.push((field.to_string(), column));
+        self.resolve(headers, duplicates, ignore_case);
+    }
+
+    /// Gets the column of a bound struct field, if bound
+    pub fn field_column(&self, field: &str) -> Option<Result<usize>> {
+        let i = self.fields.iter().position(|f| f.0 == field)?;
+        Some(self.resolved_fields[i].ok_or_else(|| match self.fields[i].1 {
+            Column::Name(ref name) => Error::UnknownColumn(name.clone()),
+            Column::Index(i) => Error::UnknownColumn(i.to_string()),
+        }))
+    }
+
+    /// Finds the column position of all hooks and bound fields
+    pub fn resolve(&mut self, headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.resolved_fields = self.fields.iter()
+            .map(|f| f.1.indices(headers, duplicates, ignore_case).first().cloned())
+            .collect();
+        self.resolved.clear();
+        for (column, hook) in &self.hooks {
+            for i in column.indices(headers, duplicates, ignore_case) {
+                if self.resolved.len() <= i {
+                    self.resolved.resize(i + 1, None);
+                }
+                self.resolved[i] = Some(hook.clone());
+            }
+        }
+        self.resolved_dates.clear();
+        for (column, format) in &self.date_formats {
+            for i in column.indices(headers, duplicates, ignore_case) {
+                if self.resolved_dates.len() <= i {
+                    self.resolved_dates.resize(i + 1, None);
+                }
+                self.resolved_dates[i] = Some(Arc::from(&**format));
+            }
+        }
+        self.resolved_binary.clear();
+        for &(ref column, encoding) in &self.binary {
+            for i in column.indices(headers, duplicates, ignore_case) {
+                if self.resolved_binary.len() <= i {
+                    self.resolved_binary.resize(i + 1, None);
+                }
+                self.resolved_binary[i] = Some(encoding);
+            }
+        }
+    }
+
+    /// Whether a date format is defined for some column
+    pub fn has_date_formats(&self) -> bool {
+        !self.date_formats.is_empty()
+    }
+
+    /// Gets the date format of the `i`th column, if any
+    pub fn date_format(&self, i: usize) -> Option<&str> {
+        self.resolved_dates.get(i).and_then(|f| f.as_deref())
+    }
+
+    /// Gets the binary encoding of the `i`th column, if any
+    pub fn binary(&self, i: usize) -> Option<Encoding> {
+        self.resolved_binary.get(i).cloned().unwrap_or(None)
+    }
+
+    /// Rewrites the `i`th column in ISO 8601 if it has a date format
+    ///
+    /// Empty columns are kept empty.
+    pub fn date<'a>(&self, i: usize, col: Cow<'a, str>) -> Result<Cow<'a, str>> {
+        match self.date_format(i) {
+            Some(format) if !col.is_empty() => date::parse(&col, format)
+                .map(|d| Cow::Owned(d.to_string()))
+                .map_err(|e| Error::Decode(format!(
+                    "Cannot parse '{}' with date format '{}' in column {}: {}", col, format, i, e))),
+            _ => Ok(col),
+        }
+    }
+
+    /// Transforms the `i`th column before it is decoded
+    pub fn apply<'a>(&self, i: usize, col: &'a str) -> Cow<'a, str> {
+        match self.resolved.get(i) {
+            Some(Some(hook)) => Cow::Owned(hook(col)),
+            _ => Cow::Borrowed(col),
+        }
+    }
+
+    /// Normalizes a column decoded as a number
+    pub fn number<'a>(&self, mut col: Cow<'a, str>) -> Cow<'a, str> {
+        if !self.currency_symbols.is_empty() {
+            col = self.strip_currency(col);
+        }
+        if let Some(sep) = self.thousands_separator {
+            if col.contains(sep) {
+                col = Cow::Owned(col.replace(sep, ""));
+            }
+        }
+        if let Some(sep) = self.decimal_separator {
+            if col.contains(sep) {
+                col = Cow::Owned(col.replace(sep, "."));
+            }
+        }
+        if let Some(percent) = self.percent {
+            let value = col.trim_end().strip_suffix('%').map(|value| match percent {
+                Percent::Number => value.trim_end().to_string(),
+                Percent::Fraction => divide_by_100(value.trim_end()),
+            });
+            if let Some(value) = value {
+                col = Cow::Owned(value);
+            }
+        }
+        col
+    }
+
+    /// Removes a currency symbol placed before or after the number,
+    /// e.g. `"$ 12"`, `"-$12"` or `"12 €"`
+    fn strip_currency<'a>(&self, col: Cow<'a, str>) -> Cow<'a, str> {
+        let trimmed = col.trim();
+        let (sign, value) = match trimmed.chars().next() {
+            Some(c) if c == '-' || c == '+' => (&trimmed[..1], trimmed[1..].trim_start()),
+            _ => ("", trimmed),
+        };
+        for symbol in &self.currency_symbols {
+            let stripped = if value.starts_with(&**symbol) {
+                &value[symbol.len()..]
+            } else if value.ends_with(&**symbol) {
+                &value[..value.len() - symbol.len()]
+            } else {
+                continue;
+            };
+            return Cow::Owned(format!("{}{}", sign, stripped.trim()));
+        }
+        col
+    }
+
+}
+
+/// Moves the decimal point 2 digits to the left: "12.5" -> "0.125"
+fn divide_by_100(value: &str) -> String {
+    let (sign, value) = match value.chars().next() {
+        Some(c) if c == '-' || c == '+' => value.split_at(1),
+        _ => ("", value),
+    };
+    let (int, frac) = match value.find('.') {
+        Some(i) => (&value[..i], &value[i + 1..]),
+        None => (value, ""),
+    };
+    if int.len() > 2 {
+        format!("{}{}.{}{}", sign, &int[..int.len() - 2], &int[int.len() - 2..], frac)
+    } else {
+        format!("{}0.{:0>2}{}", sign, int, frac)
+    }
+}
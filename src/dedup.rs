@@ -0,0 +1,204 @@
+//! Approximate deduplication
+//!
+//! Skips rows already seen, remembered in a Bloom filter: memory is fixed
+//! whatever the number of rows, at the cost of a few unique rows being
+//! wrongly taken for duplicates, at the chosen false positive rate.
+//! Duplicates are never kept.
+//!
+//! The filter is sized for the expected number of distinct rows, its false
+//! positive rate growing beyond. Its size counts against the
+//! `Csv::memory_budget`.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//!
+//! let csv = Csv::from_string("id,name\n1,bob\n2,alice\n1,bob\n1,carol").has_header(true);
+//! let mut rows = csv.dedup(1_000_000, 0.001).key(&["id"]);
+//! assert_eq!(rows.by_ref().map(|r| r.unwrap().to_string()).collect::<Vec<_>>(), vec!["1,bob", "2,alice"]);
+//! assert_eq!(rows.duplicates(), 2);
+//! ```
+
+use std::f64::consts::LN_2;
+use std::io::BufRead;
+
+use error::Result;
+use hash::Fnv64;
+use transform::column_index;
+use {Csv, Row};
+
+/// Bloom filter of 64-bit hashes
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+
+    /// Creates a filter for `items` items with a `false_positive_rate`
+    /// probability of taking a new item for an inserted one
+    ///
+    /// Uses about `-items * ln(rate) / ln(2)^2` bits, i.e. 1.2 MB per
+    /// million items at 1%.
+    pub fn new(items: usize, false_positive_rate: f64) -> BloomFilter {
+        let words = BloomFilter::words(items, false_positive_rate);
+        let hashes = (words as f64 * 64. / items.max(1) as f64 * LN_2).round().clamp(1., 32.) as u32;
+        BloomFilter { bits: vec![0; words], hashes }
+    }
+
+    /// Gets the size of a filter created with `new`, in bytes, without
+    /// allocating it
+    pub fn size_for(items: usize, false_positive_rate: f64) -> usize {
+        BloomFilter::words(items, false_positive_rate).saturating_mul(8)
+    }
+
+    /// Gets the number of 64 bit words of a filter
+    fn words(items: usize, false_positive_rate: f64) -> usize {
+        let rate = false_positive_rate.clamp(1e-12, 0.5);
+        let bits = (-(items.max(1) as f64) * rate.ln() / (LN_2 * LN_2)).ceil().max(64.);
+        (bits / 64.).ceil() as usize
+    }
+
+    /// Gets the size of the filter, in bytes
+    pub fn size(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Gets the bits set for `hash`, by double hashing
+    fn positions(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let len = self.bits.len() as u64 * 64;
+        let h2 = mix(hash) | 1;
+        (0..u64::from(self.hashes)).map(move |i| (hash.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    /// Whether `hash` may have been inserted
+    pub fn contains(&self, hash: u64) -> bool {
+        self.positions(hash).all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+
+    /// Inserts `hash`, returning false if it may have been inserted already
+    pub fn insert(&mut self, hash: u64) -> bool {
+        let mut new = false;
+        for p in self.positions(hash) {
+            let bit = 1 << (p % 64);
+            new |= self.bits[p / 64] & bit == 0;
+            self.bits[p / 64] |= bit;
+        }
+        new
+    }
+
+}
+
+/// Scrambles a hash into another, the splitmix64 finalizer
+fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Iterator over rows not seen yet
+///
+/// Created with `Csv::dedup`.
+pub struct Dedup<B: BufRead> {
+    csv: Csv<B>,
+    items: usize,
+    false_positive_rate: f64,
+    /// created on the first iteration, within the memory budget
+    filter: Option<BloomFilter>,
+    key: Option<Vec<String>>,
+    columns: Vec<usize>,
+    duplicates: usize,
+    done: bool,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining rows, skipping those already seen
+    ///
+    /// Rows are compared on all their columns, see `Row::hash_fields`.
+    pub fn dedup(self, items: usize, false_positive_rate: f64) -> Dedup<B> {
+        Dedup {
+            csv: self,
+            items,
+            false_positive_rate,
+            filter: None,
+            key: None,
+            columns: Vec::new(),
+            duplicates: 0,
+            done: false,
+        }
+    }
+
+}
+
+impl<B: BufRead> Dedup<B> {
+
+    /// Compares rows on the columns named `key` only
+    ///
+    /// The first iteration fails with `Error::UnknownColumn` if a column
+    /// has no header.
+    ///
+    /// Note: default = all columns
+    pub fn key(mut self, key: &[&str]) -> Dedup<B> {
+        self.key = Some(key.iter().map(|k| k.to_string()).collect());
+        self
+    }
+
+    /// Gets the number of rows skipped so far
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// Resolves the key columns and allocates the filter
+    fn init(&mut self) -> Result<BloomFilter> {
+        if let Some(ref key) = self.key {
+            let csv = &mut self.csv;
+            self.columns = key.iter().map(|k| column_index(csv, k)).collect::<Result<_>>()?;
+        }
+        self.csv.budget().charge(BloomFilter::size_for(self.items, self.false_positive_rate))?;
+        Ok(BloomFilter::new(self.items, self.false_positive_rate))
+    }
+
+    fn hash(&self, row: &Row) -> u64 {
+        let mut hasher = Fnv64::new();
+        if self.key.is_some() {
+            row.hash_key_fields(&self.columns, &mut hasher);
+        } else {
+            row.hash_fields(&mut hasher);
+        }
+        ::std::hash::Hasher::finish(&hasher)
+    }
+
+}
+
+impl<B: BufRead> Iterator for Dedup<B> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        if self.done {
+            return None;
+        }
+        if self.filter.is_none() {
+            match self.init() {
+                Ok(filter) => self.filter = Some(filter),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        loop {
+            let row = match self.csv.next()? {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e)),
+            };
+            let hash = self.hash(&row);
+            if self.filter.as_mut().unwrap().insert(hash) {
+                return Some(Ok(row));
+            }
+            self.duplicates += 1;
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Structured diagnostics
+//!
+//! A `Diagnostic` describes a problem with its severity, location and a
+//! possible fix, for tools rendering their own reports, e.g. editor plugins
+//! or CI checks. They are made out of validation issues with
+//! `Report::diagnostics` and out of reading errors with `Csv::diagnose`.
+//! With the `serde_json` feature, `Diagnostic::to_json` serializes them.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::diagnostic::Severity;
+//!
+//! let mut csv = Csv::from_string("a,b\nc,d,e");
+//! let error = csv.by_ref().filter_map(Result::err).next().unwrap();
+//! let diagnostic = csv.diagnose(&error);
+//! assert_eq!(diagnostic.severity, Severity::Error);
+//! assert_eq!((diagnostic.record, diagnostic.span), (Some(2), Some((4, 9))));
+//! assert!(diagnostic.to_string().starts_with("error: Expectiong 2 columns, found 3 (record 2)"));
+//! ```
+
+use std::fmt;
+use std::io::BufRead;
+
+#[cfg(feature = "serde_json")]
+use serde_json::{Map, Value};
+
+use error::Error;
+use validate::{Issue, IssueKind, Report};
+use Csv;
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A problem worth a look, which does not prevent reading
+    Warning,
+    /// A problem failing reading
+    Error,
+}
+
+impl Severity {
+
+    /// Gets the lowercase name of the severity
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+}
+
+/// A located problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// how bad the problem is
+    pub severity: Severity,
+    /// description of the problem
+    pub message: String,
+    /// record number, starting at 1 and including the header
+    pub record: Option<usize>,
+    /// position of the faulty column
+    pub field: Option<usize>,
+    /// byte range in the source, empty at the start if the end is unknown
+    pub span: Option<(u64, u64)>,
+    /// how the problem could be fixed
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+
+    /// Creates an error diagnostic without location
+    pub fn error<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            record: None,
+            field: None,
+            span: None,
+            suggestion: None,
+        }
+    }
+
+    /// Creates a warning diagnostic without location
+    pub fn warning<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, ..Diagnostic::error(message) }
+    }
+
+    /// Sets the record number
+    pub fn record(mut self, record: usize) -> Diagnostic {
+        self.record = Some(record);
+        self
+    }
+
+    /// Sets the column position
+    pub fn field(mut self, field: usize) -> Diagnostic {
+        self.field = Some(field);
+        self
+    }
+
+    /// Sets the byte range in the source
+    pub fn span(mut self, start: u64, end: u64) -> Diagnostic {
+        self.span = Some((start, end));
+        self
+    }
+
+    /// Sets the suggested fix
+    pub fn suggestion<S: Into<String>>(mut self, suggestion: S) -> Diagnostic {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// Converts into a JSON object
+    ///
+    /// Keys are those of the fields, missing locations being `null` and
+    /// spans `{"start": .., "end": ..}` objects.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("severity".to_string(), Value::from(self.severity.as_str()));
+        object.insert("message".to_string(), Value::from(&*self.message));
+        object.insert("record".to_string(), self.record.map_or(Value::Null, Value::from));
+        object.insert("field".to_string(), self.field.map_or(Value::Null, Value::from));
+        let span = self.span.map_or(Value::Null, |(start, end)| {
+            let mut span = Map::new();
+            span.insert("start".to_string(), Value::from(start));
+            span.insert("end".to_string(), Value::from(end));
+            Value::Object(span)
+        });
+        object.insert("span".to_string(), span);
+        object.insert("suggestion".to_string(), self.suggestion.as_ref().map_or(Value::Null, |s| Value::from(&**s)));
+        Value::Object(object)
+    }
+
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity.as_str(), self.message)?;
+        match (self.record, self.field) {
+            (Some(record), Some(field)) => write!(f, " (record {}, field {})", record, field)?,
+            (Some(record), None) => write!(f, " (record {})", record)?,
+            (None, Some(field)) => write!(f, " (field {})", field)?,
+            (None, None) => (),
+        }
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, "\n  help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Issue> for Diagnostic {
+    fn from(issue: &Issue) -> Diagnostic {
+        let (message, suggestion) = match issue.kind {
+            IssueKind::UnexpectedQuote => ("Quote within a non quoted column".to_string(),
+                                           "quote the whole column and double its quotes"),
+            IssueKind::UnescapedQuote => ("Unescaped quote within a quoted column".to_string(),
+                                          "double quotes within quoted columns"),
+            IssueKind::UnclosedQuote => ("Quoted column not closed at the end of the file".to_string(),
+                                         "add the missing closing quote"),
+            IssueKind::ColumnCount { expected, found } => (
+                format!("Expected {} columns, found {}", expected, found),
+                if found < expected {
+                    "add the missing columns or read with `ColumnPolicy::Pad`"
+                } else {
+                    "quote columns containing the delimiter or read with `ColumnPolicy::Truncate`"
+                }),
+            IssueKind::InvalidUtf8 { .. } => ("Column is not valid UTF-8".to_string(),
+                                              "convert the file to UTF-8"),
+            IssueKind::InvalidDate { .. } => ("Column does not match its date format".to_string(),
+                                              "fix the value or its `Csv::date_format`"),
+        };
+        let diagnostic = Diagnostic::error(message)
+            .record(issue.record)
+            .span(issue.offset, issue.offset)
+            .suggestion(suggestion);
+        match issue.kind {
+            IssueKind::InvalidUtf8 { column } | IssueKind::InvalidDate { column } => diagnostic.field(column),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl Report {
+
+    /// Gets the diagnostics of all issues, in file order
+    ///
+    /// Spans are empty, at the start of the faulty records.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.issues.iter().map(Diagnostic::from).collect()
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Describes an error returned while reading, or decoding the last row
+    ///
+    /// Errors of the record being read are located at that record, others,
+    /// e.g. decoding errors, at the last row read. Spans cover the record,
+    /// up to where its reading failed.
+    pub fn diagnose(&self, error: &Error) -> Diagnostic {
+        let diagnostic = Diagnostic::error(error.to_string());
+        let record = match *error {
+            Error::Io(_) | Error::Cancelled => return diagnostic,
+            Error::EOL | Error::UnescapedQuote | Error::UnexpextedQuote | Error::ColumnMismatch(..)
+                | Error::FieldTooLarge(_) | Error::RecordTooLarge(_) | Error::Parse(_) => self.current_line + 1,
+            _ => self.current_line,
+        };
+        let diagnostic = diagnostic.record(record).span(self.record_offset, self.position);
+        let suggestion = match *error {
+            Error::UnescapedQuote => "double quotes within quoted columns",
+            Error::UnexpextedQuote => "quote the whole column and double its quotes",
+            Error::ColumnMismatch(..) => "read with a flexible `ColumnPolicy` or fix the column count",
+            Error::FieldTooLarge(_) => "raise the limit with `Csv::max_field_size`",
+            Error::RecordTooLarge(_) => "raise the limit with `Csv::max_record_size`, or close its quotes",
+            _ => return diagnostic,
+        };
+        diagnostic.suggestion(suggestion)
+    }
+
+}
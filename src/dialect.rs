@@ -0,0 +1,299 @@
+//! Csv dialects
+//!
+//! A `Dialect` gathers the options defining a csv format: delimiter,
+//! quoting, escaping, terminator and NULL token. Set at once with
+//! `Csv::dialect` and `Writer::dialect`, the same dialect makes a writer
+//! produce what a reader parses back identically. Presets match the files
+//! of common producers, options set afterwards override those of the
+//! dialect.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::dialect::Dialect;
+//! use quick_csv::writer::Writer;
+//!
+//! let mut csv = Csv::from_string("sep=;\r\nname;price\r\npen;1,5\r\n")
+//!     .dialect(Dialect::Excel)
+//!     .has_header(true);
+//! assert_eq!(csv.headers(), vec!["name".to_string(), "price".to_string()]);
+//! let row = csv.next().unwrap().unwrap();
+//! assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["pen", "1,5"]);
+//!
+//! let dialect = Dialect { delimiter: b'|', quote: b'\'', null: Some("NULL"), ..Dialect::Rfc4180 };
+//! let mut writer = Writer::from_writer(Vec::new()).dialect(dialect);
+//! writer.write_nullable([Some("it's"), None, Some("NULL")]).unwrap();
+//! let written = writer.into_inner().unwrap();
+//! assert_eq!(written, b"'it''s'|NULL|'NULL'\r\n");
+//! let row = Csv::from_reader(&written[..]).dialect(dialect).next().unwrap().unwrap();
+//! assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["it's", "", "NULL"]);
+//! assert!(row.is_null(1) && !row.is_null(2));
+//! ```
+
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use writer::Escape;
+use {Csv, Terminator};
+
+/// Options shared by readers and writers
+///
+/// Presets are associated constants named after their producer, e.g.
+/// `Dialect::Excel`, used as is or customized with the struct update
+/// syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    /// Column delimiter
+    pub delimiter: u8,
+    /// Byte surrounding quoted columns
+    pub quote: u8,
+    /// How quotes are escaped within quoted columns
+    pub escape: Escape,
+    /// Backslash escapes used instead of quotes
+    pub backslash: Backslash,
+    /// Line ending of records
+    pub terminator: Terminator,
+    /// Column standing for NULL, as written unquoted
+    ///
+    /// `None` is `\N` with backslash escapes, no NULL otherwise. Writers
+    /// quote values equal to the token. Backslash escapes never quote, only
+    /// tokens starting with a backslash are then told from strings.
+    pub null: Option<&'static str>,
+    /// Writers quote every column, needed or not
+    pub quote_all: bool,
+    /// Writers start with a UTF-8 BOM, which readers always skip
+    pub bom: bool,
+    /// Readers consume a leading `sep=X` line setting the delimiter and
+    /// writers start with one
+    pub sep_line: bool,
+    /// Readers without a `sep=` line take `;` as delimiter if the first line
+    /// has more `;` than `,`
+    pub guess_delimiter: bool,
+}
+
+#[allow(non_upper_case_globals)]
+impl Dialect {
+    /// RFC 4180: `,` delimiter, quotes doubled, rows ending with `\r\n`
+    ///
+    /// Readers accept `\n` too.
+    pub const Rfc4180: Dialect = Dialect {
+        delimiter: b',',
+        quote: b'"',
+        escape: Escape::Double,
+        backslash: Backslash::Plain,
+        terminator: Terminator::CrLf,
+        null: None,
+        quote_all: false,
+        bom: false,
+        sep_line: false,
+        guess_delimiter: false,
+    };
+
+    /// Files saved or opened by Microsoft Excel
+    ///
+    /// Reading consumes a leading `sep=X` line with `Csv::detect_sep_line`,
+    /// or else takes `;` as delimiter if the first line has more `;`
+    /// than `,`, as saved in locales with a decimal comma. `\r\n` and a
+    /// UTF-8 BOM are handled as by any reader.
+    ///
+    /// Writing starts with a UTF-8 BOM, for Excel to detect the encoding,
+    /// and a `sep=` line, for it to split columns whatever the locale. Rows
+    /// end with `\r\n`.
+    pub const Excel: Dialect = Dialect { bom: true, sep_line: true, guess_delimiter: true, ..Dialect::Rfc4180 };
+
+    /// `,` delimiter, every column quoted, quotes doubled, rows ending
+    /// with `\n`, as Python's `unix_dialect`
+    pub const Unix: Dialect = Dialect { terminator: Terminator::Lf, quote_all: true, ..Dialect::Rfc4180 };
+
+    /// MySQL `SELECT ... INTO OUTFILE` and `LOAD DATA INFILE` defaults:
+    /// tab delimiter, backslash escapes and `\N` for NULL instead of
+    /// quotes, rows ending with `\n`
+    pub const MySql: Dialect = Dialect {
+        delimiter: b'\t',
+        backslash: Backslash::MySql,
+        terminator: Terminator::Lf,
+        ..Dialect::Rfc4180
+    };
+
+    /// PostgreSQL `COPY ... WITH (FORMAT csv)`: `,` delimiter, quotes
+    /// doubled, rows ending with `\n`, unquoted empty columns being NULL
+    pub const Postgres: Dialect = Dialect { terminator: Terminator::Lf, null: Some(""), ..Dialect::Rfc4180 };
+
+    /// PostgreSQL `COPY` text format, its default: tab delimiter, backslash
+    /// escapes and `\N` for NULL instead of quotes, rows ending with `\n`
+    pub const PostgresText: Dialect = Dialect {
+        delimiter: b'\t',
+        backslash: Backslash::Postgres,
+        terminator: Terminator::Lf,
+        ..Dialect::Rfc4180
+    };
+}
+
+/// Conventions of columns escaped with backslashes instead of quoted
+///
+/// With both, a backslash escapes the next byte, standing for itself unless
+/// listed below, e.g. an escaped delimiter or line ending, and a `\N`
+/// column is NULL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backslash {
+    /// Backslashes are plain bytes, columns being quoted as in RFC 4180
+    Plain,
+    /// MySQL `LOAD DATA` and `SELECT ... INTO OUTFILE` with `FIELDS
+    /// ESCAPED BY '\\'`: `\0`, `\b`, `\n`, `\r`, `\t` and `\Z` are NUL,
+    /// backspace, LF, CR, tab and Ctrl-Z
+    MySql,
+    /// PostgreSQL `COPY` text format: `\b`, `\f`, `\n`, `\r`, `\t` and `\v`
+    /// are backspace, form feed, LF, CR, tab and vertical tab, `\` followed
+    /// by 1 to 3 octal digits or by `x` and 1 or 2 hex digits is a byte
+    ///
+    /// The `\.` end of data marker of `COPY FROM STDIN` is not recognized.
+    Postgres,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Sets all the options of a dialect
+    ///
+    /// Must be called before `has_header`, some dialects inspecting or
+    /// consuming the start of the source.
+    pub fn dialect(mut self, dialect: Dialect) -> Csv<B> {
+        self.delimiter = dialect.delimiter;
+        self.quote = dialect.quote;
+        self.escape = dialect.escape;
+        self.backslash = dialect.backslash;
+        self.terminator = dialect.terminator;
+        self.null = dialect.null.map(|n| n.as_bytes().to_vec());
+        let position = self.position;
+        if dialect.sep_line {
+            self = self.detect_sep_line();
+        }
+        if dialect.guess_delimiter && self.position == position {
+            if let Ok(data) = self.reader.fill_buf() {
+                self.delimiter = guess_delimiter(data);
+            }
+        }
+        self
+    }
+
+    /// Rewrites the record at `base` as if read from a RFC 4180 csv, for
+    /// other quotes, backslash escapes and NULL tokens
+    ///
+    /// Columns are stored quoted with `"` if quoted or when needed, unless raw, and the
+    /// positions of NULL columns are kept in `nulls`.
+    pub(crate) fn normalize(&mut self, buf: &mut Vec<u8>, cols: &mut [usize], base: usize) {
+        let null = match self.null {
+            Some(ref null) => Some(&null[..]),
+            None if self.backslash != Backslash::Plain => Some(&b"\\N"[..]),
+            None => None,
+        };
+        let record = buf.split_off(base);
+        let mut start = 0;
+        for (i, end) in cols.iter_mut().enumerate() {
+            let col = &record[start..*end];
+            start = *end + 1;
+            if i > 0 {
+                buf.push(self.delimiter);
+            }
+            if null == Some(col) {
+                self.nulls.push(i);
+            } else if self.raw {
+                buf.extend_from_slice(col);
+            } else {
+                let quoted = self.backslash == Backslash::Plain && col.len() > 1 && col[0] == self.quote;
+                let value = if self.backslash != Backslash::Plain {
+                    Cow::Owned(unescape_column(col, self.backslash))
+                } else if quoted {
+                    Cow::Borrowed(&col[1..col.len() - 1])
+                } else {
+                    Cow::Borrowed(col)
+                };
+                let delimiter = self.delimiter;
+                // quoted columns stay quoted, see `Row::is_quoted`
+                if quoted || value.iter().any(|&b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r') {
+                    buf.push(b'"');
+                    buf.extend_from_slice(&value);
+                    buf.push(b'"');
+                } else {
+                    buf.extend_from_slice(&value);
+                }
+            }
+            *end = buf.len() - base;
+        }
+    }
+
+}
+
+/// Parses a `sep=X` line, returning the delimiter and the line length
+pub(crate) fn sep_line(data: &[u8]) -> Option<(u8, usize)> {
+    let delimiter = *data.strip_prefix(b"sep=")?.first()?;
+    match &data[5..] {
+        [b'\r', b'\n', ..] => Some((delimiter, 7)),
+        [b'\n', ..] => Some((delimiter, 6)),
+        [] => Some((delimiter, 5)),
+        _ => None,
+    }
+}
+
+/// Decodes the backslash escapes of a column
+pub(crate) fn unescape_column(col: &[u8], backslash: Backslash) -> Vec<u8> {
+    let mut value = Vec::with_capacity(col.len());
+    let mut i = 0;
+    while i < col.len() {
+        let b = col[i];
+        i += 1;
+        if b != b'\\' {
+            value.push(b);
+            continue;
+        }
+        let escaped = match col.get(i) {
+            Some(&escaped) => escaped,
+            // a trailing backslash escapes nothing
+            None => {
+                value.push(b);
+                break;
+            },
+        };
+        i += 1;
+        value.push(match (backslash, escaped) {
+            (_, b'b') => 8,
+            (_, b'n') => b'\n',
+            (_, b'r') => b'\r',
+            (_, b't') => b'\t',
+            (Backslash::MySql, b'0') => 0,
+            (Backslash::MySql, b'Z') => 26,
+            (Backslash::Postgres, b'f') => 12,
+            (Backslash::Postgres, b'v') => 11,
+            (Backslash::Postgres, b'0'..=b'7') => {
+                let digits = col[i..].iter().take(2).take_while(|d| (b'0'..=b'7').contains(d)).count();
+                let octal = col[i - 1..i + digits].iter().fold(0u32, |n, d| n * 8 + u32::from(d - b'0'));
+                i += digits;
+                octal as u8
+            },
+            (Backslash::Postgres, b'x') if col.get(i).is_some_and(u8::is_ascii_hexdigit) => {
+                let digits = col[i..].iter().take(2).take_while(|d| d.is_ascii_hexdigit()).count();
+                let hex = ::std::str::from_utf8(&col[i..i + digits]).unwrap();
+                i += digits;
+                u8::from_str_radix(hex, 16).unwrap()
+            },
+            _ => escaped,
+        });
+    }
+    value
+}
+
+/// Guesses between `,` and `;` from the first line
+fn guess_delimiter(data: &[u8]) -> u8 {
+    let (mut commas, mut semicolons) = (0, 0);
+    let mut quoted = false;
+    for &b in data {
+        match b {
+            b'\n' if !quoted => break,
+            b'"' => quoted = !quoted,
+            b',' if !quoted => commas += 1,
+            b';' if !quoted => semicolons += 1,
+            _ => (),
+        }
+    }
+    if semicolons > commas { b';' } else { b',' }
+}
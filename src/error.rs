@@ -23,6 +23,22 @@ pub enum Error {
     UnexpextedQuote,
     /// Column count mismatch
     ColumnMismatch(usize, usize),
+    /// Invalid or outdated index file
+    Index(String),
+    /// Header name found more than once
+    DuplicateHeader(String),
+    /// Column bigger than the maximum column size
+    FieldTooLarge(usize),
+    /// Row bigger than the maximum row size
+    RecordTooLarge(usize),
+    /// No header has this column name
+    UnknownColumn(String),
+    /// Header matching no struct field, in strict mode
+    UnexpectedColumn(String),
+    /// Reading was cancelled
+    Cancelled,
+    /// Buffered data bigger than the memory budget
+    MemoryBudget(usize),
 }
 
 /// Result type
@@ -38,6 +54,14 @@ impl fmt::Display for Error {
             Error::UnescapedQuote => write!(f, "A CSV column has an unescaped quote"),
             Error::UnexpextedQuote => write!(f, "A CSV column has a quote but the entire column value is not quoted"),
             Error::ColumnMismatch(exp, cur) => write!(f, "Expectiong {} columns, found {}", exp, cur),
+            Error::Index(ref msg) => write!(f, "CSV index error: {}", msg),
+            Error::DuplicateHeader(ref name) => write!(f, "Duplicate header '{}'", name),
+            Error::FieldTooLarge(max) => write!(f, "A CSV column exceeds {} bytes", max),
+            Error::RecordTooLarge(max) => write!(f, "A CSV row exceeds {} bytes", max),
+            Error::UnknownColumn(ref name) => write!(f, "Unknown column '{}'", name),
+            Error::UnexpectedColumn(ref name) => write!(f, "Column '{}' matches no field", name),
+            Error::Cancelled => write!(f, "CSV reading was cancelled"),
+            Error::MemoryBudget(max) => write!(f, "CSV buffered data exceeds the memory budget of {} bytes", max),
         }
     }
 }
@@ -52,10 +76,18 @@ impl ::std::error::Error for Error {
             Error::UnescapedQuote => "A CSV column has an unescaped quote",
             Error::UnexpextedQuote => "A CSV column has a quote but the entire column value is not quoted",
             Error::ColumnMismatch(..) => "Current column count mismatch with previous rows",
+            Error::Index(..) => "Invalid or outdated CSV index",
+            Error::DuplicateHeader(..) => "Header name found more than once",
+            Error::FieldTooLarge(..) => "A CSV column exceeds the maximum column size",
+            Error::RecordTooLarge(..) => "A CSV row exceeds the maximum row size",
+            Error::UnknownColumn(..) => "No header has this column name",
+            Error::UnexpectedColumn(..) => "A column matches no field",
+            Error::Cancelled => "CSV reading was cancelled",
+            Error::MemoryBudget(..) => "CSV buffered data exceeds the memory budget",
         }
     }
 
-    fn cause(&self) -> Option<&::std::error::Error> {
+    fn cause(&self) -> Option<&dyn (::std::error::Error)> {
         match *self {
             Error::Io(ref err) => Some(err),
             _ => None,
@@ -0,0 +1,101 @@
+//! Regex row filtering
+//!
+//! Keeps or drops rows as they are read depending on whether a column
+//! matches a regex. Requires the `regex` feature.
+//!
+//! Regexes are matched on the borrowed column bytes, filtered rows are
+//! neither allocated nor returned.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate quick_csv;
+//! extern crate regex;
+//!
+//! use quick_csv::filter::Filter;
+//! use regex::bytes::Regex;
+//!
+//! # fn main() {
+//! let csv = quick_csv::Csv::from_string("level,msg\nINFO,start\nERROR,boom\nWARN,slow")
+//!     .has_header(true)
+//!     .filter_regex("level", Regex::new("^(ERROR|WARN)$").unwrap(), Filter::Keep);
+//! let rows = csv.map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>();
+//! assert_eq!(rows, vec!["ERROR,boom", "WARN,slow"]);
+//! # }
+//! ```
+
+use std::io::BufRead;
+
+use regex::bytes::Regex;
+
+use columns::BytesColumns;
+use decode::Column;
+use headers::Duplicates;
+use Csv;
+
+/// Defines what happens to rows matching a filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Only matching rows are kept
+    Keep,
+    /// Matching rows are dropped
+    Drop,
+}
+
+/// A regex matched on one or all columns
+#[derive(Debug, Clone)]
+pub(crate) struct RegexFilter {
+    /// matched column, any column if `None`
+    column: Option<Column>,
+    /// positions of the matched column, once headers are known
+    resolved: Vec<usize>,
+    regex: Regex,
+    filter: Filter,
+}
+
+impl RegexFilter {
+
+    /// Finds the positions of the matched column
+    pub fn resolve(&mut self, headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        if let Some(ref column) = self.column {
+            self.resolved = column.indices(headers, duplicates, ignore_case);
+        }
+    }
+
+    /// Whether the record `line` passes the filter
+    pub fn keeps(&self, line: &[u8], cols: &[usize]) -> bool {
+        let columns = BytesColumns::new(line, cols);
+        let matched = match self.column {
+            None => columns.into_iter().any(|c| self.regex.is_match(c)),
+            Some(_) => self.resolved.iter()
+                .any(|&i| columns.get(i).is_some_and(|c| self.regex.is_match(c))),
+        };
+        matched == (self.filter == Filter::Keep)
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Keeps or drops rows whose column matches `regex`
+    ///
+    /// The column is referred to by position or, if there is a header, by name.
+    /// Filters are applied after value replacements and all of them must
+    /// keep a row for it to be returned. Headers are never filtered.
+    pub fn filter_regex<C: Into<Column>>(self, column: C, regex: Regex, filter: Filter) -> Csv<B> {
+        self.add_filter(Some(column.into()), regex, filter)
+    }
+
+    /// Keeps or drops rows where any column matches `regex`
+    pub fn filter_regex_any(self, regex: Regex, filter: Filter) -> Csv<B> {
+        self.add_filter(None, regex, filter)
+    }
+
+    fn add_filter(mut self, column: Option<Column>, regex: Regex, filter: Filter) -> Csv<B> {
+        let mut f = RegexFilter { column, resolved: Vec::new(), regex, filter };
+        f.resolve(self.headers.as_deref().unwrap_or(&[]), self.duplicates, self.ignore_case);
+        self.filters.push(f);
+        self
+    }
+
+}
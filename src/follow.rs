@@ -0,0 +1,141 @@
+//! Follow mode for growing sources
+//!
+//! Keeps reading a source as new data is appended to it, like `tail -f`.
+//! On end of file the reader is polled again after a fixed interval.
+//!
+//! Records are only yielded once complete: a record still being written,
+//! without its final line ending or with an unclosed quoted field, is kept
+//! until the rest of it is available. Records are split as by the Csv, with
+//! its terminator, quote and escapes.
+//!
+//! Truncated or rotated files are not detected.
+
+use std::io::{self, BufRead};
+use std::mem;
+use std::thread;
+use std::time::Duration;
+use error::{Error, Result};
+use parser::Splitter;
+use {Csv, Row};
+
+/// Iterator over the records of a growing csv, never ending on end of file
+///
+/// Created with `Csv::follow`.
+pub struct Follow<B: BufRead> {
+    csv: Csv<B>,
+    interval: Duration,
+    /// splitting state of the record being read
+    splitter: Splitter,
+    /// content of the record being read
+    line: Vec<u8>,
+    /// column ends of the record being read
+    cols: Vec<usize>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining records, waiting for more on end of file
+    ///
+    /// The reader is polled every `interval` until a new complete record
+    /// is available, the iterator only ends after an error.
+    pub fn follow(self, interval: Duration) -> Follow<B> {
+        Follow {
+            splitter: self.splitter(),
+            csv: self,
+            interval,
+            line: Vec::new(),
+            cols: Vec::new(),
+        }
+    }
+
+}
+
+impl<B: BufRead> Follow<B> {
+
+    /// Gets the headers read by the underlying Csv
+    pub fn headers(&mut self) -> Vec<String> {
+        self.csv.headers()
+    }
+
+    /// Splits bytes read into `line` until it holds a complete record
+    fn read_record(&mut self) -> Result<()> {
+        loop {
+            let (used, complete) = {
+                let available = match self.csv.reader.fill_buf() {
+                    Ok([]) => {
+                        thread::sleep(self.interval);
+                        continue;
+                    },
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(Error::from(e)),
+                };
+                let split = self.splitter.split(available, &mut self.line, 0, &mut self.cols)?;
+                if self.csv.track_spans {
+                    self.csv.source.extend_from_slice(&available[..split.0]);
+                }
+                split
+            };
+            self.csv.reader.consume(used);
+            self.csv.position += used as u64;
+            ::check_limits(&self.line, &self.cols, self.csv.limits)?;
+            if complete {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the next row kept by filters, waiting for it if needed
+    fn read_row(&mut self) -> Result<Row> {
+        if let Some(e) = self.csv.error.take() {
+            return Err(e);
+        }
+        loop {
+            let (start, skip_lf) = (self.csv.position, self.splitter.skip_lf());
+            self.csv.record_offset = start;
+            self.csv.source.clear();
+            let result = self.read_record();
+            let (mut line, mut cols) = (mem::take(&mut self.line), mem::take(&mut self.cols));
+            if let Err(e) = result {
+                self.splitter.reset();
+                return Err(e);
+            }
+            let spans = if self.csv.track_spans {
+                // a `\n` left over from the `\r\n` ending the previous record
+                let skipped = skip_lf && self.csv.source.starts_with(b"\n");
+                let source = &self.csv.source[usize::from(skipped)..];
+                ::source_spans(source, self.csv.splitter(), start + u64::from(skipped))
+            } else {
+                Vec::new()
+            };
+            if self.csv.finish_record(&mut line, &mut cols, 0, 0)? {
+                let valid_utf8 = self.csv.valid_utf8(&line);
+                return Ok(Row {
+                    line,
+                    cols,
+                    decode_options: self.csv.decode_options.clone(),
+                    valid_utf8,
+                    raw: self.csv.raw,
+                    nulls: mem::take(&mut self.csv.nulls),
+                    spans,
+                });
+            }
+        }
+    }
+
+}
+
+impl<B: BufRead> Iterator for Follow<B> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        if self.csv.exit {
+            return None;
+        }
+        let row = self.read_row();
+        if row.is_err() {
+            self.csv.exit = true;
+        }
+        Some(row)
+    }
+}
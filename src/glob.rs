@@ -0,0 +1,222 @@
+//! Multi-file reader
+//!
+//! Reads several csv files as one logical stream, typically a dataset
+//! partitioned in many files sharing the same header.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! let csv = quick_csv::Csv::from_glob("data/2024-*.csv", true).unwrap();
+//! for row in csv {
+//!     // rows of all files, in file name order
+//! }
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use error::{Error, Result};
+use {Csv, UTF8_BOM};
+
+/// Lists the files matching `pattern`, sorted by path
+///
+/// Wildcards are only supported in the file name: `*` matches any
+/// sequence of characters, `?` any single character.
+pub fn glob<P: AsRef<Path>>(pattern: P) -> Result<Vec<PathBuf>> {
+    let pattern = pattern.as_ref();
+    let name = pattern.file_name().and_then(|n| n.to_str())
+        .ok_or_else(|| Error::Parse(format!("Invalid glob pattern {}", pattern.display())))?;
+    let dir = match pattern.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => Path::new("."),
+    };
+    if dir.to_str().is_some_and(|d| d.contains(['*', '?'])) {
+        return Err(Error::Parse(format!(
+            "Wildcards are only supported in file names, found {}", dir.display())));
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let matched = entry.file_name().to_str()
+            .is_some_and(|n| matches(name.as_bytes(), n.as_bytes()));
+        if matched && entry.file_type()?.is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Matches `name` against a pattern made of `*`, `?` and literal bytes
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and of the name byte it is matched up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(&b'*') => {
+                star = Some((p, n));
+                p += 1;
+            },
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Reader concatenating several files
+///
+/// A newline is inserted between files not ending with one and UTF-8
+/// BOMs are skipped. With headers, the header line of every file but the
+/// first must be identical to the first one and is skipped, else reading
+/// fails with an `io::ErrorKind::InvalidData` error.
+pub struct Files {
+    paths: Vec<PathBuf>,
+    next: usize,
+    has_header: bool,
+    /// header line of the first file, without line ending
+    header: Option<Vec<u8>>,
+    /// bytes to return before reading the current file
+    pending: Cursor<Vec<u8>>,
+    current: Option<BufReader<File>>,
+    /// whether the last returned byte is not a newline
+    needs_newline: bool,
+}
+
+impl Files {
+
+    /// Creates a reader over `paths`, in order
+    ///
+    /// With `has_header`, the first line of every file is a header, checked
+    /// against the first file's one and only returned once. Without, files
+    /// are read whole.
+    pub fn new(paths: Vec<PathBuf>, has_header: bool) -> Files {
+        Files {
+            paths,
+            next: 0,
+            has_header,
+            header: None,
+            pending: Cursor::new(Vec::new()),
+            current: None,
+            needs_newline: false,
+        }
+    }
+
+    /// Gets the paths of the files being read
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Opens the next file, skipping its BOM and checking its header
+    fn open_next(&mut self) -> io::Result<()> {
+        let path = &self.paths[self.next];
+        let mut reader = BufReader::new(File::open(path)?);
+        if reader.fill_buf()?.starts_with(UTF8_BOM) {
+            reader.consume(UTF8_BOM.len());
+        }
+        if self.has_header {
+            let line = read_header(&mut reader)?;
+            let header = trim_line_ending(&line).to_vec();
+            match self.header {
+                None => {
+                    self.header = Some(header);
+                    self.pending = Cursor::new(line);
+                },
+                Some(ref first) if *first == header => (),
+                Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "Header of {} differs from the one of {}",
+                    path.display(), self.paths[0].display()))),
+            }
+        }
+        self.current = Some(reader);
+        self.next += 1;
+        Ok(())
+    }
+
+}
+
+impl Read for Files {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.pending.read(buf)?;
+            let n = if n > 0 {
+                n
+            } else if let Some(ref mut reader) = self.current {
+                reader.read(buf)?
+            } else if self.next < self.paths.len() {
+                if self.needs_newline {
+                    self.needs_newline = false;
+                    buf[0] = b'\n';
+                    return Ok(1);
+                }
+                self.open_next()?;
+                continue;
+            } else {
+                return Ok(0);
+            };
+            if n == 0 {
+                self.current = None;
+                continue;
+            }
+            self.needs_newline = buf[n - 1] != b'\n';
+            return Ok(n);
+        }
+    }
+}
+
+/// Reads a full header line, including quoted newlines and its line ending
+fn read_header<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    let mut in_quote = false;
+    loop {
+        let start = line.len();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(line);
+        }
+        in_quote ^= line[start..].iter().filter(|&&b| b == b'\"').count() % 2 == 1;
+        if !in_quote {
+            return Ok(line);
+        }
+    }
+}
+
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+impl Csv<BufReader<Files>> {
+
+    /// Creates a csv reading all files matching `pattern` in sorted order
+    ///
+    /// With `has_header`, files are expected to start with the same header,
+    /// read as the csv headers: a file with another header fails reading
+    /// with an `io::ErrorKind::InvalidData` error. Without, all lines of all
+    /// files are rows. See `glob` for the supported patterns.
+    pub fn from_glob<P: AsRef<Path>>(pattern: P, has_header: bool) -> Result<Csv<BufReader<Files>>> {
+        let paths = glob(pattern.as_ref())?;
+        if paths.is_empty() {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, format!(
+                "No file matches {}", pattern.as_ref().display()))));
+        }
+        Ok(Csv::from_reader(BufReader::new(Files::new(paths, has_header))).has_header(has_header))
+    }
+
+}
@@ -0,0 +1,520 @@
+//! Group-by aggregation
+//!
+//! Computes count, sum, min, max and mean per group of rows sharing the
+//! same key columns, without keeping rows in memory.
+//!
+//! Aggregations may also be written as text, e.g. for command line tools,
+//! with `Summary`: `sum(amount), mean(latency) by region`.
+//!
+//! Unsorted input keeps every group in memory. Beyond the
+//! `Csv::memory_budget`, groups are spilled to disk if `Csv::spill_to` is
+//! set, and merged back once all rows are read.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::group::Aggregate;
+//!
+//! let mut csv = Csv::from_string("region,sales\nnorth,10\nsouth,5\nnorth,2").has_header(true);
+//! let groups = csv.group_by(&["region"])
+//!     .aggregate(&[Aggregate::Count, Aggregate::Sum("sales".into())])
+//!     .unwrap();
+//! assert_eq!(groups[0].key, vec!["north"]);
+//! assert_eq!(groups[0].values, vec![Some(2.), Some(12.)]);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::io::{BufRead, Write};
+use std::str;
+
+use budget::Budget;
+use error::{Error, Result};
+use query::{tokenize, Token};
+use spill::{Record, Sorter};
+use transform::column_index;
+use width::display_width;
+use writer::Writer;
+use Csv;
+
+/// An aggregation computed per group
+///
+/// Empty values are ignored, other values must be numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Number of rows
+    Count,
+    /// Sum of a column
+    Sum(String),
+    /// Minimum of a column
+    Min(String),
+    /// Maximum of a column
+    Max(String),
+    /// Arithmetic mean of a column
+    Mean(String),
+}
+
+impl Aggregate {
+    fn column(&self) -> Option<&str> {
+        match *self {
+            Aggregate::Count => None,
+            Aggregate::Sum(ref c) | Aggregate::Min(ref c)
+                | Aggregate::Max(ref c) | Aggregate::Mean(ref c) => Some(c),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Aggregate::Count => write!(f, "count"),
+            Aggregate::Sum(ref c) => write!(f, "sum({})", c),
+            Aggregate::Min(ref c) => write!(f, "min({})", c),
+            Aggregate::Max(ref c) => write!(f, "max({})", c),
+            Aggregate::Mean(ref c) => write!(f, "mean({})", c),
+        }
+    }
+}
+
+impl str::FromStr for Aggregate {
+    type Err = Error;
+
+    /// Parses an aggregate as displayed, e.g. `sum(sales)`
+    ///
+    /// `count`, `count()` and `count(*)` are counts, `avg` is a `mean`.
+    /// Function names are case insensitive, column names with spaces or
+    /// symbols are written within double quotes.
+    fn from_str(s: &str) -> Result<Aggregate> {
+        parse_aggregate(&tokenize(s)?)
+    }
+}
+
+/// Parses the tokens of a single aggregate
+fn parse_aggregate(tokens: &[Token]) -> Result<Aggregate> {
+    let invalid = || Error::Parse(format!("Invalid aggregate {:?}", tokens));
+    let (name, column) = match tokens {
+        [Token::Name(name, false)] => (name, None),
+        [Token::Name(name, false), Token::Symbol("("), Token::Symbol(")")] => (name, None),
+        [Token::Name(name, false), Token::Symbol("("), Token::Symbol("*"), Token::Symbol(")")] => (name, None),
+        [Token::Name(name, false), Token::Symbol("("), Token::Name(c, _) | Token::Number(c), Token::Symbol(")")] =>
+            (name, Some(c.clone())),
+        _ => return Err(invalid()),
+    };
+    match (&*name.to_lowercase(), column) {
+        ("count", None) => Ok(Aggregate::Count),
+        ("sum", Some(c)) => Ok(Aggregate::Sum(c)),
+        ("min", Some(c)) => Ok(Aggregate::Min(c)),
+        ("max", Some(c)) => Ok(Aggregate::Max(c)),
+        ("mean", Some(c)) | ("avg", Some(c)) => Ok(Aggregate::Mean(c)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Aggregates of a text expression, e.g. `count, mean(latency) by region`
+///
+/// Expressions are made of comma separated aggregates, as parsed by
+/// `Aggregate::from_str`, optionally followed by `by` and comma separated
+/// key columns. Without keys, all rows are aggregated as a single group.
+///
+/// # Example
+///
+/// ```
+/// use quick_csv::Csv;
+/// use quick_csv::group::Summary;
+///
+/// let mut csv = Csv::from_string("region,latency\neu,10\nus,30\neu,20").has_header(true);
+/// let table = Summary::parse("count, mean(latency) by region").unwrap().run(&mut csv).unwrap();
+/// assert_eq!(table.headers, vec!["region", "count", "mean(latency)"]);
+/// assert_eq!(table.to_string(), "\
+/// region  count  mean(latency)
+/// eu      2      15
+/// us      1      30
+/// ");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// aggregates, in output order
+    pub aggregates: Vec<Aggregate>,
+    /// key columns, empty for a single group
+    pub keys: Vec<String>,
+}
+
+impl Summary {
+
+    /// Parses an expression
+    pub fn parse(expr: &str) -> Result<Summary> {
+        let tokens = tokenize(expr)?;
+        let by = tokens.iter().position(|t| match *t {
+            Token::Name(ref n, false) => n.eq_ignore_ascii_case("by"),
+            _ => false,
+        });
+        let (aggregates, keys) = match by {
+            Some(i) => (&tokens[..i], &tokens[i + 1..]),
+            None => (&tokens[..], &[][..]),
+        };
+        let aggregates = aggregates.split(|t| *t == Token::Symbol(","))
+            .map(parse_aggregate)
+            .collect::<Result<Vec<_>>>()?;
+        let keys = if by.is_some() {
+            keys.split(|t| *t == Token::Symbol(","))
+                .map(|key| match key {
+                    [Token::Name(k, _) | Token::Number(k)] => Ok(k.clone()),
+                    _ => Err(Error::Parse(format!("Invalid key {:?} in '{}'", key, expr))),
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+        Ok(Summary { aggregates, keys })
+    }
+
+    /// Aggregates the remaining rows of `csv`
+    pub fn run<B: BufRead>(&self, csv: &mut Csv<B>) -> Result<Table> {
+        let keys = self.keys.iter().map(|k| &**k).collect::<Vec<_>>();
+        let mut groups = csv.group_by(&keys).aggregate(&self.aggregates)?;
+        if groups.is_empty() && keys.is_empty() {
+            // without rows, the single group still has a count
+            let values = self.aggregates.iter().map(|a| Accumulator::new().value(a)).collect();
+            groups.push(Group { key: Vec::new(), values });
+        }
+        Ok(Table {
+            headers: self.keys.iter().cloned().chain(self.aggregates.iter().map(|a| a.to_string())).collect(),
+            groups,
+        })
+    }
+
+}
+
+/// Result of a `Summary`
+///
+/// Displayed as text columns aligned with `width::display_width`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// names of the keys then of the aggregates
+    pub headers: Vec<String>,
+    /// aggregated groups, in order of first appearance
+    pub groups: Vec<Group>,
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = self.groups.iter().map(|g| {
+            let values = g.values.iter().map(|v| v.map_or_else(String::new, |v| v.to_string()));
+            g.key.iter().cloned().chain(values).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+        let mut widths = self.headers.iter().map(|h| display_width(h)).collect::<Vec<_>>();
+        for row in &rows {
+            for (w, col) in widths.iter_mut().zip(row) {
+                *w = (*w).max(display_width(col));
+            }
+        }
+        for row in ::std::iter::once(&self.headers).chain(&rows) {
+            let mut line = String::new();
+            for (i, (col, w)) in row.iter().zip(&widths).enumerate() {
+                line.push_str(col);
+                if i + 1 < row.len() {
+                    line.extend(::std::iter::repeat_n(' ', w - display_width(col) + 2));
+                }
+            }
+            writeln!(f, "{}", line.trim_end_matches(' '))?;
+        }
+        Ok(())
+    }
+}
+
+/// The aggregated values of a group
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    /// values of the key columns
+    pub key: Vec<String>,
+    /// one value per aggregate, `None` if the group has no value to aggregate
+    pub values: Vec<Option<f64>>,
+}
+
+/// Running state of an aggregate
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    rows: usize,
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Accumulator {
+    fn new() -> Accumulator {
+        Accumulator { rows: 0, count: 0, sum: 0., min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+
+    fn add(&mut self, value: Option<f64>) {
+        self.rows += 1;
+        if let Some(v) = value {
+            self.count += 1;
+            self.sum += v;
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+    }
+
+    fn merge(&mut self, other: &Accumulator) {
+        self.rows += other.rows;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Appends the state as 5 columns of a spilled record
+    fn write(&self, record: &mut Record) {
+        record.push(self.rows.to_string().into_bytes());
+        record.push(self.count.to_string().into_bytes());
+        for v in &[self.sum, self.min, self.max] {
+            record.push(v.to_string().into_bytes());
+        }
+    }
+
+    /// Reads the state written by `write`
+    fn read(cols: &[Vec<u8>]) -> Option<Accumulator> {
+        fn parse<T: str::FromStr>(col: &[u8]) -> Option<T> {
+            str::from_utf8(col).ok()?.parse().ok()
+        }
+        Some(Accumulator {
+            rows: parse(&cols[0])?,
+            count: parse(&cols[1])?,
+            sum: parse(&cols[2])?,
+            min: parse(&cols[3])?,
+            max: parse(&cols[4])?,
+        })
+    }
+
+    fn value(&self, aggregate: &Aggregate) -> Option<f64> {
+        match *aggregate {
+            Aggregate::Count => Some(self.rows as f64),
+            _ if self.count == 0 => None,
+            Aggregate::Sum(_) => Some(self.sum),
+            Aggregate::Min(_) => Some(self.min),
+            Aggregate::Max(_) => Some(self.max),
+            Aggregate::Mean(_) => Some(self.sum / self.count as f64),
+        }
+    }
+}
+
+/// Group-by aggregation over the remaining rows of a csv
+///
+/// Created with `Csv::group_by`.
+pub struct GroupBy<'a, B: 'a + BufRead> {
+    csv: &'a mut Csv<B>,
+    keys: Vec<String>,
+    sorted: bool,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Groups the remaining rows by the `keys` columns
+    ///
+    /// Requires headers to find columns by name.
+    pub fn group_by(&mut self, keys: &[&str]) -> GroupBy<'_, B> {
+        GroupBy {
+            csv: self,
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            sorted: false,
+        }
+    }
+
+}
+
+impl<'a, B: BufRead> GroupBy<'a, B> {
+
+    /// Defines whether rows are already sorted (or grouped) by key
+    ///
+    /// Sorted input only keeps the current group in memory, instead of a
+    /// hash map of all groups.
+    ///
+    /// Note: default = `false`
+    pub fn sorted(mut self, sorted: bool) -> GroupBy<'a, B> {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Computes `aggregates` for every group
+    ///
+    /// Groups are returned in order of first appearance.
+    pub fn aggregate(self, aggregates: &[Aggregate]) -> Result<Vec<Group>> {
+        let mut groups = Vec::new();
+        self.run(aggregates, |g| {
+            groups.push(g);
+            Ok(())
+        })?;
+        Ok(groups)
+    }
+
+    /// Computes `aggregates` for every group and writes them as csv
+    ///
+    /// The header is made of the key names followed by aggregate names,
+    /// e.g. `sum(sales)`. With sorted input, groups are written as soon as
+    /// they are complete.
+    ///
+    /// Returns the number of groups written.
+    pub fn write_to<W: Write>(self, aggregates: &[Aggregate], writer: &mut Writer<W>) -> Result<usize> {
+        let names = aggregates.iter().map(|a| a.to_string());
+        writer.write(self.keys.iter().cloned().chain(names))?;
+        let mut count = 0;
+        self.run(aggregates, |g| {
+            count += 1;
+            let values = g.values.iter().map(|v| v.map_or_else(String::new, |v| v.to_string()));
+            writer.write(g.key.into_iter().chain(values))
+        })?;
+        Ok(count)
+    }
+
+    fn run<F>(self, aggregates: &[Aggregate], mut emit: F) -> Result<()>
+        where F: FnMut(Group) -> Result<()>
+    {
+        let mut key_cols = Vec::with_capacity(self.keys.len());
+        for k in &self.keys {
+            key_cols.push(column_index(self.csv, k)?);
+        }
+        let mut value_cols = Vec::with_capacity(aggregates.len());
+        for a in aggregates {
+            value_cols.push(match a.column() {
+                Some(c) => Some(column_index(self.csv, c)?),
+                None => None,
+            });
+        }
+
+        let finish = |key: Vec<String>, accs: &[Accumulator]| Group {
+            key,
+            values: accs.iter().zip(aggregates).map(|(acc, a)| acc.value(a)).collect(),
+        };
+        // groups with their first row number, in order of first appearance
+        let mut order: Vec<(usize, Vec<String>)> = Vec::new();
+        let mut groups: HashMap<Vec<String>, Vec<Accumulator>> = HashMap::new();
+        let mut current: Option<(Vec<String>, Vec<Accumulator>)> = None;
+        let mut budget = self.csv.budget();
+        let limit = self.csv.memory_budget;
+        let can_spill = self.csv.spill.is_some();
+        let keys = key_cols.len();
+        let mut spilled = self.csv.sorter(|a: &Record, b: &Record| a[1..=keys].cmp(&b[1..=keys]));
+        let mut spilling = false;
+        for (n, row) in (&mut *self.csv).enumerate() {
+            let row = row?;
+            let cols = row.columns()?;
+            let key = key_cols.iter().map(|&i| cols.get(i).unwrap_or("").to_string()).collect::<Vec<_>>();
+            let accs = if self.sorted {
+                if current.as_ref().is_some_and(|c| c.0 != key) {
+                    let (key, accs) = current.take().unwrap();
+                    emit(finish(key, &accs))?;
+                }
+                &mut current.get_or_insert_with(|| (key, vec![Accumulator::new(); aggregates.len()])).1
+            } else {
+                if !groups.contains_key(&key) {
+                    // the key is kept twice, in `order` and `groups`
+                    let key_size = key.iter().map(|k| k.len() + mem::size_of::<String>()).sum::<usize>();
+                    let size = 2 * key_size + aggregates.len() * mem::size_of::<Accumulator>();
+                    if can_spill && !budget.fits(size) && !order.is_empty() {
+                        spill_groups(&mut spilled, &mut order, &mut groups)?;
+                        spilling = true;
+                        budget = Budget::new(limit);
+                    }
+                    if let Err(e) = budget.charge(size) {
+                        if !can_spill {
+                            return Err(e);
+                        }
+                    }
+                    order.push((n, key.clone()));
+                }
+                groups.entry(key).or_insert_with(|| vec![Accumulator::new(); aggregates.len()])
+            };
+            for (acc, col) in accs.iter_mut().zip(&value_cols) {
+                acc.add(match *col {
+                    Some(i) => number(cols.get(i).unwrap_or(""), i)?,
+                    None => None,
+                });
+            }
+        }
+
+        if let Some((key, accs)) = current {
+            emit(finish(key, &accs))?;
+        }
+        if !spilling {
+            for (_, key) in order {
+                let accs = groups.remove(&key).unwrap_or_default();
+                emit(finish(key, &accs))?;
+            }
+            return Ok(());
+        }
+
+        // merges the spilled groups by key, then sorts them by first appearance
+        spill_groups(&mut spilled, &mut order, &mut groups)?;
+        let mut merged = self.csv.sorter(|a: &Record, b: &Record| a[0].cmp(&b[0]));
+        let mut last: Option<Record> = None;
+        for record in spilled.finish()? {
+            let record = record?;
+            if let Some(ref mut last) = last {
+                if last[1..=keys] == record[1..=keys] {
+                    // the first record of a key has its first row number
+                    let mut accs = read_accumulators(last, keys)?;
+                    for (acc, other) in accs.iter_mut().zip(read_accumulators(&record, keys)?) {
+                        acc.merge(&other);
+                    }
+                    last.truncate(keys + 1);
+                    for acc in &accs {
+                        acc.write(last);
+                    }
+                    continue;
+                }
+            }
+            if let Some(last) = last.replace(record) {
+                merged.push(last)?;
+            }
+        }
+        if let Some(last) = last {
+            merged.push(last)?;
+        }
+        for record in merged.finish()? {
+            let record = record?;
+            let accs = read_accumulators(&record, keys)?;
+            let key = record[1..=keys].iter().map(|k| String::from_utf8_lossy(k).into_owned()).collect();
+            emit(finish(key, &accs))?;
+        }
+        Ok(())
+    }
+
+}
+
+/// Parses the `i`th column as a number, `None` if empty
+fn number(col: &str, i: usize) -> Result<Option<f64>> {
+    let col = col.trim();
+    if col.is_empty() {
+        return Ok(None);
+    }
+    col.parse().map(Some).map_err(|e| Error::Decode(format!(
+        "Cannot aggregate '{}' in column {}: {}", col, i, e)))
+}
+
+/// Moves all groups to `sorter`, as records of their first row number, key
+/// and accumulators
+fn spill_groups<F>(sorter: &mut Sorter<F>, order: &mut Vec<(usize, Vec<String>)>,
+                   groups: &mut HashMap<Vec<String>, Vec<Accumulator>>) -> Result<()>
+    where F: Fn(&Record, &Record) -> ::std::cmp::Ordering
+{
+    for (n, key) in order.drain(..) {
+        let accs = groups.remove(&key).unwrap_or_default();
+        // zero padded so that bytes compare as numbers
+        let mut record = vec![format!("{:020}", n).into_bytes()];
+        record.extend(key.into_iter().map(String::into_bytes));
+        for acc in &accs {
+            acc.write(&mut record);
+        }
+        sorter.push(record)?;
+    }
+    Ok(())
+}
+
+fn read_accumulators(record: &Record, keys: usize) -> Result<Vec<Accumulator>> {
+    record[keys + 1..].chunks(5)
+        .map(|cols| Accumulator::read(cols).ok_or_else(|| Error::Parse("Invalid spilled group".to_string())))
+        .collect()
+}
@@ -0,0 +1,148 @@
+//! Stable hashing
+//!
+//! `Fnv64` is a 64-bit FNV-1a hasher, which unlike the standard
+//! `DefaultHasher` gives the same hashes across runs, platforms and Rust
+//! versions: hashes can be stored and compared later.
+//!
+//! `Row::hash_fields` and `Row::hash_key` feed the columns of a row to any
+//! hasher, e.g. to join, deduplicate or sync rows on a key.
+//!
+//! `Csv::checksum` hashes the logical content of a csv, the same for all
+//! files holding the same rows whatever their delimiter, quoting or line
+//! endings.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//!
+//! let a = Csv::from_string("a,\"b\"\r\nc,d").checksum().unwrap();
+//! let b = Csv::from_string("a;b\nc;d\n").delimiter(b';').checksum().unwrap();
+//! assert_eq!(a, b);
+//! assert_ne!(a, Csv::from_string("a,b\nc,e").checksum().unwrap());
+//! ```
+
+use std::hash::Hasher;
+use std::io::{self, BufRead, Write};
+
+use error::Result;
+use transform;
+use {Csv, Row};
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0100_0000_01b3;
+
+/// Length written in place of missing and NULL columns
+const MISSING: u64 = u64::MAX;
+
+/// 64-bit FNV-1a hasher
+///
+/// Also a `Write`r hashing all bytes written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fnv64 {
+    state: u64,
+}
+
+impl Fnv64 {
+
+    /// Creates a hasher with the standard FNV offset basis
+    pub fn new() -> Fnv64 {
+        Fnv64 { state: OFFSET_BASIS }
+    }
+
+}
+
+impl Default for Fnv64 {
+    fn default() -> Fnv64 {
+        Fnv64::new()
+    }
+}
+
+impl Hasher for Fnv64 {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state = (self.state ^ u64::from(b)).wrapping_mul(PRIME);
+        }
+    }
+}
+
+impl Write for Fnv64 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Hasher::write(self, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Row {
+
+    /// Feeds all columns to `hasher`
+    ///
+    /// Every column is written as its little endian `u64` length followed by
+    /// its unescaped bytes, so the same columns always write the same bytes,
+    /// whatever the platform, quoting or delimiter, and `a,bc` differs from
+    /// `ab,c`. With a stable hasher such as `Fnv64`, hashes are stable too.
+    pub fn hash_fields<H: Hasher>(&self, hasher: &mut H) {
+        let cols = self.bytes_columns();
+        for i in 0..cols.len() {
+            self.hash_field(cols.get(i), i, hasher);
+        }
+    }
+
+    /// Feeds the `columns` columns to `hasher`, in that order
+    ///
+    /// Missing and NULL columns are written as a `u64::MAX` length without
+    /// bytes, unlike empty columns.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::hash::Fnv64;
+    ///
+    /// let rows = quick_csv::Csv::from_string("1,bob,x\n1,bob,y").collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(rows[0].hash_key(&[0, 1], Fnv64::new()), rows[1].hash_key(&[0, 1], Fnv64::new()));
+    /// assert_ne!(rows[0].hash_key(&[2], Fnv64::new()), rows[1].hash_key(&[2], Fnv64::new()));
+    /// ```
+    pub fn hash_key_fields<H: Hasher>(&self, columns: &[usize], hasher: &mut H) {
+        let cols = self.bytes_columns();
+        for &i in columns {
+            self.hash_field(cols.get(i), i, hasher);
+        }
+    }
+
+    /// Hashes the `columns` columns with `hasher`, see `hash_key_fields`
+    pub fn hash_key<H: Hasher>(&self, columns: &[usize], mut hasher: H) -> u64 {
+        self.hash_key_fields(columns, &mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_field<H: Hasher>(&self, col: Option<&[u8]>, i: usize, hasher: &mut H) {
+        match col {
+            Some(col) if !self.is_null(i) => {
+                hasher.write(&(col.len() as u64).to_le_bytes());
+                hasher.write(col);
+            },
+            _ => hasher.write(&MISSING.to_le_bytes()),
+        }
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Hashes the remaining rows, headers included, in their canonical form
+    ///
+    /// Rows are hashed as written by `transform::canonicalize`, with `Fnv64`:
+    /// checksums are stable across runs and only change with the columns.
+    pub fn checksum(&mut self) -> Result<u64> {
+        Ok(transform::canonicalize(self, Fnv64::new())?.finish())
+    }
+
+}
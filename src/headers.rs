@@ -0,0 +1,124 @@
+//! Headers management module
+//!
+//! Provides header names normalization and duplicate header policies
+
+/// Defines how header names are normalized before being exposed or
+/// matched against column names
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::headers::Normalization;
+///
+/// let normalization = Normalization { snake_case: true, ..Default::default() };
+/// assert_eq!(normalization.normalize(" First Name "), "first_name");
+/// assert_eq!(normalization.normalize("Cust. ID"), "cust_id");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Normalization {
+    /// Removes leading and trailing whitespaces
+    pub trim: bool,
+    /// Converts to lowercase
+    pub lowercase: bool,
+    /// Converts to snake_case, implies `trim` and `lowercase`
+    ///
+    /// `"First Name"` and `"firstName"` both become `"first_name"`
+    pub snake_case: bool,
+    /// Removes a leading UTF-8 BOM
+    pub strip_bom: bool,
+}
+
+impl Normalization {
+
+    /// Enables all normalizations
+    pub fn all() -> Normalization {
+        Normalization {
+            trim: true,
+            lowercase: true,
+            snake_case: true,
+            strip_bom: true,
+        }
+    }
+
+    /// Normalizes a header name
+    pub fn normalize(&self, header: &str) -> String {
+        let mut header = header;
+        if self.strip_bom {
+            header = header.trim_start_matches('\u{feff}');
+        }
+        if self.trim {
+            header = header.trim();
+        }
+        if self.snake_case {
+            snake_case(header)
+        } else if self.lowercase {
+            header.to_lowercase()
+        } else {
+            header.to_string()
+        }
+    }
+
+}
+
+/// Converts a header to snake_case, `"First Name"` becoming `"first_name"`
+pub(crate) fn snake_case(header: &str) -> String {
+    let mut snake = String::with_capacity(header.len());
+    let mut separate = false;
+    let mut prev_lower = false;
+    for c in header.chars() {
+        if !c.is_alphanumeric() {
+            separate = !snake.is_empty();
+            prev_lower = false;
+            continue;
+        }
+        if separate || (prev_lower && c.is_uppercase()) {
+            snake.push('_');
+        }
+        separate = false;
+        prev_lower = c.is_lowercase() || c.is_numeric();
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Defines how duplicate header names are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Duplicate names are an `Error::DuplicateHeader`
+    Error,
+    /// Names refer to their first column
+    KeepFirst,
+    /// Names refer to their last column
+    KeepLast,
+    /// Names refer to all their columns
+    All,
+}
+
+impl Duplicates {
+
+    /// Finds the positions of the columns named `name`, ignoring case or not
+    pub fn find(&self, headers: &[String], name: &str, ignore_case: bool) -> Vec<usize> {
+        let mut positions = headers.iter().enumerate()
+            .filter(|&(_, h)| matches(h, name, ignore_case))
+            .map(|(i, _)| i);
+        match *self {
+            Duplicates::Error | Duplicates::KeepFirst => positions.next().into_iter().collect(),
+            Duplicates::KeepLast => positions.next_back().into_iter().collect(),
+            Duplicates::All => positions.collect(),
+        }
+    }
+
+}
+
+/// Whether `header` is named `name`
+pub(crate) fn matches(header: &str, name: &str, ignore_case: bool) -> bool {
+    header == name || ignore_case && header.chars().flat_map(char::to_lowercase)
+        .eq(name.chars().flat_map(char::to_lowercase))
+}
+
+/// Finds the first duplicate header name, ignoring case or not
+pub fn find_duplicate(headers: &[String], ignore_case: bool) -> Option<&str> {
+    headers.iter().enumerate()
+        .find(|&(i, h)| headers[..i].iter().any(|p| matches(p, h, ignore_case)))
+        .map(|(_, h)| &**h)
+}
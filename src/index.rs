@@ -0,0 +1,219 @@
+//! Row index module
+//!
+//! Stores the byte offset of every row so that any row can be reached
+//! without parsing the preceding ones.
+//!
+//! Building an index requires parsing the whole file once, it can then be
+//! saved to a sidecar file and loaded back as long as the source file is unchanged.
+
+use std::fs::{File, Metadata};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use dialect::Dialect;
+use error::{Error, Result};
+use {Checkpoint, Csv};
+
+const MAGIC: &[u8] = b"QCSVIDX\0";
+const VERSION: u32 = 1;
+/// Size of the magic, version, fingerprint and offsets count
+const HEADER_LEN: u64 = 8 + 4 + 8 + 8 + 4 + 8;
+
+/// Identifies a version of the indexed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl Fingerprint {
+    fn from_metadata(metadata: &Metadata) -> Fingerprint {
+        let modified = metadata.modified().ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Fingerprint {
+            len: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        }
+    }
+}
+
+/// Byte offsets of all rows of a csv file
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use quick_csv::Csv;
+/// use quick_csv::index::Index;
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let index = Index::load_or_build("data.csv.idx", "data.csv", b',').unwrap();
+/// if let Some(checkpoint) = index.checkpoint(1000) {
+///     let file = BufReader::new(File::open("data.csv").unwrap());
+///     let mut csv = Csv::from_checkpoint(file, checkpoint).unwrap();
+///     let row_1000 = csv.next();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    fingerprint: Fingerprint,
+    offsets: Vec<u64>,
+}
+
+impl Index {
+
+    /// Builds an index by parsing the entire file, an RFC 4180 csv with
+    /// `delimiter`
+    pub fn build<P: AsRef<Path>>(path: P, delimiter: u8) -> Result<Index> {
+        Index::build_with_dialect(path, Dialect { delimiter, ..Dialect::Rfc4180 })
+    }
+
+    /// Builds an index by parsing the entire file, written in `dialect`
+    ///
+    /// Quotes, escapes and terminators of the dialect decide where records
+    /// start.
+    pub fn build_with_dialect<P: AsRef<Path>>(path: P, dialect: Dialect) -> Result<Index> {
+        let file = File::open(path)?;
+        let fingerprint = Fingerprint::from_metadata(&file.metadata()?);
+        let mut csv = Csv::from_reader(BufReader::new(file)).dialect(dialect);
+        let mut offsets = Vec::new();
+        loop {
+            let offset = csv.checkpoint().offset();
+            match csv.next() {
+                Some(row) => { row?; },
+                None => break,
+            }
+            offsets.push(offset);
+        }
+        Ok(Index {
+            fingerprint,
+            offsets,
+        })
+    }
+
+    /// Saves the index into a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&self.fingerprint.len.to_le_bytes())?;
+        w.write_all(&self.fingerprint.modified_secs.to_le_bytes())?;
+        w.write_all(&self.fingerprint.modified_nanos.to_le_bytes())?;
+        w.write_all(&(self.offsets.len() as u64).to_le_bytes())?;
+        for offset in &self.offsets {
+            w.write_all(&offset.to_le_bytes())?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Loads an index file
+    ///
+    /// Fails with `Error::Index` if the index file is invalid or truncated,
+    /// or if the source file has changed since the index was built.
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(path: P, source: Q) -> Result<Index> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+        let mut magic = [0; 8];
+        read_exact(&mut r, &mut magic)?;
+        if magic != MAGIC {
+            return Err(Error::Index("not an index file".into()));
+        }
+        let version = read_u32(&mut r)?;
+        if version != VERSION {
+            return Err(Error::Index(format!("unsupported index version {}", version)));
+        }
+        let fingerprint = Fingerprint {
+            len: read_u64(&mut r)?,
+            modified_secs: read_u64(&mut r)?,
+            modified_nanos: read_u32(&mut r)?,
+        };
+        if fingerprint != Fingerprint::from_metadata(&source.as_ref().metadata()?) {
+            return Err(Error::Index("source file has changed".into()));
+        }
+        let len = read_u64(&mut r)?;
+        // checked before allocating, the count may be corrupt
+        if len.checked_mul(8).and_then(|n| n.checked_add(HEADER_LEN)) != Some(size) {
+            return Err(Error::Index(format!("{} offsets do not match the index size of {} bytes", len, size)));
+        }
+        let len = len as usize;
+        let mut offsets = Vec::with_capacity(len);
+        for _ in 0..len {
+            offsets.push(read_u64(&mut r)?);
+        }
+        Ok(Index {
+            fingerprint,
+            offsets,
+        })
+    }
+
+    /// Loads an index file or, if it is missing or outdated,
+    /// builds a new index and saves it
+    pub fn load_or_build<P: AsRef<Path>, Q: AsRef<Path>>(path: P, source: Q, delimiter: u8)
+        -> Result<Index>
+    {
+        Index::load_or_build_with_dialect(path, source, Dialect { delimiter, ..Dialect::Rfc4180 })
+    }
+
+    /// Loads an index file or, if it is missing or outdated, builds a new
+    /// index of a source written in `dialect` and saves it
+    pub fn load_or_build_with_dialect<P: AsRef<Path>, Q: AsRef<Path>>(path: P, source: Q, dialect: Dialect)
+        -> Result<Index>
+    {
+        match Index::load(&path, &source) {
+            Ok(index) => Ok(index),
+            Err(Error::Index(_)) | Err(Error::Io(_)) => {
+                let index = Index::build_with_dialect(source, dialect)?;
+                index.save(path)?;
+                Ok(index)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Gets the byte offset of the `n`th row
+    pub fn offset(&self, n: usize) -> Option<u64> {
+        self.offsets.get(n).cloned()
+    }
+
+    /// Gets a `Checkpoint` to resume parsing at the `n`th row
+    pub fn checkpoint(&self, n: usize) -> Option<Checkpoint> {
+        self.offset(n).map(|offset| Checkpoint::new(offset, n))
+    }
+
+    /// Gets rows count
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// `Index` is empty if there is no rows
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+}
+
+/// Reads exactly `buf.len()` bytes, a truncated file being an `Error::Index`
+fn read_exact<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<()> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Index("truncated index file".into()),
+        _ => Error::from(e),
+    })
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    read_exact(r, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    read_exact(r, &mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
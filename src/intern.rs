@@ -0,0 +1,175 @@
+//! String interning module
+//!
+//! Low cardinality columns (country codes, enum-like values ...) repeat the
+//! same few strings over and over. Interning them keeps a single shared
+//! `Arc<str>` per distinct value instead of one `String` per row.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use rustc_serialize::{Decodable, Decoder};
+
+/// Handshake between `Interned::decode` and the decoder of `Row::decode`
+enum Slot {
+    Idle,
+    /// the next string read is interned in place
+    Requested,
+    Filled(Arc<str>),
+}
+
+thread_local! {
+    static POOL: RefCell<Interner> = RefCell::new(Interner::new());
+    static SLOT: RefCell<Slot> = const { RefCell::new(Slot::Idle) };
+}
+
+/// Restores the pool it holds on drop, `f` of `Interned::scoped` panicking
+/// or not
+struct RestorePool(Interner);
+
+impl Drop for RestorePool {
+    fn drop(&mut self) {
+        let outer = mem::take(&mut self.0);
+        // the thread local may be gone when unwinding a thread
+        let _ = POOL.try_with(|p| *p.borrow_mut() = outer);
+    }
+}
+
+/// Whether `Interned::decode` is reading the next string
+///
+/// Lets `Columns` intern borrowed columns with `fill`, without the `String`
+/// of `read_str`.
+pub(crate) fn requested() -> bool {
+    SLOT.with(|slot| matches!(*slot.borrow(), Slot::Requested))
+}
+
+/// Interns `s` in the thread pool for `Interned::decode`
+pub(crate) fn fill(s: &str) {
+    let shared = POOL.with(|p| p.borrow_mut().intern(s));
+    SLOT.with(|slot| *slot.borrow_mut() = Slot::Filled(shared));
+}
+
+/// A pool of shared strings
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::intern::Interner;
+///
+/// let mut interner = Interner::new();
+/// let csv = quick_csv::Csv::from_string("FR\nUS\nFR");
+/// let countries = csv.map(|r| interner.intern(r.unwrap().columns().unwrap().next().unwrap()))
+///     .collect::<Vec<_>>();
+/// assert!(::std::sync::Arc::ptr_eq(&countries[0], &countries[2]));
+/// assert_eq!(interner.len(), 2);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    pool: HashSet<Arc<str>>,
+}
+
+impl Interner {
+
+    /// Creates a new empty pool
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Gets the shared string equal to `s`, allocating it only if it is not
+    /// in the pool yet
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(shared) = self.pool.get(s) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = Arc::from(s);
+        self.pool.insert(shared.clone());
+        shared
+    }
+
+    /// Gets distinct strings count
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// `Interner` is empty if it has no strings
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    /// Removes all strings from the pool
+    ///
+    /// Strings already returned are still valid but won't be shared with new ones.
+    pub fn clear(&mut self) {
+        self.pool.clear();
+    }
+
+}
+
+/// An interned string which can be decoded
+///
+/// Decoded strings are interned in a thread local pool, without allocating
+/// a `String` per row when decoded by `Row::decode`. Use
+/// `Interned::clear_pool` to release the pool, or `Interned::scoped` to
+/// decode with a pool of its own.
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::intern::Interned;
+///
+/// let csv = quick_csv::Csv::from_string("1,FR\n2,FR");
+/// let rows = csv.map(|r| r.unwrap().decode::<(u8, Interned)>().unwrap()).collect::<Vec<_>>();
+/// assert_eq!(&*rows[0].1, "FR");
+/// assert!(::std::sync::Arc::ptr_eq(&rows[0].1 .0, &rows[1].1 .0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interned(pub Arc<str>);
+
+impl Interned {
+    /// Removes all strings from the current thread pool
+    pub fn clear_pool() {
+        POOL.with(|p| p.borrow_mut().clear());
+    }
+
+    /// Gets distinct strings count of the current thread pool
+    pub fn pool_len() -> usize {
+        POOL.with(|p| p.borrow().len())
+    }
+
+    /// Runs `f` with a new empty pool, released once `f` returns, the
+    /// current pool being restored
+    pub fn scoped<T, F: FnOnce() -> T>(f: F) -> T {
+        let _restore = RestorePool(POOL.with(|p| mem::take(&mut *p.borrow_mut())));
+        f()
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Decodable for Interned {
+    fn decode<D: Decoder>(d: &mut D) -> ::std::result::Result<Interned, D::Error> {
+        SLOT.with(|slot| *slot.borrow_mut() = Slot::Requested);
+        let s = d.read_str();
+        let slot = SLOT.with(|slot| mem::replace(&mut *slot.borrow_mut(), Slot::Idle));
+        let s = s?;
+        Ok(Interned(match slot {
+            Slot::Filled(shared) => shared,
+            // other decoders allocate the string
+            _ => POOL.with(|p| p.borrow_mut().intern(&s)),
+        }))
+    }
+}
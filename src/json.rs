@@ -0,0 +1,122 @@
+//! JSON rows
+//!
+//! Yields rows as `serde_json::Value` objects keyed by headers.
+//! Requires the `serde_json` feature.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate quick_csv;
+//! #[macro_use] extern crate serde_json;
+//!
+//! # fn main() {
+//! let csv = quick_csv::Csv::from_string("name,age\nbob,42").has_header(true);
+//! let rows = csv.json_rows().infer_types(true).collect::<Result<Vec<_>, _>>().unwrap();
+//! assert_eq!(rows, vec![json!({"name": "bob", "age": 42})]);
+//! # }
+//! ```
+
+use std::io::BufRead;
+
+use serde_json::{Map, Number, Value};
+
+use coerce;
+use error::{Error, Result};
+use headers;
+use {Csv, Row};
+
+/// Iterator over rows as JSON objects
+///
+/// Created with `Csv::json_rows`.
+pub struct JsonRows<B: BufRead> {
+    csv: Csv<B>,
+    headers: Vec<String>,
+    infer_types: bool,
+    /// duplicate header, reported before any row
+    error: Option<Error>,
+    exit: bool,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining rows as JSON objects keyed by headers
+    ///
+    /// Columns without a header are keyed `column_<i>`. Duplicate keys, which
+    /// would overwrite each other, are an `Error::DuplicateHeader`: reported
+    /// once before any row for headers, and for each row with a `column_<i>`
+    /// key also a header.
+    pub fn json_rows(mut self) -> JsonRows<B> {
+        let headers = self.headers();
+        let error = headers::find_duplicate(&headers, false).map(|h| Error::DuplicateHeader(h.to_string()));
+        JsonRows {
+            csv: self,
+            headers,
+            infer_types: false,
+            error,
+            exit: false,
+        }
+    }
+
+}
+
+impl<B: BufRead> JsonRows<B> {
+
+    /// Defines whether values are converted into JSON booleans and numbers
+    ///
+    /// With type inference, values are coerced as with `Csv::coerce`, field
+    /// hooks included: `true`/`false` become booleans, numbers become numbers and empty
+    /// values become `null`. Other values, and floats JSON cannot represent,
+    /// are strings.
+    ///
+    /// Note: default = `false`, all values are strings
+    pub fn infer_types(mut self, infer_types: bool) -> JsonRows<B> {
+        self.infer_types = infer_types;
+        self
+    }
+
+    fn object(&self, row: &Row) -> Result<Value> {
+        let mut object = Map::new();
+        for (i, col) in row.columns()?.enumerate() {
+            let key = match self.headers.get(i) {
+                Some(header) => header.clone(),
+                None => {
+                    let key = format!("column_{}", i);
+                    if self.headers.contains(&key) {
+                        return Err(Error::DuplicateHeader(key));
+                    }
+                    key
+                },
+            };
+            let value = if self.infer_types { infer(row, &coerce::hooked(row, i, col)) } else { Value::String(col.to_string()) };
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+
+}
+
+/// Converts a column into the most specific JSON value
+fn infer(row: &Row, col: &str) -> Value {
+    match coerce::infer(row, col) {
+        coerce::Value::Null => Value::Null,
+        coerce::Value::Bool(b) => Value::Bool(b),
+        coerce::Value::Int(i) => Value::Number(Number::from(i)),
+        coerce::Value::Float(f) => Number::from_f64(f).map_or_else(|| Value::String(col.to_string()), Value::Number),
+        coerce::Value::Str(s) => Value::String(s),
+    }
+}
+
+impl<B: BufRead> Iterator for JsonRows<B> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        if self.exit {
+            return None;
+        }
+        if let Some(e) = self.error.take() {
+            self.exit = true;
+            return Some(Err(e));
+        }
+        self.csv.next().map(|row| row.and_then(|row| self.object(&row)))
+    }
+}
@@ -47,26 +47,119 @@
 //! - `bytes_columns`:
 //!   - similar to `columns` but columns are of type `&[u8]`, which means you may want to convert it to &str first
 //!   - performance gain compared to `columns` is minimal, use it only if you *really* need to as it is less convenient
+//!
+//! ## Architecture
+//!
+//! Record splitting, quote handling and column offsets live in the IO-free
+//! `parser` module. Front-ends only feed it bytes:
+//! - `Csv` reads from any `BufRead`, including slices with `Csv::from_string`
+//! - `parser::Parser` is a push parser emitting fields to a `Handler`
+//! - `parser::RowParser` queues complete rows from partial or non-blocking input,
+//!   for async runtimes and poll-based servers
+//! - `stream::CsvStream` reads a `futures::io::AsyncBufRead` (`futures` feature)
 
 #![deny(missing_docs)]
 
 extern crate rustc_serialize;
+#[cfg(feature = "rayon")] extern crate rayon;
+#[cfg(feature = "regex")] extern crate regex;
+#[cfg(feature = "polars")] extern crate polars;
+#[cfg(feature = "serde_json")] extern crate serde_json;
+#[cfg(feature = "tracing")] extern crate tracing;
+#[cfg(feature = "futures")] extern crate futures_core;
+#[cfg(feature = "futures")] extern crate futures_io;
+#[cfg(feature = "flate2")] extern crate flate2;
+#[cfg(feature = "chrono")] extern crate chrono;
+#[cfg(feature = "csv")] extern crate csv;
+#[cfg(feature = "uuid")] extern crate uuid as uuid_crate;
+#[cfg(feature = "derive")] extern crate quick_csv_derive;
+#[cfg(test)] extern crate quickcheck;
 
+pub mod arena;
+pub mod binary;
+mod budget;
+pub mod codegen;
+pub mod date;
+pub mod coerce;
 pub mod columns;
+#[cfg(feature = "csv")] pub mod compat;
+#[cfg(feature = "polars")] pub mod dataframe;
+pub mod decode;
+pub mod dedup;
+pub mod diagnostic;
+pub mod dialect;
 pub mod error;
+#[cfg(feature = "regex")] pub mod filter;
+pub mod follow;
+pub mod glob;
+pub mod group;
+pub mod hash;
+pub mod headers;
+pub mod index;
+pub mod intern;
+#[cfg(feature = "serde_json")] pub mod json;
+pub mod metrics;
+pub mod net;
+pub mod parser;
+pub mod profile;
+pub mod query;
+pub mod record;
+mod replace;
+pub mod repair;
+pub mod reverse;
+pub mod shard;
+pub mod spill;
+#[cfg(feature = "futures")] pub mod stream;
+pub mod string_record;
+pub mod timeout;
+pub mod top;
+pub mod transform;
+mod utf8;
+#[cfg(feature = "uuid")] pub mod uuid;
+pub mod validate;
+pub mod width;
+pub mod writer;
+#[cfg(feature = "rayon")] pub mod parallel;
 
-use self::columns::{Columns, BytesColumns};
+use self::columns::{Columns, BytesColumns, NamedColumns};
+use self::decode::{Column, DecodeOptions, Percent};
+use self::headers::{Duplicates, Normalization};
+use self::metrics::Metrics;
+use self::parser::Splitter;
+use self::replace::Replacements;
+pub use self::record::CsvRecord;
+#[cfg(feature = "derive")] pub use quick_csv_derive::CsvRecord;
+use std::borrow::Cow;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-use std::iter::Iterator;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::iter::{Enumerate, Iterator};
+use std::mem;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use budget::Budget;
+use dialect::Backslash;
+use utf8::Utf8Chunks;
+use writer::Escape;
 use error::{Error, Result};
 use rustc_serialize::Decodable;
 
 #[cfg(test)] mod test;
 
-const UTF8_BOM: &'static [u8] = b"\xef\xbb\xbf";
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Maximum sizes of rows and columns, in bytes
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    record: usize,
+    field: usize,
+}
+
+/// Callback of `Csv::on_truncate`
+type TruncateFn = dyn FnMut(usize, usize, usize) + Send;
 
 /// Csv reader
 /// 
@@ -103,14 +196,83 @@ pub struct Csv<B: BufRead> {
     has_header: bool,
     /// header
     headers: Option<Vec<String>>,
-    /// flexible column count
-    flexible: bool,
+    /// how rows not matching column count are handled
+    column_policy: ColumnPolicy,
+    /// called when a row is truncated
+    on_truncate: Option<Box<TruncateFn>>,
+    /// updated as rows are read
+    metrics: Option<Arc<dyn Metrics>>,
+    /// reading stops once set
+    cancel: Option<Arc<AtomicBool>>,
+    /// span of all events of this reader
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    /// a progress event is emitted every `progress_every` rows, never if 0
+    #[cfg(feature = "tracing")]
+    progress_every: usize,
+    /// maximum row and column sizes
+    limits: Limits,
+    /// maximum bytes buffered by operations keeping data in memory
+    memory_budget: usize,
+    /// where rows beyond the memory budget are spilled
+    spill: Option<spill::Spill>,
+    /// rows are kept as they appear in the source
+    raw: bool,
+    /// byte surrounding quoted columns
+    quote: u8,
+    /// how quotes are escaped within quoted columns
+    escape: Escape,
+    /// backslash escaping convention of columns
+    backslash: Backslash,
+    /// column standing for NULL
+    null: Option<Vec<u8>>,
+    /// `\N` columns of the last record
+    nulls: Vec<usize>,
+    /// source byte ranges of columns are kept
+    track_spans: bool,
+    /// source of the last record, with `track_spans`
+    source: Vec<u8>,
+    /// source byte ranges of the columns of the last record
+    spans: Vec<(u64, u64)>,
+    /// byte offset of the start of the last record read
+    record_offset: u64,
+    /// when rows are checked for valid utf8
+    utf8_validation: Utf8Validation,
+    /// source validated so far, with `Utf8Validation::PerChunk`
+    utf8_chunks: Utf8Chunks,
+    /// rows are assumed to be valid utf8
+    assume_utf8: bool,
+    /// how empty lines are interpreted
+    empty_record: EmptyRecord,
+    /// bytes ending records
+    terminator: Terminator,
+    /// line endings found by `detect_terminator`
+    line_endings: Option<LineEndings>,
+    /// options shared with rows, applied when decoding
+    decode_options: Option<Arc<DecodeOptions>>,
+    /// header names normalization
+    normalization: Normalization,
+    /// duplicate header names policy
+    duplicates: Duplicates,
+    /// header names are matched ignoring case
+    ignore_case: bool,
+    /// header names to rename, with their new name
+    renames: Vec<(String, String)>,
+    /// column values to replace
+    replacements: Option<Replacements>,
+    /// regex filters every row must pass
+    #[cfg(feature = "regex")]
+    filters: Vec<filter::RegexFilter>,
+    /// error to return on next iteration
+    error: Option<Error>,
     /// column count
     len: Option<usize>,
     /// if was error, exit next
     exit: bool,
     /// line count
     current_line: usize,
+    /// bytes consumed from the reader
+    position: u64,
 }
 
 impl<B: BufRead> Csv<B> {
@@ -120,16 +282,56 @@ impl<B: BufRead> Csv<B> {
     /// Note: default delimiter = ','
     pub fn from_reader(mut reader: B) -> Csv<B> {
         let result = try_consume_utf8_bom(&mut reader);
+        let exit = result.is_err();
 
         Csv {
-            reader: reader,
+            reader,
             delimiter: b',',
             has_header: false,
             headers: None,
-            flexible: false,
+            column_policy: ColumnPolicy::Strict,
+            on_truncate: None,
+            metrics: None,
+            cancel: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("csv", path = tracing::field::Empty),
+            #[cfg(feature = "tracing")]
+            progress_every: 100_000,
+            limits: Limits {
+                record: usize::MAX,
+                field: usize::MAX,
+            },
+            memory_budget: usize::MAX,
+            spill: None,
+            raw: false,
+            quote: b'"',
+            escape: Escape::Double,
+            backslash: Backslash::Plain,
+            null: None,
+            nulls: Vec::new(),
+            track_spans: false,
+            source: Vec::new(),
+            spans: Vec::new(),
+            record_offset: 0,
+            utf8_validation: Utf8Validation::OnAccess,
+            utf8_chunks: Utf8Chunks::default(),
+            assume_utf8: false,
+            empty_record: EmptyRecord::OneColumn,
+            terminator: Terminator::Lf,
+            line_endings: None,
+            decode_options: None,
+            normalization: Normalization::default(),
+            duplicates: Duplicates::KeepFirst,
+            ignore_case: false,
+            renames: Vec::new(),
+            replacements: None,
+            #[cfg(feature = "regex")]
+            filters: Vec::new(),
+            error: None,
             len: None,
-            exit: result.is_err(),
+            exit,
             current_line: 0,
+            position: result.unwrap_or(0) as u64,
         }
     }
 
@@ -140,11 +342,529 @@ impl<B: BufRead> Csv<B> {
     }
 
     /// Sets flexible columns
-    pub fn flexible(mut self, flexible: bool) -> Csv<B> {
-        self.flexible = flexible;
+    ///
+    /// Shorthand for `column_policy(ColumnPolicy::Flexible)`, or
+    /// `column_policy(ColumnPolicy::Strict)` if `flexible` is false
+    pub fn flexible(self, flexible: bool) -> Csv<B> {
+        self.column_policy(if flexible { ColumnPolicy::Flexible } else { ColumnPolicy::Strict })
+    }
+
+    /// Sets how rows with a column count different from the first row are handled
+    ///
+    /// Note: default = `ColumnPolicy::Strict`
+    pub fn column_policy(mut self, column_policy: ColumnPolicy) -> Csv<B> {
+        self.column_policy = column_policy;
+        self
+    }
+
+    /// Sets a function called whenever a row is truncated by `ColumnPolicy::Truncate`
+    ///
+    /// Arguments are the line number (as `current_line` once the row is read),
+    /// the expected column count and the column count found.
+    pub fn on_truncate<F>(mut self, f: F) -> Csv<B>
+        where F: FnMut(usize, usize, usize) + Send + 'static
+    {
+        self.on_truncate = Some(Box::new(f));
+        self
+    }
+
+    /// Sets metrics updated whenever a row is read or reading fails
+    pub fn metrics<M: Metrics + 'static>(mut self, metrics: Arc<M>) -> Csv<B> {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Stops reading once `flag` is set, returning an `Error::Cancelled`
+    ///
+    /// The flag is checked before every record, so that another thread can
+    /// abort a long read promptly.
+    pub fn cancel_on(mut self, flag: Arc<AtomicBool>) -> Csv<B> {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Emits a `tracing` progress event every `rows` rows, never if 0
+    ///
+    /// Requires the `tracing` feature. Events are emitted within a `csv`
+    /// span, with the file path if opened with `Csv::from_file`, for
+    /// file opening, headers, progress and errors.
+    ///
+    /// Note: default = 100 000
+    #[cfg(feature = "tracing")]
+    pub fn trace_progress(mut self, rows: usize) -> Csv<B> {
+        self.progress_every = rows;
+        self
+    }
+
+    /// Sets the maximum size of a row, in bytes
+    ///
+    /// Reading a bigger row fails with `Error::RecordTooLarge`. This prevents
+    /// invalid inputs, e.g. with a missing closing quote, from being buffered
+    /// entirely into a single row.
+    pub fn max_record_size(mut self, size: usize) -> Csv<B> {
+        self.limits.record = size;
+        self
+    }
+
+    /// Sets the maximum size of a column, in bytes
+    ///
+    /// Reading a bigger column fails with `Error::FieldTooLarge`
+    pub fn max_field_size(mut self, size: usize) -> Csv<B> {
+        self.limits.field = size;
+        self
+    }
+
+    /// Sets the maximum number of bytes kept in memory by buffering operations
+    ///
+    /// Operations collecting data (`group_by`, `transform::pivot`,
+    /// `transform::sort`, `tail`) fail with `Error::MemoryBudget` beyond it,
+    /// unless they can spill to disk with `spill_to`. Batching ones
+    /// (`read_batch`, `decode_parallel`, `decode_pipeline`) read smaller
+    /// batches. Sizes are estimated from the buffered bytes.
+    ///
+    /// Note: default = unlimited
+    pub fn memory_budget(mut self, bytes: usize) -> Csv<B> {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Sets when rows are checked for valid UTF-8
+    ///
+    /// Note: default = `Utf8Validation::OnAccess`
+    pub fn utf8_validation(mut self, utf8_validation: Utf8Validation) -> Csv<B> {
+        self.utf8_validation = utf8_validation;
+        self
+    }
+
+    /// Skips UTF-8 validation entirely
+    ///
+    /// # Safety
+    ///
+    /// Rows are converted to `&str` without validation. The source must only
+    /// contain valid UTF-8, otherwise the behavior is undefined. Use
+    /// `Row::bytes_columns` for bytes-only processing instead if unsure.
+    pub unsafe fn assume_utf8(mut self) -> Csv<B> {
+        self.assume_utf8 = true;
+        self
+    }
+
+    /// Defines how empty lines are returned
+    ///
+    /// Note: default = `EmptyRecord::OneColumn`
+    pub fn empty_record(mut self, empty_record: EmptyRecord) -> Csv<B> {
+        self.empty_record = empty_record;
+        self
+    }
+
+    /// Defines which line endings end records
+    ///
+    /// Note: default = `Terminator::Lf`, for `\n` and `\r\n`
+    pub fn terminator(mut self, terminator: Terminator) -> Csv<B> {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Sets the terminator from the line endings of the start of the source
+    ///
+    /// Line endings within quoted columns are ignored. Only the data already
+    /// buffered by the reader is inspected, nothing is consumed. What was
+    /// found is returned by `line_endings`. Must be called before `has_header`
+    /// so that the header is read with the detected terminator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::{Csv, Terminator};
+    ///
+    /// let mut csv = Csv::from_string("a,b\r1,2\r\n3,4\r").detect_terminator();
+    /// assert_eq!(csv.line_endings().map(|l| (l.cr, l.crlf, l.lf)), Some((1, 1, 0)));
+    /// assert_eq!(csv.line_endings().unwrap().terminator(), Terminator::Any);
+    /// assert_eq!(csv.count(), 3);
+    /// ```
+    pub fn detect_terminator(mut self) -> Csv<B> {
+        let line_endings = match self.reader.fill_buf() {
+            Ok(data) => LineEndings::sniff(data),
+            Err(_) => LineEndings::default(),
+        };
+        self.terminator = line_endings.terminator();
+        self.line_endings = Some(line_endings);
+        self
+    }
+
+    /// Gets the line endings found by `detect_terminator`, if called
+    pub fn line_endings(&self) -> Option<LineEndings> {
+        self.line_endings
+    }
+
+    /// Consumes a leading `sep=X` line, as written by Excel, setting the
+    /// delimiter to `X`
+    ///
+    /// Without such a line, nothing is consumed and the delimiter is kept.
+    /// Must be called before `has_header`, the line being otherwise read
+    /// as a one-column header or row.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::Csv;
+    ///
+    /// let mut csv = Csv::from_string("sep=;\na;b,c").detect_sep_line();
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a", "b,c"]);
+    /// ```
+    pub fn detect_sep_line(mut self) -> Csv<B> {
+        let sep = match self.reader.fill_buf() {
+            Ok(data) => dialect::sep_line(data),
+            Err(_) => None,
+        };
+        if let Some((delimiter, len)) = sep {
+            self.reader.consume(len);
+            self.position += len as u64;
+            self.delimiter = delimiter;
+        }
+        self
+    }
+
+    /// Keeps columns byte-for-byte as they appear in the source
+    ///
+    /// Quoted columns keep their surrounding quotes and escaped quotes stay
+    /// doubled, in `Row::columns`, `Row::bytes_columns`, `Row::as_bytes`
+    /// and decoding alike. Only the line ending is removed. Useful to
+    /// reproduce the source exactly, e.g. for checksums or audits.
+    ///
+    /// Note: default = `false`, columns are unescaped
+    pub fn raw(mut self, raw: bool) -> Csv<B> {
+        self.raw = raw;
+        self
+    }
+
+    /// Sets the byte surrounding quoted columns
+    ///
+    /// Note: default = `"`
+    pub fn quote(mut self, quote: u8) -> Csv<B> {
+        self.quote = quote;
+        self
+    }
+
+    /// Defines how quotes are escaped within quoted columns
+    ///
+    /// With `Escape::Backslash`, a backslash escapes the next byte of quoted
+    /// columns, as written by `Writer::escape`.
+    ///
+    /// Note: default = `Escape::Double`
+    pub fn escape(mut self, escape: Escape) -> Csv<B> {
+        self.escape = escape;
+        self
+    }
+
+    /// Reads columns written exactly as `token`, unquoted, as NULL
+    ///
+    /// NULL columns are empty, with `Row::is_null` telling them from empty
+    /// strings, e.g. with an empty token, `a,,b` has a NULL column while
+    /// `a,"",b` has an empty one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let row = quick_csv::Csv::from_string("1,NULL,\"NULL\"").null_token("NULL").next().unwrap().unwrap();
+    /// assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["1", "", "NULL"]);
+    /// assert!(row.is_null(1) && !row.is_null(2));
+    /// ```
+    ///
+    /// Note: default = no NULL, or `\N` with backslash escapes
+    pub fn null_token<S: Into<String>>(mut self, token: S) -> Csv<B> {
+        self.null = Some(token.into().into_bytes());
+        self
+    }
+
+    /// Reads columns escaped with backslashes instead of quoted, e.g. as
+    /// MySQL `LOAD DATA` or the PostgreSQL `COPY` text format
+    ///
+    /// Quotes are plain bytes and a backslash escapes the next byte, e.g. an
+    /// escaped delimiter or line ending. A `\N` column is NULL: empty, with
+    /// `Row::is_null` telling it from an empty string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::dialect::Backslash;
+    ///
+    /// let row = quick_csv::Csv::from_string("a\\tb\t\\N\t\"c\"\n")
+    ///     .delimiter(b'\t')
+    ///     .backslash_escapes(Backslash::MySql)
+    ///     .next().unwrap().unwrap();
+    /// assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a\tb", "", "\"c\""]);
+    /// assert!(row.is_null(1));
+    /// ```
+    ///
+    /// Note: default = `Backslash::Plain`, columns are quoted as in RFC 4180
+    pub fn backslash_escapes(mut self, backslash: Backslash) -> Csv<B> {
+        self.backslash = backslash;
+        self
+    }
+
+    /// Keeps the byte range of every column in the source, see `Row::span`
+    ///
+    /// Records are split a second time to find the ranges, as they may
+    /// differ from those of `Row::as_bytes` once escaped quotes are collapsed.
+    ///
+    /// Note: default = `false`
+    pub fn track_spans(mut self, track_spans: bool) -> Csv<B> {
+        self.track_spans = track_spans;
         self
     }
 
+    /// Registers a function transforming a column before it is decoded
+    ///
+    /// The column is referred to by position or, if there is a header, by name.
+    /// Hooks are only applied by `Row::decode`, `Row::columns` is unaffected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("name,weight\nbob,42 kg")
+    ///     .has_header(true)
+    ///     .field_hook("weight", |s| s.trim_end_matches(" kg").to_string());
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.decode::<(String, u32)>().unwrap(), ("bob".to_string(), 42));
+    /// ```
+    pub fn field_hook<C, F>(mut self, column: C, hook: F) -> Csv<B>
+        where C: Into<Column>,
+              F: Fn(&str) -> String + Send + Sync + 'static
+    {
+        let headers = self.headers.clone().unwrap_or_default();
+        let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+        self.decode_options_mut().add_hook(column.into(), Arc::new(hook), &headers, duplicates, ignore_case);
+        self
+    }
+
+    /// Sets the date format of a column, by position or header name
+    ///
+    /// Its values are parsed with `date::parse` and rewritten in ISO 8601,
+    /// e.g. `2024-02-03`, before being decoded by `Row::decode`: they decode
+    /// into `String`s or types parsing ISO dates with `FromStr`. Values not
+    /// matching fail decoding with `Error::Decode`, and are reported by
+    /// `validate` as `IssueKind::InvalidDate`. Empty values are kept empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("id,day\n1,03/02/2024\n2,2024-02-03")
+    ///     .has_header(true)
+    ///     .date_format("day", "%d/%m/%Y");
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.decode::<(u32, String)>().unwrap(), (1, "2024-02-03".to_string()));
+    /// assert!(csv.next().unwrap().unwrap().decode::<(u32, String)>().is_err());
+    /// ```
+    pub fn date_format<C: Into<Column>>(mut self, column: C, format: &str) -> Csv<B> {
+        let headers = self.headers.clone().unwrap_or_default();
+        let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+        self.decode_options_mut().add_date_format(column.into(), format, &headers, duplicates, ignore_case);
+        self
+    }
+
+    /// Sets the binary encoding of a column, by position or header name
+    ///
+    /// Its values are decoded from base64 or hex by `Row::decode` into a
+    /// `Vec<u8>` (or `Option<Vec<u8>>`, empty values being `None`), which
+    /// then reads this single column instead of all remaining ones. Invalid
+    /// values fail decoding with `Error::Decode`.
+    pub fn binary_column<C: Into<Column>>(mut self, column: C, encoding: binary::Encoding) -> Csv<B> {
+        let headers = self.headers.clone().unwrap_or_default();
+        let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+        self.decode_options_mut().add_binary(column.into(), encoding, &headers, duplicates, ignore_case);
+        self
+    }
+
+    /// Decodes the struct field `field` from a column, by header name or position
+    ///
+    /// Other fields are decoded positionally, from the first column on,
+    /// whatever the bound fields: a struct can read some columns by name and
+    /// the unnamed trailing ones by position. A field bound to a name
+    /// missing from headers fails with `Error::UnknownColumn`.
+    ///
+    /// `CsvRecord` structs use `#[csv(name = "...")]` and `#[csv(index = ...)]`
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate quick_csv;
+    /// # extern crate rustc_serialize;
+    /// use rustc_serialize::{Decodable, Decoder};
+    ///
+    /// struct Reading {
+    ///     station: String,
+    ///     first: f64,
+    ///     second: f64,
+    /// }
+    ///
+    /// impl Decodable for Reading {
+    ///     fn decode<D: Decoder>(d: &mut D) -> Result<Reading, D::Error> {
+    ///         d.read_struct("Reading", 3, |d| Ok(Reading {
+    ///             station: d.read_struct_field("station", 0, Decodable::decode)?,
+    ///             first: d.read_struct_field("first", 1, Decodable::decode)?,
+    ///             second: d.read_struct_field("second", 2, Decodable::decode)?,
+    ///         }))
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut csv = quick_csv::Csv::from_string("id,station,,\n1,north,2.5,3")
+    ///     .has_header(true)
+    ///     .bind_field("station", "station")
+    ///     .bind_field("first", 2)
+    ///     .bind_field("second", 3);
+    /// let reading = csv.next().unwrap().unwrap().decode::<Reading>().unwrap();
+    /// assert_eq!((&*reading.station, reading.first, reading.second), ("north", 2.5, 3.));
+    /// # }
+    /// ```
+    pub fn bind_field<C: Into<Column>>(mut self, field: &str, column: C) -> Csv<B> {
+        let headers = self.headers.clone().unwrap_or_default();
+        let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+        self.decode_options_mut().bind_field(field, column.into(), &headers, duplicates, ignore_case);
+        self
+    }
+
+    /// Replaces the value `from` of a column with `to`
+    ///
+    /// The column is referred to by position or, if there is a header, by name.
+    /// Replacement happens as rows are read, before they are decoded or
+    /// written, and only on whole values: `"N/A"` does not match `"N/A "`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("name,sex\nbob,M\nalice,F")
+    ///     .has_header(true)
+    ///     .replace_value("sex", "M", "male")
+    ///     .replace_value("sex", "F", "female");
+    /// let row = csv.nth(1).unwrap().unwrap();
+    /// assert_eq!(row.as_str().unwrap(), "alice,female");
+    /// ```
+    pub fn replace_value<C: Into<Column>>(mut self, column: C, from: &str, to: &str) -> Csv<B> {
+        let headers = self.headers.clone().unwrap_or_default();
+        let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+        self.replacements.get_or_insert_with(Default::default)
+            .add(column.into(), from, to, &headers, duplicates, ignore_case);
+        self
+    }
+
+    /// Sets a digit group separator ignored when decoding numbers
+    ///
+    /// With `' '`, `"1 234 567"` decodes into `1234567`. A `','` separator
+    /// only works on quoted columns or with a different delimiter.
+    pub fn thousands_separator(mut self, separator: char) -> Csv<B> {
+        self.decode_options_mut().thousands_separator = Some(separator);
+        self
+    }
+
+    /// Sets the decimal separator used when decoding numbers
+    ///
+    /// European files often use `','`, generally with a `';'` delimiter:
+    /// `"3,14"` then decodes into `3.14`.
+    ///
+    /// Note: default = '.'
+    pub fn decimal_separator(mut self, separator: char) -> Csv<B> {
+        self.decode_options_mut().decimal_separator = Some(separator).filter(|&s| s != '.');
+        self
+    }
+
+    /// Sets currency symbols stripped, with surrounding whitespaces,
+    /// when decoding numbers
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("$ 12.5,-£3,7 €").currency_symbols(&["$", "€", "£"]);
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.decode::<(f64, i8, u8)>().unwrap(), (12.5, -3, 7));
+    /// ```
+    pub fn currency_symbols<S: AsRef<str>>(mut self, symbols: &[S]) -> Csv<B> {
+        self.decode_options_mut().currency_symbols =
+            symbols.iter().map(|s| s.as_ref().to_string()).filter(|s| !s.is_empty()).collect();
+        self
+    }
+
+    /// Decodes numbers ending with '%'
+    ///
+    /// Without this option, percentages fail to decode into numbers.
+    pub fn percent(mut self, percent: Percent) -> Csv<B> {
+        self.decode_options_mut().percent = Some(percent);
+        self
+    }
+
+    fn decode_options_mut(&mut self) -> &mut DecodeOptions {
+        Arc::make_mut(self.decode_options.get_or_insert_with(Default::default))
+    }
+
+    /// Sets how header names are normalized
+    ///
+    /// Normalized names are returned by `headers` and used to find columns by name.
+    pub fn normalize_headers(mut self, normalization: Normalization) -> Csv<B> {
+        self.normalization = normalization;
+        if let Some(h) = self.headers.take() {
+            self.set_headers(h);
+        }
+        self
+    }
+
+    /// Sets how duplicate header names are handled
+    ///
+    /// With `Duplicates::Error`, the next iteration fails with
+    /// `Error::DuplicateHeader` if headers contain duplicates.
+    ///
+    /// Note: default = `Duplicates::KeepFirst`
+    pub fn duplicate_headers(mut self, duplicates: Duplicates) -> Csv<B> {
+        self.duplicates = duplicates;
+        if let Some(h) = self.headers.take() {
+            self.set_headers(h);
+        }
+        self
+    }
+
+    /// Matches header names ignoring case
+    ///
+    /// `Email`, `EMAIL` and `email` then all refer to the same column, be it
+    /// in `column_index`, column options or `CsvRecord` fields. Headers are
+    /// returned unchanged by `headers`.
+    ///
+    /// Note: default = `false`, names are compared exactly
+    pub fn case_insensitive_headers(mut self, ignore_case: bool) -> Csv<B> {
+        self.ignore_case = ignore_case;
+        if let Some(h) = self.headers.take() {
+            self.set_headers(h);
+        }
+        self
+    }
+
+    /// Renames the header `old` into `new`
+    ///
+    /// `old` is matched against normalized names. Renamed names are returned
+    /// by `headers` and used to find columns by name, rows are untouched.
+    pub fn rename_header<S: Into<String>>(mut self, old: &str, new: S) -> Csv<B> {
+        self.renames.push((old.to_string(), new.into()));
+        if let Some(h) = self.headers.take() {
+            self.set_headers(h);
+        }
+        self
+    }
+
+    /// Gets the position of the column named `name`
+    ///
+    /// Duplicate names are resolved according to `duplicate_headers`,
+    /// with `Duplicates::All` the first position is returned
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.column_indices(name).first().cloned()
+    }
+
+    /// Gets the positions of all columns named `name`
+    ///
+    /// Only returns more than one position with `Duplicates::All`
+    pub fn column_indices(&self, name: &str) -> Vec<usize> {
+        self.headers.as_ref().map_or_else(Vec::new, |h| self.duplicates.find(h, name, self.ignore_case))
+    }
+
     /// Defines whether there is a header or not
     pub fn has_header(mut self, has_header: bool) -> Csv<B> {
         self.has_header = has_header;
@@ -158,17 +878,51 @@ impl<B: BufRead> Csv<B> {
             return h.clone();
         }
         if self.has_header {            
-            if let Some(r) = self.next() {
-                if let Ok(r) = r {
-                    let h = r.decode().ok().unwrap_or_else(Vec::new);
-                    self.headers = Some(h.clone());
-                    return h;
-                }
+            if let Some(Ok(r)) = self.next() {
+                let h = r.columns().map(|c| c.map(|c| c.to_string()).collect())
+                    .unwrap_or_else(|_| Vec::new());
+                self.set_headers(h);
+                return self.headers.clone().unwrap_or_default();
             }
         }
         Vec::new()
     }
 
+    fn set_headers(&mut self, mut headers: Vec<String>) {
+        if self.normalization != Normalization::default() {
+            for h in &mut headers {
+                *h = self.normalization.normalize(h);
+            }
+        }
+        for (old, new) in &self.renames {
+            for h in headers.iter_mut().filter(|h| headers::matches(h, old, self.ignore_case)) {
+                *h = new.clone();
+            }
+        }
+        if self.duplicates == Duplicates::Error {
+            if let Some(name) = headers::find_duplicate(&headers, self.ignore_case) {
+                self.error = Some(Error::DuplicateHeader(name.to_string()));
+            }
+        }
+        if self.decode_options.is_some() {
+            let (duplicates, ignore_case) = (self.duplicates, self.ignore_case);
+            self.decode_options_mut().resolve(&headers, duplicates, ignore_case);
+        }
+        if let Some(ref mut replacements) = self.replacements {
+            replacements.resolve(&headers, self.duplicates, self.ignore_case);
+        }
+        #[cfg(feature = "regex")]
+        for f in &mut self.filters {
+            f.resolve(&headers, self.duplicates, self.ignore_case);
+        }
+        #[cfg(feature = "tracing")]
+        {
+            let _enter = self.span.enter();
+            tracing::debug!(headers = ?headers, "csv headers parsed");
+        }
+        self.headers = Some(headers);
+    }
+
     /// Get column count
     pub fn column_count(&self) -> Option<usize> {
         self.len
@@ -181,14 +935,51 @@ impl<B: BufRead> Csv<B> {
         self.current_line
     }
 
+    /// Gets a `Checkpoint` of the current position
+    ///
+    /// The checkpoint can later be used to resume parsing with
+    /// `Csv::from_checkpoint` instead of starting over from the beginning.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            offset: self.position,
+            records: self.current_line,
+        }
+    }
+
+}
+
+impl<B: BufRead + Seek> Csv<B> {
+
+    /// Creates a Csv resuming from a previously saved `Checkpoint`
+    ///
+    /// The reader is seeked to the checkpoint offset and `current_line`
+    /// continues from the checkpoint record count.
+    ///
+    /// Note: headers are not read again, column count is taken from the first resumed row
+    pub fn from_checkpoint(mut reader: B, checkpoint: Checkpoint) -> Result<Csv<B>> {
+        reader.seek(SeekFrom::Start(checkpoint.offset))?;
+        let mut csv = Csv::from_reader(reader);
+        csv.position += checkpoint.offset;
+        csv.current_line = checkpoint.records;
+        Ok(csv)
+    }
+
 }
 
 impl Csv<BufReader<File>> {
     /// Creates a csv from a file path
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Csv<BufReader<File>>>
     {
-        let reader = BufReader::new(try!(File::open(path)));
-        Ok(Csv::from_reader(reader))
+        let reader = BufReader::new(File::open(path.as_ref())?);
+        let csv = Csv::from_reader(reader);
+        #[cfg(feature = "tracing")]
+        {
+            let path = path.as_ref().display();
+            csv.span.record("path", tracing::field::display(&path));
+            let _enter = csv.span.enter();
+            tracing::debug!(path = %path, "csv file opened");
+        }
+        Ok(csv)
     }
 }
 
@@ -203,180 +994,940 @@ impl<'a> Csv<&'a [u8]> {
 impl<B: BufRead> Iterator for Csv<B> {
     type Item = Result<Row>;
     fn next(&mut self) -> Option<Result<Row>> {
-        if self.exit { return None; }
         let mut buf = Vec::new();
         let mut cols = self.len.map_or_else(Vec::new, Vec::with_capacity);
-        match read_line(&mut self.reader, &mut buf, self.delimiter, &mut cols) {
-            Ok(0) => None,
-            Ok(_n) => {
-                if buf.ends_with(&[b'\r']) {
-                    buf.pop();
-                }
-                cols.push(buf.len());
-                let c = cols.len();
-                if let Some(n) = self.len {
-                    if n != c && !self.flexible {
-                        self.exit = true;
-                        return Some(Err(Error::ColumnMismatch(n, c)));
-                    }
-                } else {
-                    self.len = Some(c);
-                }
-                self.current_line += 1;
+        match self.read_record(&mut buf, &mut cols) {
+            Some(Ok(())) => {
+                let valid_utf8 = self.valid_utf8_read(&buf);
                 Some(Ok(Row {
                     line: buf,
-                    cols: cols,
+                    cols,
+                    decode_options: self.decode_options.clone(),
+                    valid_utf8,
+                    raw: self.raw,
+                    nulls: mem::take(&mut self.nulls),
+                    spans: mem::take(&mut self.spans),
                 }))
+            },
+            Some(Err(e)) => Some(Err(e)),
+            None => None,
+        }
+    }
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Reads the next row into an existing `Row`, reusing its buffers
+    ///
+    /// Returns `Ok(false)` once the csv is exhausted. Unlike iterating, which
+    /// allocates new buffers for every row, this does not allocate once
+    /// `row` buffers are big enough.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("a,b\nc,d");
+    /// let mut row = quick_csv::Row::default();
+    /// let mut count = 0;
+    /// while csv.read_row(&mut row).unwrap() {
+    ///     count += row.len();
+    /// }
+    /// assert_eq!(count, 4);
+    /// ```
+    pub fn read_row(&mut self, row: &mut Row) -> Result<bool> {
+        row.line.clear();
+        row.cols.clear();
+        match self.read_record(&mut row.line, &mut row.cols) {
+            Some(Ok(())) => {
+                row.valid_utf8 = self.valid_utf8_read(&row.line);
+                row.decode_options = self.decode_options.clone();
+                row.raw = self.raw;
+                row.nulls.clone_from(&self.nulls);
+                row.spans.clone_from(&self.spans);
+                Ok(true)
+            },
+            Some(Err(e)) => Err(e),
+            None => Ok(false),
+        }
+    }
+
+    /// Reads the next row at the end of `buf` and `cols`
+    ///
+    /// Column positions pushed into `cols` are relative to the row start.
+    /// On error, `buf` and `cols` are left untouched.
+    fn read_record(&mut self, buf: &mut Vec<u8>, cols: &mut Vec<usize>) -> Option<Result<()>> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        let (base, col_base, position) = (buf.len(), cols.len(), self.position);
+        let result = self.read_record_at(buf, cols, base, col_base);
+        if let Some(Err(_)) = result {
+            self.exit = true;
+            buf.truncate(base);
+            cols.truncate(col_base);
+        }
+        if let Some(ref metrics) = self.metrics {
+            match result {
+                Some(Ok(())) => metrics.record(self.position - position),
+                Some(Err(ref e)) => metrics.error(e),
+                None => (),
             }
-            Err(e) => {
-                self.exit = true;
-                Some(Err(e))
+        }
+        #[cfg(feature = "tracing")]
+        match result {
+            Some(Ok(())) if self.progress_every > 0 && self.current_line.is_multiple_of(self.progress_every) => {
+                tracing::info!(records = self.current_line, bytes = self.position, "csv progress");
             },
+            Some(Err(ref e)) => tracing::warn!(line = self.current_line, error = %e, "csv read error"),
+            _ => (),
+        }
+        result
+    }
+
+    fn read_record_at(&mut self, buf: &mut Vec<u8>, cols: &mut Vec<usize>,
+                      base: usize, col_base: usize) -> Option<Result<()>> {
+        if self.exit { return None; }
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
         }
+        loop {
+            if self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)) {
+                return Some(Err(Error::Cancelled));
+            }
+            let splitter = self.splitter();
+            self.record_offset = self.position;
+            self.source.clear();
+            let source = if self.track_spans { Some(&mut self.source) } else { None };
+            let utf8 = if self.utf8_validation == Utf8Validation::PerChunk && !self.assume_utf8 {
+                Some((&mut self.utf8_chunks, self.position))
+            } else {
+                None
+            };
+            match read_line(&mut self.reader, buf, splitter, cols, self.limits, source, utf8) {
+                Ok(0) => return None,
+                Ok(n) => {
+                    if self.track_spans {
+                        self.spans = source_spans(&self.source, splitter, self.position);
+                    }
+                    self.position += n as u64;
+                    match self.finish_record(buf, cols, base, col_base) {
+                        Ok(true) => return Some(Ok(())),
+                        Ok(false) => {
+                            buf.truncate(base);
+                            cols.truncate(col_base);
+                        },
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// Checks and completes a record freshly read at `base`/`col_base`
+    ///
+    /// Strips the line ending, then applies the empty record, field size
+    /// and column policies, value replacements and filters.
+    ///
+    /// Returns false if the record is filtered out.
+    fn finish_record(&mut self, buf: &mut Vec<u8>, cols: &mut Vec<usize>,
+                     base: usize, col_base: usize) -> Result<bool> {
+        if buf.len() > base && buf.ends_with(b"\r") {
+            buf.pop();
+        }
+        self.nulls.clear();
+        let len = buf.len() - base;
+        if len > 0 || self.empty_record == EmptyRecord::OneColumn {
+            cols.push(len);
+            if self.backslash != Backslash::Plain || self.quote != b'"' || self.null.is_some() {
+                self.normalize(buf, &mut cols[col_base..], base);
+            }
+            if self.limits.field < len {
+                let mut start = 0;
+                for &end in &cols[col_base..] {
+                    if end - start > self.limits.field {
+                        return Err(Error::FieldTooLarge(self.limits.field));
+                    }
+                    start = end + 1;
+                }
+            }
+            let c = cols.len() - col_base;
+            if let Some(n) = self.len {
+                match self.column_policy {
+                    _ if n == c => (),
+                    ColumnPolicy::Flexible => (),
+                    ColumnPolicy::Pad if c < n => {
+                        while cols.len() - col_base < n {
+                            buf.push(self.delimiter);
+                            cols.push(buf.len() - base);
+                        }
+                    },
+                    ColumnPolicy::Truncate if c > n => {
+                        buf.truncate(base + cols[col_base + n - 1]);
+                        cols.truncate(col_base + n);
+                        if let Some(ref mut f) = self.on_truncate {
+                            f(self.current_line + 1, n, c);
+                        }
+                    },
+                    _ => return Err(Error::ColumnMismatch(n, c)),
+                }
+            } else {
+                self.len = Some(c);
+            }
+        }
+        self.current_line += 1;
+        // the header row is never replaced nor filtered
+        if self.has_header && self.headers.is_none() {
+            return Ok(true);
+        }
+        if let Some(ref replacements) = self.replacements {
+            let quoting = replace::Quoting {
+                delimiter: self.delimiter,
+                raw: self.raw,
+                quote: self.quote,
+                escape: self.escape,
+                backslash: self.backslash,
+            };
+            replacements.apply(buf, cols, base, col_base, &quoting);
+        }
+        #[cfg(feature = "regex")]
+        {
+            let (line, cols) = (&buf[base..], &cols[col_base..]);
+            if !self.filters.iter().all(|f| f.keeps(line, cols)) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Gets a splitter for the next record
+    fn splitter(&self) -> Splitter {
+        Splitter::new(self.delimiter).raw(self.raw).terminator(self.terminator).backslash(self.backslash != Backslash::Plain)
+            .quote(self.quote).escape(self.escape)
+    }
+
+    /// Gets the memory budget of a new buffering operation
+    fn budget(&self) -> Budget {
+        Budget::new(self.memory_budget)
+    }
+
+    /// Gets the utf8 validation result of a new row, if already known
+    ///
+    /// `Utf8Validation::PerChunk` rows are validated as a whole, the source
+    /// ranges of rows being only known for the ones of `read_record_at`.
+    fn valid_utf8(&self, line: &[u8]) -> Option<bool> {
+        if self.assume_utf8 {
+            Some(true)
+        } else if self.utf8_validation == Utf8Validation::OnAccess {
+            None
+        } else {
+            Some(::std::str::from_utf8(line).is_ok())
+        }
+    }
+
+    /// Gets the utf8 validation result of a row just read by `read_record_at`
+    fn valid_utf8_read(&mut self, line: &[u8]) -> Option<bool> {
+        // `\x` escapes are unescaped into any byte
+        if self.utf8_validation == Utf8Validation::PerChunk && !self.assume_utf8 && self.backslash == Backslash::Plain {
+            if let Some(valid) = self.utf8_chunks.check(self.record_offset, self.position) {
+                return Some(valid);
+            }
+        }
+        self.valid_utf8(line)
+    }
+
+}
+
+/// Defines how rows with an unexpected column count are handled
+///
+/// The expected column count is the one of the first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnPolicy {
+    /// Rows with a different column count are an `Error::ColumnMismatch`
+    Strict,
+    /// Rows may have any column count
+    Flexible,
+    /// Rows with missing columns are padded with empty columns
+    ///
+    /// Exporters often omit trailing empty columns. Padded rows are returned
+    /// as if they had the missing delimiters. Rows with extra columns are errors.
+    Pad,
+    /// Columns beyond the expected count are dropped
+    ///
+    /// Useful when a free-text last column may contain unquoted delimiters.
+    /// Truncated rows are returned as if they ended before the extra columns.
+    /// Rows with missing columns are errors.
+    Truncate,
+}
+
+/// Defines when rows are checked for valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Validation {
+    /// Rows are validated on each `Row::columns`, `Row::as_str` or `Row::decode` call
+    ///
+    /// Rows only accessed with `Row::bytes_columns` are never validated.
+    OnAccess,
+    /// Rows are validated once, when they are read
+    ///
+    /// Faster when rows are accessed more than once.
+    PerRecord,
+    /// Chunks of the source are validated as they are read, invalid bytes
+    /// being mapped back to the rows holding them
+    ///
+    /// Validates fewer, bigger slices than `PerRecord`, which pays off with
+    /// many short rows.
+    PerChunk,
+}
+
+/// Defines what an empty line is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyRecord {
+    /// An empty line is a row with one empty column
+    ///
+    /// Rows are checked against the column count as any other row
+    OneColumn,
+    /// An empty line is a row without any column
+    ///
+    /// Such rows are `is_empty` and are never checked against the column count
+    NoColumns,
+}
+
+/// Defines which line endings end records
+///
+/// Writers end rows with `\r\n` for `CrLf` and `Any`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    /// `\n`, a preceding `\r` being removed: both `\n` and `\r\n`
+    Lf,
+    /// `\r\n`, read as `Lf`
+    CrLf,
+    /// A bare `\r`, as in old Mac files, `\n` being part of columns
+    Cr,
+    /// Any of `\n`, `\r\n` or a bare `\r`, for files mixing them
+    Any,
+}
+
+impl Terminator {
+    /// Whether `b` ends a record
+    fn ends(&self, b: u8) -> bool {
+        match *self {
+            Terminator::Lf | Terminator::CrLf => b == b'\n',
+            Terminator::Cr => b == b'\r',
+            Terminator::Any => b == b'\n' || b == b'\r',
+        }
+    }
+
+    /// Gets the line ending written
+    pub(crate) fn as_bytes(&self) -> &'static [u8] {
+        match *self {
+            Terminator::Lf => b"\n",
+            Terminator::Cr => b"\r",
+            Terminator::CrLf | Terminator::Any => b"\r\n",
+        }
+    }
+}
+
+/// Numbers of line endings of each kind, as found by `Csv::detect_terminator`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEndings {
+    /// `\n` alone
+    pub lf: usize,
+    /// `\r\n`
+    pub crlf: usize,
+    /// `\r` alone
+    pub cr: usize,
+}
+
+impl LineEndings {
+
+    /// Counts the line endings of `data` outside quoted columns
+    ///
+    /// A `\r` ending `data` is not counted, being possibly followed by `\n`.
+    pub fn sniff(data: &[u8]) -> LineEndings {
+        let mut endings = LineEndings::default();
+        let mut quoted = false;
+        for (i, &b) in data.iter().enumerate() {
+            match b {
+                b'\"' => quoted = !quoted,
+                _ if quoted => (),
+                b'\n' if i > 0 && data[i - 1] == b'\r' => endings.crlf += 1,
+                b'\n' => endings.lf += 1,
+                b'\r' => match data.get(i + 1) {
+                    Some(&b'\n') | None => (),
+                    Some(_) => endings.cr += 1,
+                },
+                _ => (),
+            }
+        }
+        endings
+    }
+
+    /// Whether there are different kinds of line endings
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr].iter().filter(|&&n| n > 0).count() > 1
+    }
+
+    /// Gets the terminator reading all these line endings
+    ///
+    /// `Terminator::Lf` if there is no line ending at all.
+    pub fn terminator(&self) -> Terminator {
+        match (self.lf + self.crlf, self.cr) {
+            (_, 0) => Terminator::Lf,
+            (0, _) => Terminator::Cr,
+            _ => Terminator::Any,
+        }
+    }
+
+}
+
+/// Position of a `Csv` reader in its source
+///
+/// Consists of the byte offset of the next row to read and the number of
+/// rows read so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    offset: u64,
+    records: usize,
+}
+
+impl Checkpoint {
+
+    /// Creates a new checkpoint
+    pub fn new(offset: u64, records: usize) -> Checkpoint {
+        Checkpoint {
+            offset,
+            records,
+        }
+    }
+
+    /// Gets the byte offset of the next row
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Gets the number of rows read before the checkpoint
+    pub fn records(&self) -> usize {
+        self.records
     }
+
 }
 
 /// Row struct used as Csv iterator Item
 ///
 /// Row can be decoded into a Result<T: Decodable>
+#[derive(Clone, Default)]
 pub struct Row {
     line: Vec<u8>,
     cols: Vec<usize>,
+    decode_options: Option<Arc<DecodeOptions>>,
+    /// utf8 validation result, if already known
+    valid_utf8: Option<bool>,
+    /// columns keep their quotes and escapes
+    raw: bool,
+    /// NULL columns, see `is_null`
+    nulls: Vec<usize>,
+    /// source byte ranges of columns, see `span`
+    spans: Vec<(u64, u64)>,
 }
 
 impl Row {
 
+    /// Creates a row out of its columns, e.g. to write computed rows
+    ///
+    /// The row is as if read from a csv with a `,` delimiter: `as_bytes`
+    /// has columns containing `,`, quotes or line endings quoted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::{Csv, Row};
+    ///
+    /// let row = Row::from_fields(["total", "1,5"]);
+    /// assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["total", "1,5"]);
+    /// assert_eq!(row, Csv::from_string("total,\"1,5\"").next().unwrap().unwrap());
+    /// ```
+    pub fn from_fields<I, S>(fields: I) -> Row
+        where I: IntoIterator<Item = S>,
+              S: AsRef<[u8]>
+    {
+        let mut row = Row::default();
+        for field in fields {
+            row.push(field);
+        }
+        row
+    }
+
+    /// Replaces the `i`th column
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no `i`th column.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut row = quick_csv::Csv::from_string("a;b;c").delimiter(b';').next().unwrap().unwrap();
+    /// row.set(1, "x;y");
+    /// row.remove(0);
+    /// row.push("d");
+    /// assert_eq!(row.as_str().unwrap(), "\"x;y\";c;d");
+    /// ```
+    pub fn set<S: AsRef<[u8]>>(&mut self, i: usize, value: S) {
+        assert!(i < self.len(), "no column {} in a row of {} columns", i, self.len());
+        let (start, end) = columns::column_span(&self.cols, i);
+        let field = self.encode(value.as_ref());
+        let len = field.len();
+        self.line.splice(start..end, field);
+        for c in &mut self.cols[i..] {
+            *c = *c + len - (end - start);
+        }
+        self.nulls.retain(|&n| n != i);
+    }
+
+    /// Appends a column
+    pub fn push<S: AsRef<[u8]>>(&mut self, value: S) {
+        if !self.cols.is_empty() {
+            let delimiter = self.delimiter();
+            self.line.push(delimiter);
+        }
+        let field = self.encode(value.as_ref());
+        self.line.extend_from_slice(&field);
+        self.cols.push(self.line.len());
+    }
+
+    /// Removes the `i`th column
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no `i`th column.
+    pub fn remove(&mut self, i: usize) {
+        assert!(i < self.len(), "no column {} in a row of {} columns", i, self.len());
+        let (start, end) = columns::column_span(&self.cols, i);
+        // the column goes with the delimiter following it, or preceding it if last
+        let (start, end) = match i {
+            _ if i + 1 < self.cols.len() => (start, end + 1),
+            0 => (start, end),
+            _ => (start - 1, end),
+        };
+        self.line.drain(start..end);
+        self.cols.remove(i);
+        for c in &mut self.cols[i..] {
+            *c -= end - start;
+        }
+        if i < self.spans.len() {
+            self.spans.remove(i);
+        }
+        self.nulls.retain(|&n| n != i);
+        for n in &mut self.nulls {
+            if *n > i {
+                *n -= 1;
+            }
+        }
+    }
+
+    /// Gets the byte range of the `i`th column in the source, quotes included
+    ///
+    /// Only known for rows read with `Csv::track_spans` and for columns of
+    /// the source, e.g. not for those added by `ColumnPolicy::Pad`. Offsets
+    /// are from the start of the source, its UTF-8 BOM included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("a,b\n1,\"x\"\"y\"\n").track_spans(true);
+    /// csv.next();
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.span(1), Some((6, 12)));
+    /// assert_eq!(row.span(2), None);
+    /// ```
+    pub fn span(&self, i: usize) -> Option<(u64, u64)> {
+        self.spans.get(i).cloned()
+    }
+
+    /// Whether the `i`th column is quoted, e.g. `""` rather than empty
+    ///
+    /// Quoted empty columns are written back quoted by `write_csv` and
+    /// `Writer::write_row`, other columns being quoted only when needed.
+    /// Columns read with `Csv::backslash_escapes` or set with `set` and
+    /// `push` are quoted only if containing the delimiter, a quote or a line
+    /// ending.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let row = quick_csv::Csv::from_string("a,\"\",").next().unwrap().unwrap();
+    /// assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a", "", ""]);
+    /// assert!(row.is_quoted(1) && !row.is_quoted(2));
+    /// assert_eq!(row.to_string(), "a,\"\",");
+    /// ```
+    pub fn is_quoted(&self, i: usize) -> bool {
+        if i >= self.len() {
+            return false;
+        }
+        let (start, end) = columns::column_span(&self.cols, i);
+        end - start > 1 && self.line[start] == b'"'
+    }
+
+    /// Whether the `i`th column is NULL, read as the token of
+    /// `Csv::null_token`, or `\N` with `Csv::backslash_escapes`
+    ///
+    /// NULL columns are empty otherwise.
+    pub fn is_null(&self, i: usize) -> bool {
+        self.nulls.contains(&i)
+    }
+
+    /// Gets the delimiter of the row, `,` if there is a single column
+    fn delimiter(&self) -> u8 {
+        match self.cols.len() {
+            0 | 1 => b',',
+            _ => self.line[self.cols[0]],
+        }
+    }
+
+    /// Gets a column value as stored in `line`, quoted if needed
+    ///
+    /// Quotes surrounding columns are removed when reading them while
+    /// escaped quotes are already collapsed, unless raw.
+    fn encode(&mut self, value: &[u8]) -> Vec<u8> {
+        if self.valid_utf8 != Some(true) || ::std::str::from_utf8(value).is_err() {
+            self.valid_utf8 = None;
+        }
+        let delimiter = self.delimiter();
+        if !value.iter().any(|&b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r') {
+            return value.to_vec();
+        }
+        let mut field = Vec::with_capacity(value.len() + 2);
+        field.push(b'"');
+        for &b in value {
+            if b == b'"' && self.raw {
+                field.push(b'"');
+            }
+            field.push(b);
+        }
+        field.push(b'"');
+        field
+    }
+
     /// Gets an iterator over columns
-    pub fn columns(&self) -> Result<Columns> {
-        match ::std::str::from_utf8(&self.line) {
-            Err(_) => Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData,
-                                    "stream did not contain valid UTF-8"))),
-            Ok(s) => Ok(Columns::new(s, &self.cols)),
+    ///
+    /// Columns are unescaped: surrounding quotes are removed and doubled
+    /// quotes (`""`) are collapsed into a single `"`, unless read with `Csv::raw`.
+    pub fn columns(&self) -> Result<Columns<'_>> {
+        self.as_str().map(|s| Columns::new(s, &self.cols).raw(self.raw))
+    }
+
+    /// Writes the row as csv, without line terminator
+    ///
+    /// Columns are quoted as with `writer::Writer`. Rows read with `Csv::raw`
+    /// keep their columns as read.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let row = quick_csv::Csv::from_string("a,\"b,c\",\"d\"\"e\"").next().unwrap().unwrap();
+    /// let mut out = Vec::new();
+    /// row.write_csv(&mut out, b';').unwrap();
+    /// assert_eq!(out, b"a;b,c;\"d\"\"e\"");
+    /// assert_eq!(row.to_string(), "a,\"b,c\",\"d\"\"e\"");
+    /// ```
+    pub fn write_csv<W: io::Write>(&self, writer: &mut W, delimiter: u8) -> io::Result<()> {
+        if self.len() == 1 && self.cols[0] == 0 {
+            // a lone empty column would be an empty line
+            return writer.write_all(b"\"\"");
+        }
+        for (i, col) in self.bytes_columns().enumerate() {
+            if i > 0 {
+                writer.write_all(&[delimiter])?;
+            }
+            if self.raw {
+                writer.write_all(col)?;
+            } else {
+                let quoted = col.is_empty() && self.is_quoted(i);
+                writer::write_column(writer, col, delimiter, b'"', Escape::Double, quoted)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets an iterator over `(position, column)` pairs
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let row = quick_csv::Csv::from_string("a,b").next().unwrap().unwrap();
+    /// assert_eq!(row.fields().unwrap().collect::<Vec<_>>(), vec![(0, "a"), (1, "b")]);
+    /// ```
+    pub fn fields(&self) -> Result<Enumerate<Columns<'_>>> {
+        self.columns().map(Iterator::enumerate)
+    }
+
+    /// Gets an iterator over `(header, column)` pairs
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("id,name\n1,bob").has_header(true);
+    /// let headers = csv.headers();
+    /// let row = csv.next().unwrap().unwrap();
+    /// let pairs = row.named_columns(&headers).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(pairs, vec![("id", "1"), ("name", "bob")]);
+    /// ```
+    pub fn named_columns<'a>(&'a self, headers: &'a [String]) -> Result<NamedColumns<'a>> {
+        self.columns().map(|columns| NamedColumns::new(headers, columns))
+    }
+
+    /// Gets an iterator over `(position, column)` pairs of bytes
+    pub fn bytes_fields(&self) -> Enumerate<BytesColumns<'_>> {
+        self.bytes_columns().enumerate()
+    }
+
+    /// Gets the entire row content
+    ///
+    /// The line terminator is removed and quoted columns keep their quotes,
+    /// but escaped quotes (`""`) are already collapsed into a single `"`
+    /// unless read with `Csv::raw`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.line
+    }
+
+    /// Gets the entire row content as a `&str`
+    ///
+    /// See `as_bytes`
+    pub fn as_str(&self) -> Result<&str> {
+        let invalid = || Error::Io(io::Error::new(io::ErrorKind::InvalidData,
+                                                  "stream did not contain valid UTF-8"));
+        match self.valid_utf8 {
+            // validated when read or assumed valid with `Csv::assume_utf8`
+            Some(true) => Ok(unsafe { ::std::str::from_utf8_unchecked(&self.line) }),
+            Some(false) => Err(invalid()),
+            None => ::std::str::from_utf8(&self.line).map_err(|_| invalid()),
         }
     }
 
     ///  Creates a new BytesColumns iterator over &[u8]
-    pub fn bytes_columns(&self) -> BytesColumns {
-        BytesColumns::new(&self.line, &self.cols)
+    ///
+    /// Columns are unescaped as with `columns`.
+    pub fn bytes_columns(&self) -> BytesColumns<'_> {
+        BytesColumns::new(&self.line, &self.cols).raw(self.raw)
     }
 
     /// Decode row into custom decodable type
+    ///
+    /// Arrays up to 32 elements, e.g. `[f64; 12]`, decode all the columns of
+    /// rows of exactly their length, see `decode_array` for longer arrays.
+    /// A trailing `Vec` takes all the remaining columns, e.g. `(String, Vec<f64>)`.
     pub fn decode<T: Decodable>(&self) -> Result<T> {
-        let mut columns = try!(self.columns());
+        let mut columns = self.columns()?.with_options(self.decode_options.as_deref());
         Decodable::decode(&mut columns)
     }
 
+    /// Decodes all the columns into an array, whatever its length
+    ///
+    /// Fails if the row does not have exactly `N` columns.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut csv = quick_csv::Csv::from_string("1,2,3\n4,5").flexible(true);
+    /// let row = csv.next().unwrap().unwrap();
+    /// assert_eq!(row.decode_array::<f64, 3>().unwrap(), [1., 2., 3.]);
+    /// assert!(csv.next().unwrap().unwrap().decode_array::<f64, 3>().is_err());
+    /// ```
+    pub fn decode_array<T: Decodable, const N: usize>(&self) -> Result<[T; N]> {
+        if self.len() != N {
+            return Err(Error::Decode(format!("Expected {} columns, found {}", N, self.len())));
+        }
+        let mut columns = self.columns()?.with_options(self.decode_options.as_deref());
+        let mut error = None;
+        let values = [(); N].map(|_| match error {
+            Some(_) => None,
+            None => columns.decode::<T>().map_err(|e| error = Some(e)).ok(),
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(values.map(Option::unwrap)),
+        }
+    }
+
+    /// Parses the `i`th column with `FromStr`
+    ///
+    /// Unlike `decode`, decoding options such as field hooks are not applied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let row = quick_csv::Csv::from_string("a,b,c,42").next().unwrap().unwrap();
+    /// assert_eq!(row.get_as::<u64>(3).unwrap(), 42);
+    /// assert!(row.get_as::<u64>(0).is_err());
+    /// ```
+    pub fn get_as<T>(&self, i: usize) -> Result<T>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        let col = self.columns()?.get(i).ok_or_else(|| Error::Decode(format!(
+            "No column {}, the row has {} columns", i, self.len())))?;
+        col.parse().map_err(|e| Error::Decode(format!("Cannot parse column {} ('{}'): {}", i, col, e)))
+    }
+
     /// Gets columns count
     pub fn len(&self) -> usize {
         self.cols.len()
     }
 
     /// `Row` is empty if there is no columns
+    ///
+    /// This only happens for empty lines read with `EmptyRecord::NoColumns`,
+    /// with the default `EmptyRecord::OneColumn` they have one empty column.
     pub fn is_empty(&self) -> bool {
         self.cols.is_empty()
     }
 
 }
 
-/// Consumes bytes as long as they are within quotes
-/// manages "" as quote escape
-/// returns
-/// - Ok(true) if entirely consumed
-/// - Ok(false) if no issue but it reached end of buffer
-/// - Err(Error::UnescapeQuote) if a quote if found within the column
-macro_rules! consume_quote {
-    ($bytes: expr, $delimiter: expr, $in_quote: expr,
-     $start: expr, $buf: expr, $available: expr, $quote_count: expr) => {
-        $in_quote = false;
-        loop {
-            match $bytes.next() {
-                Some((_, &b'\"')) => {
-                    match $bytes.clone().next() {
-                        Some((i, &b'\"')) => {
-                            $bytes.next(); // escaping quote
-                            $buf.extend_from_slice(&$available[$start..i]);
-                            $start = i + 1;
-                            $quote_count += 1;
-                        },
-                        None | Some((_, &b'\r')) | Some((_, &b'\n')) => break,
-                        Some((_, d)) if *d == $delimiter => break,
-                        Some((_, _)) => return Err(Error::UnescapedQuote),
-                    }
-                },
-                None => {
-                    $in_quote = true;
-                    break;
-                },
-                _ => (),
-            }
+/// Rows are equal if their columns are
+impl PartialEq for Row {
+    fn eq(&self, other: &Row) -> bool {
+        self.bytes_columns().eq(other.bytes_columns())
+    }
+}
+
+impl fmt::Debug for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Row")
+            .field(&self.bytes_columns().map(String::from_utf8_lossy).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Formats the row as csv with a `,` delimiter, see `Row::write_csv`
+///
+/// Invalid UTF-8 is replaced with `U+FFFD`.
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut csv = Vec::with_capacity(self.line.len() + 2);
+        self.write_csv(&mut csv, b',').map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&csv))
+    }
+}
+
+/// Splits a single record into its columns, without a reader
+///
+/// Quotes surrounding columns are removed and doubled quotes are collapsed,
+/// in which case columns are owned. A trailing line ending is ignored but
+/// the line must not contain another record.
+///
+/// # Example
+///
+/// ```rust
+/// let cols = quick_csv::parse_record("a,\"b,c\",\"d \"\"e\"\"\"\n", b',').unwrap();
+/// assert_eq!(cols, vec!["a", "b,c", "d \"e\""]);
+/// ```
+pub fn parse_record(line: &str, delimiter: u8) -> Result<Vec<Cow<'_, str>>> {
+    let mut buf = Vec::with_capacity(line.len());
+    let mut cols = Vec::new();
+    let limits = Limits { record: usize::MAX, field: usize::MAX };
+    let n = read_line(&mut line.as_bytes(), &mut buf, Splitter::new(delimiter), &mut cols, limits, None, None)?;
+    if n < line.len() {
+        return Err(Error::Parse(format!("More than one record in {:?}", line)));
+    }
+    if buf.ends_with(b"\r") {
+        buf.pop();
+    }
+    cols.push(buf.len());
+
+    // without collapsed quotes, columns are at the same offsets in `line`
+    let borrowed = line.as_bytes().starts_with(&buf);
+    (0..cols.len()).map(|i| {
+        let (start, end) = columns::column_range(&buf, &cols, i);
+        match line.get(start..end) {
+            Some(col) if borrowed => Ok(Cow::Borrowed(col)),
+            _ => String::from_utf8(buf[start..end].to_vec()).map(Cow::Owned)
+                .map_err(|e| Error::Parse(format!("Invalid utf8 column: {}", e))),
         }
+    }).collect()
+}
+
+/// Finds the byte ranges of the columns of a record, starting at `offset`
+fn source_spans(source: &[u8], splitter: Splitter, offset: u64) -> Vec<(u64, u64)> {
+    // raw splitting keeps every byte, columns end at their source offsets
+    let (mut buf, mut ends) = (Vec::with_capacity(source.len()), Vec::new());
+    if splitter.raw(true).split(source, &mut buf, 0, &mut ends).is_err() {
+        return Vec::new();
+    }
+    if buf.ends_with(b"\r") {
+        buf.pop();
     }
+    if buf.is_empty() && ends.is_empty() {
+        return vec![(offset, offset)];
+    }
+    ends.push(buf.len());
+    let mut start = 0;
+    ends.iter().map(|&end| {
+        let span = (offset + start as u64, offset + end as u64);
+        start = end + 1;
+        span
+    }).collect()
+}
+
+/// Checks the size of a record being read, and of its last column
+fn check_limits(record: &[u8], cols: &[usize], limits: Limits) -> Result<()> {
+    if record.len() > limits.record {
+        return Err(Error::RecordTooLarge(limits.record));
+    }
+    if record.len() - cols.last().map_or(0, |&c| c + 1) > limits.field {
+        return Err(Error::FieldTooLarge(limits.field));
+    }
+    Ok(())
 }
 
 /// Reads an entire line into memory
-fn read_line<R: BufRead>(r: &mut R, buf: &mut Vec<u8>,
-                         delimiter: u8, cols: &mut Vec<usize>) -> Result<usize>
+///
+/// IO front-end of `parser::Splitter`, which does the actual splitting.
+///
+/// Bytes consumed are also appended to `source`, if any, and chunks read
+/// fed to `utf8`, along with the offset of the line.
+fn read_line<R: BufRead>(r: &mut R, buf: &mut Vec<u8>, mut splitter: Splitter, cols: &mut Vec<usize>,
+                         limits: Limits, mut source: Option<&mut Vec<u8>>,
+                         mut utf8: Option<(&mut Utf8Chunks, u64)>) -> Result<usize>
 {
+    let (base, col_base) = (buf.len(), cols.len());
     let mut read = 0;
-    let mut in_quote = false;
     let mut done = false;
-    let mut quote_count = 0;
     while !done {
         let used = {
             let available = match r.fill_buf() {
-                Ok(n) if n.is_empty() => return Ok(read),
+                Ok([]) => return Ok(read),
                 Ok(n) => n,
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
                 Err(e) => return Err(Error::from(e)),
             };
-
-            let mut bytes = available.iter().enumerate();
-            let mut start = 0;
-
-            // previous buffer was exhausted without exiting from quotes
-            if in_quote {
-                consume_quote!(bytes, delimiter, in_quote, start, buf, available, quote_count);
+            if let Some((ref mut chunks, offset)) = utf8 {
+                chunks.feed(available, offset + read as u64);
             }
-
-            // use a simple loop instead of for loop to allow nested loop
-            let used: usize;
-            loop {
-                match bytes.next() {
-                    Some((i, &b'\"')) => {
-                        if i == 0 || available[i - 1] == delimiter {
-                            consume_quote!(bytes, delimiter, in_quote, start, buf, available, quote_count);
-                        } else {
-                            return Err(Error::UnexpextedQuote);
-                        }
-                    },
-                    Some((i, &b'\n')) => {
-                        done = true;
-                        used = i + 1;
-                        buf.extend_from_slice(&available[start..i]);
-                        break;
-                    },
-                    Some((i, &d)) => {
-                        if d == delimiter { cols.push(read + i - quote_count); }
-                    },
-                    None => {
-                        used = available.len();
-                        buf.extend_from_slice(&available[start..used]);
-                        break;
-                    },
-                }
+            let (used, complete) = splitter.split(available, buf, base, cols)?;
+            if let Some(ref mut source) = source {
+                source.extend_from_slice(&available[..used]);
             }
+            done = complete;
             used
         };
         r.consume(used);
         read += used;
+        check_limits(&buf[base..], &cols[col_base..], limits)?;
+    }
+    if splitter.skip_lf() && r.fill_buf()?.starts_with(b"\n") {
+        // the `\r\n` of `Terminator::Any` spans two chunks
+        r.consume(1);
+        read += 1;
     }
     Ok(read)
 }
 
-fn try_consume_utf8_bom<B: BufRead>(reader: &mut B) -> Result<()> {
-    if try!(reader.fill_buf()).starts_with(UTF8_BOM) {
+fn try_consume_utf8_bom<B: BufRead>(reader: &mut B) -> Result<usize> {
+    if reader.fill_buf()?.starts_with(UTF8_BOM) {
         reader.consume(UTF8_BOM.len());
+        return Ok(UTF8_BOM.len());
     }
 
-    Ok(())
+    Ok(0)
 }
@@ -0,0 +1,105 @@
+//! Throughput metrics
+//!
+//! A `Metrics` implementation set with `Csv::metrics` is updated as rows
+//! are read, to be exported to a monitoring system or logs. `Throughput`
+//! is a ready-made one, made of atomic counters.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use quick_csv::Csv;
+//! use quick_csv::metrics::Throughput;
+//!
+//! let throughput = Arc::new(Throughput::new());
+//! let csv = Csv::from_string("a,b\nc,d\ne").metrics(throughput.clone());
+//! assert_eq!(csv.filter(|r| r.is_ok()).count(), 2);
+//! assert_eq!((throughput.records(), throughput.bytes(), throughput.errors()), (2, 8, 1));
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use error::Error;
+
+/// Receives the reader progress
+///
+/// Methods take `&self` so that metrics can be shared with other threads,
+/// e.g. an exporter.
+pub trait Metrics: Send + Sync {
+    /// A row was read, `bytes` being the source bytes consumed since the
+    /// previous row, including filtered rows
+    fn record(&self, bytes: u64);
+    /// Reading failed
+    fn error(&self, error: &Error);
+}
+
+/// Counts rows, bytes and errors since its creation
+#[derive(Debug)]
+pub struct Throughput {
+    records: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+    start: Instant,
+}
+
+impl Default for Throughput {
+    fn default() -> Throughput {
+        Throughput::new()
+    }
+}
+
+impl Throughput {
+
+    /// Creates new counters, starting the clock
+    pub fn new() -> Throughput {
+        Throughput {
+            records: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// Gets the number of rows read
+    pub fn records(&self) -> u64 {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of bytes read
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of errors
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Gets the time elapsed since the counters were created
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Gets the average number of rows read per second
+    pub fn records_per_sec(&self) -> f64 {
+        self.records() as f64 / self.elapsed().as_secs_f64()
+    }
+
+    /// Gets the average number of bytes read per second
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes() as f64 / self.elapsed().as_secs_f64()
+    }
+
+}
+
+impl Metrics for Throughput {
+    fn record(&self, bytes: u64) {
+        self.records.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn error(&self, _: &Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
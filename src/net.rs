@@ -0,0 +1,109 @@
+//! Network address columns
+//!
+//! Parses columns into `IpAddr`s (`192.168.0.1`, `::1`) and
+//! `SocketAddr`s (`192.168.0.1:8080`, `[::1]:443`), e.g. from network logs.
+//!
+//! Both types implement `FromStr`, so `CsvRecord` fields and `Row::get_as`
+//! parse them as is. `Row::decode` needs the `IpField` and
+//! `SocketAddrField` wrappers. Errors name the column, and the struct field
+//! when decoding a struct.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::net::{IpField, SocketAddrField};
+//!
+//! let row = quick_csv::Csv::from_string("10.0.0.1,[::1]:443").next().unwrap().unwrap();
+//! let (ip, peer) = row.decode::<(IpField, SocketAddrField)>().unwrap();
+//! assert!(ip.is_ipv4() && peer.port() == 443);
+//! assert_eq!(row.get_ip(1).unwrap_err().to_string(),
+//!            "CSV decode error: Column 1: Cannot parse '[::1]:443' as an IP address: invalid IP address syntax");
+//! ```
+
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use rustc_serialize::{Decodable, Decoder};
+
+use error::{Error, Result};
+use Row;
+
+/// Parses `col`, surrounding spaces aside, `what` naming the type in errors
+fn parse<T>(col: &str, what: &str) -> ::std::result::Result<T, String>
+    where T: FromStr, T::Err: ::std::fmt::Display
+{
+    col.trim().parse().map_err(|e| format!("Cannot parse '{}' as {}: {}", col, what, e))
+}
+
+/// An `IpAddr` decoded by `Row::decode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IpField(pub IpAddr);
+
+/// A `SocketAddr` decoded by `Row::decode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddrField(pub SocketAddr);
+
+impl Decodable for IpField {
+    fn decode<D: Decoder>(d: &mut D) -> ::std::result::Result<IpField, D::Error> {
+        let col = d.read_str()?;
+        parse(&col, "an IP address").map(IpField).map_err(|e| d.error(&e))
+    }
+}
+
+impl Decodable for SocketAddrField {
+    fn decode<D: Decoder>(d: &mut D) -> ::std::result::Result<SocketAddrField, D::Error> {
+        let col = d.read_str()?;
+        parse(&col, "a socket address").map(SocketAddrField).map_err(|e| d.error(&e))
+    }
+}
+
+impl Deref for IpField {
+    type Target = IpAddr;
+
+    fn deref(&self) -> &IpAddr {
+        &self.0
+    }
+}
+
+impl Deref for SocketAddrField {
+    type Target = SocketAddr;
+
+    fn deref(&self) -> &SocketAddr {
+        &self.0
+    }
+}
+
+impl From<IpField> for IpAddr {
+    fn from(field: IpField) -> IpAddr {
+        field.0
+    }
+}
+
+impl From<SocketAddrField> for SocketAddr {
+    fn from(field: SocketAddrField) -> SocketAddr {
+        field.0
+    }
+}
+
+impl Row {
+
+    /// Parses the `i`th column as an IPv4 or IPv6 address
+    pub fn get_ip(&self, i: usize) -> Result<IpAddr> {
+        self.get_parsed(i, "an IP address")
+    }
+
+    /// Parses the `i`th column as an IP address and a port
+    pub fn get_socket_addr(&self, i: usize) -> Result<SocketAddr> {
+        self.get_parsed(i, "a socket address")
+    }
+
+    fn get_parsed<T>(&self, i: usize, what: &str) -> Result<T>
+        where T: FromStr, T::Err: ::std::fmt::Display
+    {
+        let col = self.columns()?.get(i).ok_or_else(|| Error::Decode(format!(
+            "No column {}, the row has {} columns", i, self.len())))?;
+        parse(col, what).map_err(|e| Error::Decode(format!("Column {}: {}", i, e)))
+    }
+
+}
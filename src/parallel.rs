@@ -0,0 +1,187 @@
+//! Parallel decoding module
+//!
+//! Requires the `rayon` feature.
+//!
+//! Rows are still parsed sequentially, but decoding, which dominates for
+//! wide structs, is spread across the rayon thread pool.
+//!
+//! `decode_parallel` parses on the calling thread and collects all values,
+//! while `decode_pipeline` parses on a thread of its own and streams values
+//! as they are decoded.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::vec;
+
+use rayon;
+use rayon::prelude::*;
+use rustc_serialize::Decodable;
+
+use error::Result;
+use {Csv, Row};
+
+/// Number of rows parsed before being decoded in parallel
+const BATCH_SIZE: usize = 4096;
+
+impl<B: BufRead + Send> Csv<B> {
+
+    /// Decodes all remaining rows in parallel
+    ///
+    /// Batches of rows are decoded on the rayon thread pool while the next
+    /// batch is being parsed. Decoded values are returned in input order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let csv = quick_csv::Csv::from_string("a,1\nb,2\nc,3");
+    /// let rows = csv.decode_parallel::<(String, u8)>().unwrap();
+    /// assert_eq!(rows[2], ("c".to_string(), 3));
+    /// ```
+    pub fn decode_parallel<T: Decodable + Send>(mut self) -> Result<Vec<T>> {
+        let mut decoded = Vec::new();
+        // a batch is decoded while the next one is parsed
+        let max_bytes = self.memory_budget / 2;
+        let mut batch = self.next_batch(max_bytes)?;
+        while !batch.is_empty() {
+            let (next, values) = rayon::join(
+                || self.next_batch(max_bytes),
+                || batch.par_iter().map(Row::decode).collect::<Result<Vec<T>>>());
+            decoded.extend(values?);
+            batch = next?;
+        }
+        Ok(decoded)
+    }
+
+    /// Decodes all remaining rows in parallel, as an iterator
+    ///
+    /// Rows are parsed on a dedicated thread and decoded by batches on the
+    /// rayon thread pool. Values are yielded in input order, with at most
+    /// `batches` batches being decoded or waiting to be yielded, which bounds
+    /// memory use whatever the consumer speed.
+    ///
+    /// An error ends the iteration.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let csv = quick_csv::Csv::from_string("a,1\nb,2\nc,3");
+    /// let rows = csv.decode_pipeline::<(String, u8)>(4).collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(rows[2], ("c".to_string(), 3));
+    /// ```
+    pub fn decode_pipeline<T>(mut self, batches: usize) -> Pipeline<T>
+        where B: 'static,
+              T: Decodable + Send + 'static
+    {
+        let batches = batches.max(1);
+        let (slots, released) = mpsc::sync_channel(batches);
+        let (sender, results) = mpsc::channel();
+        let max_bytes = self.memory_budget / batches;
+        thread::spawn(move || {
+            for seq in 0.. {
+                // waits for the consumer to take an earlier batch
+                if slots.send(()).is_err() {
+                    break;
+                }
+                let batch = match self.next_batch(max_bytes) {
+                    Ok(ref batch) if batch.is_empty() => break,
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        let _ = sender.send((seq, vec![Err(e)]));
+                        break;
+                    },
+                };
+                let sender = sender.clone();
+                rayon::spawn(move || {
+                    let values = batch.iter().map(Row::decode).collect();
+                    let _ = sender.send((seq, values));
+                });
+            }
+        });
+        Pipeline {
+            results,
+            released,
+            waiting: BTreeMap::new(),
+            next: 0,
+            values: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    /// Reads up to `BATCH_SIZE` rows, stopping once they reach `max_bytes`
+    fn next_batch(&mut self, max_bytes: usize) -> Result<Vec<Row>> {
+        let mut rows = Vec::with_capacity(BATCH_SIZE);
+        let mut bytes = 0;
+        while rows.len() < BATCH_SIZE && bytes <= max_bytes {
+            match self.next() {
+                Some(row) => {
+                    let row = row?;
+                    bytes += row.line.len();
+                    rows.push(row);
+                },
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+}
+
+/// Iterator over values decoded in parallel, in input order
+///
+/// Created with `Csv::decode_pipeline`.
+pub struct Pipeline<T> {
+    results: Receiver<(usize, Vec<Result<T>>)>,
+    /// frees a batch slot for the parsing thread
+    released: Receiver<()>,
+    /// batches decoded ahead of the next one
+    waiting: BTreeMap<usize, Vec<Result<T>>>,
+    /// sequence number of the next batch
+    next: usize,
+    values: vec::IntoIter<Result<T>>,
+    done: bool,
+}
+
+impl<T> Pipeline<T> {
+
+    /// Gets the next batch in input order, `None` once all are yielded
+    fn next_batch(&mut self) -> Option<Vec<Result<T>>> {
+        while !self.waiting.contains_key(&self.next) {
+            match self.results.recv() {
+                Ok((seq, values)) => self.waiting.insert(seq, values),
+                // all batches were sent
+                Err(_) => return None,
+            };
+        }
+        let batch = self.waiting.remove(&self.next);
+        self.next += 1;
+        let _ = self.released.recv();
+        batch
+    }
+
+}
+
+impl<T> Iterator for Pipeline<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            match self.values.next() {
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+                Some(value) => return Some(value),
+                None => (),
+            }
+            match self.next_batch() {
+                Some(values) => self.values = values.into_iter(),
+                None => self.done = true,
+            }
+        }
+    }
+}
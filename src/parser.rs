@@ -0,0 +1,491 @@
+//! Push parser module
+//!
+//! Sans-IO core of the crate: record splitting, quote handling and column
+//! offsets, shared by all readers including `Csv`. Data is fed chunk by
+//! chunk, for custom IO stacks driving the parsing themselves. `Parser` emits events
+//! to a `Handler` while `RowParser` queues complete rows, and can be driven
+//! by a non-blocking reader.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::parser::{Handler, Parser};
+//!
+//! #[derive(Default)]
+//! struct Collect(Vec<Vec<String>>, Vec<String>);
+//!
+//! impl Handler for Collect {
+//!     fn field(&mut self, field: &[u8]) {
+//!         self.1.push(String::from_utf8_lossy(field).into_owned());
+//!     }
+//!     fn record(&mut self) {
+//!         self.0.push(::std::mem::take(&mut self.1));
+//!     }
+//! }
+//!
+//! let mut parser = Parser::new();
+//! let mut collect = Collect::default();
+//! parser.feed(b"a,\"b", &mut collect).unwrap();
+//! parser.feed(b"\"\"c\"\nd,e", &mut collect).unwrap();
+//! parser.finish(&mut collect).unwrap();
+//! assert_eq!(collect.0, vec![vec!["a", "b\"c"], vec!["d", "e"]]);
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::mem;
+
+use columns::column_range;
+use error::{Error, Result};
+use writer::Escape;
+use {Row, Terminator};
+
+/// Position within a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// nothing read yet in the current column
+    FieldStart,
+    /// within a column not starting with a quote
+    Unquoted,
+    /// within a quoted column
+    Quoted,
+    /// after a quote within a quoted column, either closing or escaping
+    QuoteInQuoted,
+    /// after a backslash escaping the next byte
+    Escaped,
+    /// after a backslash within a quoted column
+    EscapedInQuoted,
+}
+
+/// Record splitting state machine, keeping its state between chunks
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Splitter {
+    state: State,
+    delimiter: u8,
+    /// escaped quotes are kept as is
+    raw: bool,
+    terminator: Terminator,
+    /// the last record ended with `\r`, a following `\n` belongs to it
+    skip_lf: bool,
+    /// backslashes escape the next byte and quotes are plain bytes
+    backslash: bool,
+    quote: u8,
+    /// how quotes are escaped within quoted columns
+    escape: Escape,
+}
+
+impl Splitter {
+
+    pub fn new(delimiter: u8) -> Splitter {
+        Splitter {
+            state: State::FieldStart,
+            delimiter,
+            raw: false,
+            terminator: Terminator::Lf,
+            skip_lf: false,
+            backslash: false,
+            quote: b'"',
+            escape: Escape::Double,
+        }
+    }
+
+    /// Sets the byte surrounding quoted columns
+    pub fn quote(mut self, quote: u8) -> Splitter {
+        self.quote = quote;
+        self
+    }
+
+    /// Defines how quotes are escaped within quoted columns
+    pub fn escape(mut self, escape: Escape) -> Splitter {
+        self.escape = escape;
+        self
+    }
+
+    /// Defines whether backslashes escape the next byte, quotes being plain
+    /// bytes, the escapes being kept
+    pub fn backslash(mut self, backslash: bool) -> Splitter {
+        self.backslash = backslash;
+        self
+    }
+
+    /// Defines which bytes end records
+    pub fn terminator(mut self, terminator: Terminator) -> Splitter {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Whether the last record ended with a `\r` possibly followed by a `\n`
+    /// not split yet
+    pub fn skip_lf(&self) -> bool {
+        self.skip_lf
+    }
+
+    /// Defines whether escaped quotes are kept doubled
+    pub fn raw(mut self, raw: bool) -> Splitter {
+        self.raw = raw;
+        self
+    }
+
+    /// Whether the last byte split is within a quoted column
+    pub fn in_quotes(&self) -> bool {
+        self.state == State::Quoted || self.state == State::EscapedInQuoted
+    }
+
+    /// Goes back to the start of a record
+    pub fn reset(&mut self) {
+        self.state = State::FieldStart;
+    }
+
+    /// Parses `input` up to the end of a record
+    ///
+    /// The record content is appended to `buf`, keeping delimiters and quotes
+    /// surrounding columns but collapsing escaped quotes, unless raw. Column ends but the last one
+    /// are appended to `cols`, relative to `base`. The line ending is not
+    /// part of the record.
+    ///
+    /// Returns the number of bytes consumed and whether the record is complete.
+    pub fn split(&mut self, input: &[u8], buf: &mut Vec<u8>, base: usize,
+                 cols: &mut Vec<usize>) -> Result<(usize, bool)> {
+        self.try_split(input, buf, base, cols).map_err(|(_, e)| e)
+    }
+
+    /// Skips `input` up to the end of a record, quotes aside
+    ///
+    /// Returns the number of bytes consumed, if the record ends within `input`.
+    pub fn skip_record(&mut self, input: &[u8]) -> Option<usize> {
+        let start = usize::from(mem::replace(&mut self.skip_lf, false) && input.first() == Some(&b'\n'));
+        let i = start + input[start..].iter().position(|&b| self.terminator.ends(b))?;
+        self.state = State::FieldStart;
+        if input[i] == b'\r' && self.terminator == Terminator::Any {
+            if i + 1 == input.len() {
+                self.skip_lf = true;
+            } else if input[i + 1] == b'\n' {
+                return Some(i + 2);
+            }
+        }
+        Some(i + 1)
+    }
+
+    /// Same as `split`, errors coming with the position of the byte at fault
+    fn try_split(&mut self, input: &[u8], buf: &mut Vec<u8>, base: usize,
+                 cols: &mut Vec<usize>) -> ::std::result::Result<(usize, bool), (usize, Error)> {
+        let (delimiter, quote) = (self.delimiter, self.quote);
+        let mut start = 0;
+        if mem::replace(&mut self.skip_lf, false) && input.first() == Some(&b'\n') {
+            start = 1;
+        }
+        for (i, &b) in input.iter().enumerate().skip(start) {
+            match self.state {
+                State::Escaped => self.state = State::Unquoted,
+                State::EscapedInQuoted => self.state = State::Quoted,
+                _ if self.backslash && b == b'\\' => self.state = State::Escaped,
+                State::Quoted if b == b'\\' && self.escape == Escape::Backslash => {
+                    // escaping backslash, dropped
+                    if !self.raw {
+                        buf.extend_from_slice(&input[start..i]);
+                        start = i + 1;
+                    }
+                    self.state = State::EscapedInQuoted;
+                },
+                State::Quoted => {
+                    if b == quote {
+                        self.state = State::QuoteInQuoted;
+                    }
+                },
+                State::QuoteInQuoted if b == quote => {
+                    // escaped quote, the first one is kept
+                    if !self.raw {
+                        buf.extend_from_slice(&input[start..i]);
+                        start = i + 1;
+                    }
+                    self.state = State::Quoted;
+                },
+                _ if self.terminator.ends(b) => {
+                    buf.extend_from_slice(&input[start..i]);
+                    self.state = State::FieldStart;
+                    if b == b'\r' && self.terminator == Terminator::Any {
+                        if i + 1 == input.len() {
+                            self.skip_lf = true;
+                        } else if input[i + 1] == b'\n' {
+                            return Ok((i + 2, true));
+                        }
+                    }
+                    return Ok((i + 1, true));
+                },
+                State::QuoteInQuoted if b == b'\r' => self.state = State::Unquoted,
+                State::QuoteInQuoted if b != delimiter => {
+                    return Err((i, Error::UnescapedQuote));
+                },
+                State::FieldStart if b == quote && !self.backslash => self.state = State::Quoted,
+                State::Unquoted if b == quote && !self.backslash => return Err((i, Error::UnexpextedQuote)),
+                _ if b == delimiter => {
+                    // the delimiter is kept, columns end at its position
+                    buf.extend_from_slice(&input[start..i]);
+                    cols.push(buf.len() - base);
+                    start = i;
+                    self.state = State::FieldStart;
+                },
+                _ => self.state = State::Unquoted,
+            }
+        }
+        buf.extend_from_slice(&input[start..]);
+        Ok((input.len(), false))
+    }
+
+}
+
+/// Receives the events of a `Parser`
+pub trait Handler {
+    /// A column of the current record, without surrounding quotes and
+    /// with escaped quotes collapsed
+    fn field(&mut self, field: &[u8]);
+    /// The end of the current record
+    fn record(&mut self);
+}
+
+/// Push parser, fed with chunks of csv data
+///
+/// Events of a record are emitted once the record is complete: all its
+/// fields, then the record end.
+pub struct Parser {
+    splitter: Splitter,
+    /// content of the current record
+    buf: Vec<u8>,
+    /// column ends of the current record
+    cols: Vec<usize>,
+    /// whether some bytes of the current record were fed
+    pending: bool,
+    /// the current record failed, its bytes are skipped up to its end
+    skipping: bool,
+}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
+}
+
+impl Parser {
+
+    /// Creates a new parser
+    ///
+    /// Note: default delimiter = ','
+    pub fn new() -> Parser {
+        Parser {
+            splitter: Splitter::new(b','),
+            buf: Vec::new(),
+            cols: Vec::new(),
+            pending: false,
+            skipping: false,
+        }
+    }
+
+    /// Sets a new delimiter
+    pub fn delimiter(mut self, delimiter: u8) -> Parser {
+        self.splitter.delimiter = delimiter;
+        self
+    }
+
+    /// Parses a new chunk, emitting events of all records it completes
+    ///
+    /// On error, the rest of the current record is skipped up to its line
+    /// end, quotes aside, and parsing goes on with the next record. The
+    /// first error is returned once the whole chunk is parsed.
+    pub fn feed<H: Handler>(&mut self, input: &[u8], handler: &mut H) -> Result<()> {
+        self.split_records(input, |buf, cols| emit(buf, cols, handler))
+    }
+
+    /// Emits events of the last record, if not terminated by a newline
+    ///
+    /// Fails with an `Error::Parse` if the record ends within a quoted
+    /// column.
+    pub fn finish<H: Handler>(&mut self, handler: &mut H) -> Result<()> {
+        self.finish_record(|buf, cols| emit(buf, cols, handler))
+    }
+
+    /// Splits `input` into records, calling `f` with each complete record
+    /// and its column ends
+    fn split_records<F>(&mut self, mut input: &[u8], mut f: F) -> Result<()>
+        where F: FnMut(&mut Vec<u8>, &mut Vec<usize>)
+    {
+        let mut error = None;
+        while !input.is_empty() {
+            if self.skipping {
+                match self.splitter.skip_record(input) {
+                    Some(used) => input = &input[used..],
+                    None => break,
+                }
+                self.skipping = false;
+                continue;
+            }
+            match self.splitter.try_split(input, &mut self.buf, 0, &mut self.cols) {
+                Ok((used, done)) => {
+                    input = &input[used..];
+                    self.pending = true;
+                    if done {
+                        self.complete(&mut f);
+                    }
+                },
+                Err((i, e)) => {
+                    self.clear();
+                    self.skipping = true;
+                    input = &input[i..];
+                    error = error.or(Some(e));
+                },
+            }
+        }
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Calls `f` with the last record, if not terminated by a newline
+    fn finish_record<F>(&mut self, mut f: F) -> Result<()>
+        where F: FnMut(&mut Vec<u8>, &mut Vec<usize>)
+    {
+        if self.skipping {
+            // already reported by `split_records`
+            self.skipping = false;
+            self.clear();
+        } else if self.pending && self.splitter.in_quotes() {
+            self.clear();
+            return Err(Error::Parse("Quoted column not closed at the end of the input".to_string()));
+        } else if self.pending {
+            self.complete(&mut f);
+        }
+        Ok(())
+    }
+
+    fn complete<F>(&mut self, f: &mut F)
+        where F: FnMut(&mut Vec<u8>, &mut Vec<usize>)
+    {
+        if self.buf.ends_with(b"\r") {
+            self.buf.pop();
+        }
+        self.cols.push(self.buf.len());
+        f(&mut self.buf, &mut self.cols);
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.splitter.reset();
+        self.buf.clear();
+        self.cols.clear();
+        self.pending = false;
+    }
+
+}
+
+fn emit<H: Handler>(buf: &[u8], cols: &[usize], handler: &mut H) {
+    for i in 0..cols.len() {
+        let (start, end) = column_range(buf, cols, i);
+        handler.field(&buf[start..end]);
+    }
+    handler.record();
+}
+
+/// Incremental parser, fed with partial input and yielding complete rows
+///
+/// Parsing state is kept between `feed` calls, rows are queued as soon as
+/// they are complete and popped by iterating. The iterator returning `None`
+/// only means no complete row is available yet.
+///
+/// # Example
+///
+/// ```
+/// use quick_csv::parser::RowParser;
+///
+/// let mut parser = RowParser::new();
+/// parser.feed(b"a,\"b").unwrap();
+/// assert!(parser.next().is_none());
+/// parser.feed(b"\nc\"\n").unwrap();
+/// let rows = parser.by_ref().map(|r| r.columns().unwrap().collect::<Vec<_>>().join("|"))
+///     .collect::<Vec<_>>();
+/// assert_eq!(rows, vec!["a|b\nc"]);
+/// ```
+#[derive(Default)]
+pub struct RowParser {
+    parser: Parser,
+    rows: VecDeque<Row>,
+    eof: bool,
+}
+
+impl RowParser {
+
+    /// Creates a new incremental parser
+    ///
+    /// Note: default delimiter = ','
+    pub fn new() -> RowParser {
+        RowParser::default()
+    }
+
+    /// Sets a new delimiter
+    pub fn delimiter(mut self, delimiter: u8) -> RowParser {
+        self.parser = self.parser.delimiter(delimiter);
+        self
+    }
+
+    /// Parses a new chunk, queuing all rows it completes
+    ///
+    /// On error, the current record is dropped, rows completed before and
+    /// after it being queued as with `Parser::feed`.
+    pub fn feed(&mut self, input: &[u8]) -> Result<()> {
+        let rows = &mut self.rows;
+        self.parser.split_records(input, |buf, cols| rows.push_back(row(buf, cols)))
+    }
+
+    /// Queues the last row, if not terminated by a newline
+    ///
+    /// Fails with an `Error::Parse` if the row ends within a quoted column.
+    pub fn finish(&mut self) -> Result<()> {
+        let rows = &mut self.rows;
+        self.eof = true;
+        self.parser.finish_record(|buf, cols| rows.push_back(row(buf, cols)))
+    }
+
+    /// Feeds all data currently available from a non-blocking reader
+    ///
+    /// Reads until the reader would block or reaches its end, in which case
+    /// the last row is queued and `true` is returned. Interrupted reads are
+    /// retried.
+    pub fn read_from<R: Read>(&mut self, reader: &mut R) -> Result<bool> {
+        let mut chunk = [0; 8 * 1024];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => {
+                    self.finish()?;
+                    return Ok(true);
+                },
+                Ok(n) => self.feed(&chunk[..n])?,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+    }
+
+    /// Whether `finish` was called or the reader reached its end
+    pub fn is_finished(&self) -> bool {
+        self.eof
+    }
+
+    /// Gets the number of complete rows not yet popped
+    pub fn available(&self) -> usize {
+        self.rows.len()
+    }
+
+}
+
+fn row(buf: &mut Vec<u8>, cols: &mut Vec<usize>) -> Row {
+    Row {
+        line: mem::take(buf),
+        cols: mem::take(cols),
+        ..Row::default()
+    }
+}
+
+impl Iterator for RowParser {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        self.rows.pop_front()
+    }
+}
@@ -0,0 +1,256 @@
+//! Data quality profiling
+//!
+//! Counts per column the empty fields, the fields holding a null token,
+//! such as `NA` or `null`, and the rows too short to have the column, in a
+//! single pass over the remaining rows.
+//!
+//! Distinct values may be counted too, exactly or approximately with a
+//! `HyperLogLog` using little memory whatever the cardinality.
+//!
+//! Rows of different lengths require a flexible column policy.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//!
+//! let mut csv = Csv::from_string("a,b\n1,NA\n,2\n3").has_header(true).flexible(true);
+//! let profile = csv.profile().null_tokens(&["NA"]).run().unwrap();
+//! assert_eq!(profile.rows, 3);
+//! assert_eq!(profile.columns[0].empty, 1);
+//! assert_eq!((profile.columns[1].null_tokens, profile.columns[1].missing), (1, 1));
+//! ```
+
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
+use std::mem;
+
+use budget::Budget;
+use error::Result;
+use Csv;
+
+/// How distinct values are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    /// Keeps every distinct value, within the `Csv::memory_budget`
+    Exact,
+    /// Estimates with a `HyperLogLog` of the given precision (4 to 16)
+    Approximate(u8),
+}
+
+/// Approximate distinct counter
+///
+/// Uses `2^precision` bytes, with a standard error of about
+/// `1.04 / sqrt(2^precision)`, i.e. 0.8% with a precision of 14.
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+
+    /// Creates an empty counter, `precision` being clamped between 4 and 16
+    pub fn new(precision: u8) -> HyperLogLog {
+        let precision = precision.clamp(4, 16);
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Adds a value
+    pub fn insert<T: Hash + ?Sized>(&mut self, value: &T) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - self.precision)) as usize;
+        // position of the first set bit of the remaining bits, from 1
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() as u8 + 1;
+        if self.registers[register] < rank {
+            self.registers[register] = rank;
+        }
+    }
+
+    /// Estimates the number of distinct values added
+    pub fn count(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1. + 1.079 / m),
+        };
+        let sum = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum::<f64>();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+
+}
+
+/// Distinct values of a column seen so far
+enum Distinct {
+    Exact(HashSet<String>),
+    Approximate(HyperLogLog),
+}
+
+impl Distinct {
+    fn new(cardinality: Cardinality) -> Distinct {
+        match cardinality {
+            Cardinality::Exact => Distinct::Exact(HashSet::new()),
+            Cardinality::Approximate(precision) => Distinct::Approximate(HyperLogLog::new(precision)),
+        }
+    }
+
+    fn insert(&mut self, value: &str, budget: &mut Budget) -> Result<()> {
+        match *self {
+            Distinct::Exact(ref mut set) => {
+                if !set.contains(value) {
+                    budget.charge(value.len() + mem::size_of::<String>())?;
+                    set.insert(value.to_string());
+                }
+            },
+            Distinct::Approximate(ref mut hll) => hll.insert(value),
+        }
+        Ok(())
+    }
+
+    fn count(&self) -> usize {
+        match *self {
+            Distinct::Exact(ref set) => set.len(),
+            Distinct::Approximate(ref hll) => hll.count(),
+        }
+    }
+}
+
+/// Statistics of a column
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnProfile {
+    /// header name, if any
+    pub name: Option<String>,
+    /// fields with a value, neither empty nor a null token
+    pub values: usize,
+    /// empty fields
+    pub empty: usize,
+    /// fields equal to a null token
+    pub null_tokens: usize,
+    /// rows without this column
+    pub missing: usize,
+    /// number of distinct values, if counted
+    pub distinct: Option<usize>,
+}
+
+impl ColumnProfile {
+    /// Gets the number of rows without a value, for whatever reason
+    pub fn nulls(&self) -> usize {
+        self.empty + self.null_tokens + self.missing
+    }
+}
+
+/// Statistics of all columns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// number of rows, excluding the header
+    pub rows: usize,
+    /// statistics by column position
+    pub columns: Vec<ColumnProfile>,
+}
+
+/// Profiling pass over the remaining rows of a csv
+///
+/// Created with `Csv::profile`.
+pub struct Profiler<'a, B: 'a + BufRead> {
+    csv: &'a mut Csv<B>,
+    null_tokens: Vec<String>,
+    cardinality: Option<Cardinality>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Profiles the remaining rows
+    pub fn profile(&mut self) -> Profiler<'_, B> {
+        Profiler {
+            csv: self,
+            null_tokens: Vec::new(),
+            cardinality: None,
+        }
+    }
+
+}
+
+impl<'a, B: BufRead> Profiler<'a, B> {
+
+    /// Sets the values counted as nulls, compared exactly
+    ///
+    /// Note: default = none, only empty fields are nulls
+    pub fn null_tokens<S: AsRef<str>>(mut self, tokens: &[S]) -> Profiler<'a, B> {
+        self.null_tokens = tokens.iter().map(|t| t.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Counts the distinct values of every column, nulls excluded
+    ///
+    /// Exact counts fail with `Error::MemoryBudget` once the distinct
+    /// values exceed the `Csv::memory_budget`.
+    ///
+    /// Note: default = not counted
+    pub fn distinct(mut self, cardinality: Cardinality) -> Profiler<'a, B> {
+        self.cardinality = Some(cardinality);
+        self
+    }
+
+    /// Reads all remaining rows
+    pub fn run(self) -> Result<Profile> {
+        let mut budget = self.csv.budget();
+        let mut distinct: Vec<Distinct> = Vec::new();
+        let headers = self.csv.headers();
+        let mut profile = Profile {
+            rows: 0,
+            columns: headers.into_iter()
+                .map(|h| ColumnProfile { name: Some(h), ..ColumnProfile::default() })
+                .collect(),
+        };
+        for row in &mut *self.csv {
+            let row = row?;
+            for (i, col) in row.columns()?.enumerate() {
+                if profile.columns.len() <= i {
+                    // all previous rows miss the new column
+                    profile.columns.push(ColumnProfile { missing: profile.rows, ..ColumnProfile::default() });
+                }
+                let column = &mut profile.columns[i];
+                if col.is_empty() {
+                    column.empty += 1;
+                } else if self.null_tokens.iter().any(|t| t == col) {
+                    column.null_tokens += 1;
+                } else {
+                    column.values += 1;
+                    if let Some(cardinality) = self.cardinality {
+                        if distinct.len() <= i {
+                            distinct.resize_with(i + 1, || Distinct::new(cardinality));
+                        }
+                        distinct[i].insert(col, &mut budget)?;
+                    }
+                }
+            }
+            for column in profile.columns.iter_mut().skip(row.len()) {
+                column.missing += 1;
+            }
+            profile.rows += 1;
+        }
+        if let Some(cardinality) = self.cardinality {
+            for (i, column) in profile.columns.iter_mut().enumerate() {
+                column.distinct = Some(distinct.get(i).map_or_else(|| Distinct::new(cardinality).count(), Distinct::count));
+            }
+        }
+        Ok(profile)
+    }
+
+}
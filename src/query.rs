@@ -0,0 +1,437 @@
+//! SQL-like queries
+//!
+//! Runs a small subset of SQL over a csv with headers and writes the
+//! result as a new csv:
+//!
+//! ```text
+//! select <* | column, ...>
+//!     [where <condition>]
+//!     [order by <column> [asc | desc], ...]
+//!     [limit <count>]
+//! ```
+//!
+//! Conditions compare columns and literals (`'text'` or numbers) with
+//! `=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`, combined with `and`, `or`, `not`
+//! and parentheses. Values are compared as numbers when both sides are
+//! numbers, as text otherwise. `order by` sorts numbers by value before
+//! any text, sorted bytewise. Column names with spaces or keywords are
+//! written within double quotes.
+//!
+//! Rows are streamed unless there is an `order by`, which needs to keep all
+//! selected rows, within the `Csv::memory_budget` and spilled to disk past
+//! it with `Csv::spill_to`.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::query::Query;
+//! use quick_csv::writer::Writer;
+//!
+//! let mut csv = Csv::from_string("name,age\nbob,42\nalice,37\neve,12").has_header(true);
+//! let query = Query::parse("select name where age >= 18 order by age limit 5").unwrap();
+//! let mut writer = Writer::from_writer(Vec::new());
+//! assert_eq!(query.run(&mut csv, &mut writer).unwrap(), 2);
+//! assert_eq!(writer.into_inner().unwrap(), &b"name\nalice\nbob\n"[..]);
+//! ```
+
+use std::cmp::Ordering;
+use std::io::{BufRead, Write};
+use std::str;
+
+use columns::Columns;
+use error::{Error, Result};
+use spill::Record;
+use transform::column_index;
+use writer::Writer;
+use Csv;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// bare or double quoted name
+    Name(String, bool),
+    Text(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &["<=", ">=", "!=", "<>", "=", "<", ">", ",", "(", ")", "*"];
+
+pub(crate) fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '\"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    // a doubled quote is an escaped quote
+                    Some((_, q)) if q == c => match chars.peek() {
+                        Some(&(_, q)) if q == c => {
+                            chars.next();
+                            s.push(c);
+                        },
+                        _ => break,
+                    },
+                    Some((_, ch)) => s.push(ch),
+                    None => return Err(Error::Parse(format!("Unclosed quote at {}", i))),
+                }
+            }
+            tokens.push(if c == '\'' { Token::Text(s) } else { Token::Name(s, true) });
+        } else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let mut s = String::new();
+            s.push(c);
+            chars.next();
+            while let Some(&(_, ch)) = chars.peek() {
+                if !ch.is_ascii_digit() && ch != '.' && ch != 'e' && ch != 'E' { break; }
+                s.push(ch);
+                chars.next();
+            }
+            tokens.push(Token::Number(s));
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut s = String::new();
+            while let Some(&(_, ch)) = chars.peek() {
+                if !ch.is_alphanumeric() && ch != '_' { break; }
+                s.push(ch);
+                chars.next();
+            }
+            tokens.push(Token::Name(s, false));
+        } else {
+            let symbol = SYMBOLS.iter().find(|s| query[i..].starts_with(*s))
+                .ok_or_else(|| Error::Parse(format!("Unexpected '{}' at {}", c, i)))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// A column, referred to by name `C = String` or position `C = usize`, or a literal
+#[derive(Debug, Clone, PartialEq)]
+enum Operand<C> {
+    Column(C),
+    Literal(String),
+}
+
+impl Operand<usize> {
+    fn value<'a>(&'a self, cols: &Columns<'a>) -> &'a str {
+        match *self {
+            Operand::Column(i) => cols.get(i).unwrap_or(""),
+            Operand::Literal(ref s) => s,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr<C> {
+    Or(Box<Expr<C>>, Box<Expr<C>>),
+    And(Box<Expr<C>>, Box<Expr<C>>),
+    Not(Box<Expr<C>>),
+    Compare(Operand<C>, Op, Operand<C>),
+}
+
+impl Expr<String> {
+    /// Replaces column names with their positions
+    fn compile<F: FnMut(&str) -> Result<usize>>(&self, f: &mut F) -> Result<Expr<usize>> {
+        let operand = |o: &Operand<String>, f: &mut F| -> Result<Operand<usize>> {
+            Ok(match *o {
+                Operand::Column(ref name) => Operand::Column(f(name)?),
+                Operand::Literal(ref s) => Operand::Literal(s.clone()),
+            })
+        };
+        Ok(match *self {
+            Expr::Or(ref a, ref b) => Expr::Or(Box::new(a.compile(f)?), Box::new(b.compile(f)?)),
+            Expr::And(ref a, ref b) => Expr::And(Box::new(a.compile(f)?), Box::new(b.compile(f)?)),
+            Expr::Not(ref e) => Expr::Not(Box::new(e.compile(f)?)),
+            Expr::Compare(ref a, op, ref b) => Expr::Compare(operand(a, f)?, op, operand(b, f)?),
+        })
+    }
+}
+
+impl Expr<usize> {
+    fn eval(&self, cols: &Columns) -> bool {
+        match *self {
+            Expr::Or(ref a, ref b) => a.eval(cols) || b.eval(cols),
+            Expr::And(ref a, ref b) => a.eval(cols) && b.eval(cols),
+            Expr::Not(ref e) => !e.eval(cols),
+            Expr::Compare(ref a, op, ref b) => op.matches(compare(a.value(cols), b.value(cols))),
+        }
+    }
+}
+
+/// Compares as numbers if both values are numbers, as text otherwise
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+/// Orders rows with a total order: numbers first, by value, then text
+fn sort_order(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Recursive descent parser over tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.peek().cloned().ok_or_else(|| Error::Parse("Unexpected end of query".into()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is the keyword `keyword`
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Name(ref n, false)) if n.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(Error::Parse(format!("Expected '{}', found {:?}", keyword, self.peek())))
+        }
+    }
+
+    fn symbol(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Name(ref n, quoted) if quoted || !is_keyword(n) => Ok(n.clone()),
+            t => Err(Error::Parse(format!("Expected a column name, found {:?}", t))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr<String>> {
+        let mut e = self.and()?;
+        while self.keyword("or") {
+            e = Expr::Or(Box::new(e), Box::new(self.and()?));
+        }
+        Ok(e)
+    }
+
+    fn and(&mut self) -> Result<Expr<String>> {
+        let mut e = self.not()?;
+        while self.keyword("and") {
+            e = Expr::And(Box::new(e), Box::new(self.not()?));
+        }
+        Ok(e)
+    }
+
+    fn not(&mut self) -> Result<Expr<String>> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.symbol("(") {
+            let e = self.or()?;
+            if !self.symbol(")") {
+                return Err(Error::Parse(format!("Expected ')', found {:?}", self.peek())));
+            }
+            return Ok(e);
+        }
+        let a = self.operand()?;
+        let op = match self.next()? {
+            Token::Symbol("=") => Op::Eq,
+            Token::Symbol("!=") | Token::Symbol("<>") => Op::Ne,
+            Token::Symbol("<") => Op::Lt,
+            Token::Symbol("<=") => Op::Le,
+            Token::Symbol(">") => Op::Gt,
+            Token::Symbol(">=") => Op::Ge,
+            t => return Err(Error::Parse(format!("Expected a comparison, found {:?}", t))),
+        };
+        Ok(Expr::Compare(a, op, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand<String>> {
+        match self.peek() {
+            Some(Token::Text(_)) | Some(Token::Number(_)) => match self.next()? {
+                Token::Text(s) | Token::Number(s) => Ok(Operand::Literal(s)),
+                _ => unreachable!(),
+            },
+            _ => self.name().map(Operand::Column),
+        }
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    ["select", "where", "order", "by", "asc", "desc", "limit", "and", "or", "not"]
+        .iter().any(|k| name.eq_ignore_ascii_case(k))
+}
+
+/// A parsed query, see the module documentation for the syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    /// selected columns, all if `None`
+    select: Option<Vec<String>>,
+    filter: Option<Expr<String>>,
+    /// columns to sort by, with `true` for descending order
+    order_by: Vec<(String, bool)>,
+    limit: Option<usize>,
+}
+
+impl Query {
+
+    /// Parses a query
+    pub fn parse(query: &str) -> Result<Query> {
+        let mut p = Parser { tokens: tokenize(query)?, pos: 0 };
+        p.expect_keyword("select")?;
+        let select = if p.symbol("*") {
+            None
+        } else {
+            let mut columns = vec![p.name()?];
+            while p.symbol(",") {
+                columns.push(p.name()?);
+            }
+            Some(columns)
+        };
+        let filter = if p.keyword("where") { Some(p.or()?) } else { None };
+        let mut order_by = Vec::new();
+        if p.keyword("order") {
+            p.expect_keyword("by")?;
+            loop {
+                let name = p.name()?;
+                let desc = p.keyword("desc");
+                if !desc {
+                    p.keyword("asc");
+                }
+                order_by.push((name, desc));
+                if !p.symbol(",") { break; }
+            }
+        }
+        let limit = if p.keyword("limit") {
+            match p.next()? {
+                Token::Number(ref n) => Some(n.parse().map_err(|_| {
+                    Error::Parse(format!("Invalid limit {}", n))
+                })?),
+                t => return Err(Error::Parse(format!("Expected a limit, found {:?}", t))),
+            }
+        } else {
+            None
+        };
+        if let Some(t) = p.peek() {
+            return Err(Error::Parse(format!("Unexpected {:?}", t)));
+        }
+        Ok(Query { select, filter, order_by, limit })
+    }
+
+    /// Runs the query over the remaining rows of `csv`
+    ///
+    /// Column names are resolved against the csv headers, the selected
+    /// headers are written first.
+    ///
+    /// Returns the number of rows written, excluding the header.
+    pub fn run<B, W>(&self, csv: &mut Csv<B>, writer: &mut Writer<W>) -> Result<usize>
+        where B: BufRead, W: Write
+    {
+        let headers = csv.headers();
+        let select = match self.select {
+            Some(ref names) => names.iter().map(|n| column_index(csv, n)).collect::<Result<Vec<_>>>()?,
+            None => (0..headers.len()).collect(),
+        };
+        let filter = match self.filter {
+            Some(ref e) => Some(e.compile(&mut |n| column_index(csv, n))?),
+            None => None,
+        };
+        let order_by = self.order_by.iter()
+            .map(|&(ref n, desc)| column_index(csv, n).map(|i| (i, desc)))
+            .collect::<Result<Vec<_>>>()?;
+        let limit = self.limit.unwrap_or(usize::MAX);
+
+        writer.write(select.iter().map(|&i| &headers[i]))?;
+        let mut count = 0;
+        // sort keys come first in records, then the selected values
+        let keys = order_by.len();
+        let mut sorter = csv.sorter(|a: &Record, b: &Record| {
+            a[..keys].iter().zip(&b[..keys]).zip(&order_by)
+                .map(|((a, b), &(_, desc))| {
+                    let (a, b) = (str::from_utf8(a).unwrap_or(""), str::from_utf8(b).unwrap_or(""));
+                    if desc { sort_order(b, a) } else { sort_order(a, b) }
+                })
+                .find(|&o| o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        for row in csv {
+            if order_by.is_empty() && count >= limit {
+                break;
+            }
+            let row = row?;
+            let cols = row.columns()?;
+            if !filter.as_ref().is_none_or(|f| f.eval(&cols)) {
+                continue;
+            }
+            let values = select.iter().map(|&i| cols.get(i).unwrap_or(""));
+            if order_by.is_empty() {
+                writer.write(values)?;
+                count += 1;
+            } else {
+                let key_values = order_by.iter().map(|&(i, _)| cols.get(i).unwrap_or(""));
+                sorter.push(key_values.chain(values).map(|v| v.as_bytes().to_vec()).collect())?;
+            }
+        }
+
+        if !order_by.is_empty() {
+            for record in sorter.finish()?.take(limit) {
+                writer.write(&record?[keys..])?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+}
@@ -0,0 +1,275 @@
+//! Struct records
+//!
+//! `CsvRecord` types decode rows positionally, their columns being found
+//! once by header name. With the `derive` feature, `#[derive(CsvRecord)]`
+//! implements it for structs with named fields, with optional attributes:
+//! - `#[csv(name = "Cust. ID")]`: header of the field, else the field name
+//! - `#[csv(alias = "cust_id")]`: another accepted header, may be repeated
+//! - `#[csv(index = 3)]`: column position, whatever the headers
+//! - `#[csv(default)]`: empty or missing values give `Default::default()`,
+//!   or the result of a function with `#[csv(default = "path")]`. On the
+//!   struct, fields default to those of the struct `Default`
+//! - `#[csv(date_format = "%d/%m/%Y")]`: parses with `FromFormat`, e.g.
+//!   chrono dates with the `chrono` feature
+//! - `#[csv(rest)]` on a last `Vec` field: all columns after those of other
+//!   fields, e.g. a variable number of measurements
+//! - `#[csv(crate = "path")]` on the struct: path of this crate, if renamed
+//!
+//! `#[serde(rename = "...")]`, `#[serde(alias = "...")]` and
+//! `#[serde(default)]` are honored the same way, `csv` attributes taking
+//! precedence. Rows shorter than the headers, read with a flexible column
+//! policy, then decode with the defaults of their missing trailing columns.
+//!
+//! Columns without a field are skipped, wherever they are: adding a column
+//! upstream does not break decoding.
+//!
+//! Without headers, fields are taken in order. `Option` fields are `None`
+//! if empty or missing, other fields are parsed with `FromStr`.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use quick_csv::CsvRecord;
+//!
+//! #[derive(CsvRecord)]
+//! struct Order {
+//!     #[csv(name = "Cust. ID")]
+//!     id: u64,
+//!     total: Option<f64>,
+//! }
+//!
+//! let csv = quick_csv::Csv::from_string("total,Cust. ID\n2.5,1").has_header(true);
+//! let orders = csv.records::<Order>().collect::<Result<Vec<_>, _>>().unwrap();
+//! assert_eq!((orders[0].id, orders[0].total), (1, Some(2.5)));
+//! # }
+//! ```
+
+use std::fmt::Display;
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use columns::Columns;
+use error::{Error, Result};
+use headers;
+use {Csv, Row};
+
+/// A struct decoded from a row
+pub trait CsvRecord: Sized {
+    /// Finds the column of every field given the headers, if any
+    ///
+    /// `None` columns are missing from the csv.
+    fn columns(headers: &Headers) -> Result<Vec<Option<usize>>>;
+
+    /// Decodes a row given the columns found by `columns`
+    fn from_row(row: &Row, columns: &[Option<usize>]) -> Result<Self>;
+}
+
+/// Header names, as matched by `CsvRecord::columns`
+#[derive(Debug, Clone, Copy)]
+pub struct Headers<'a> {
+    names: &'a [String],
+    ignore_case: bool,
+    strict: bool,
+}
+
+impl<'a> Headers<'a> {
+
+    /// Creates headers, names being compared ignoring case or not
+    pub fn new(names: &'a [String], ignore_case: bool) -> Headers<'a> {
+        Headers { names, ignore_case, strict: false }
+    }
+
+    /// Defines whether optional fields must have a column too
+    pub fn strict(mut self, strict: bool) -> Headers<'a> {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether optional fields must have a column too
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Gets the header names, empty if the csv has no header
+    pub fn names(&self) -> &'a [String] {
+        self.names
+    }
+
+    /// Whether the csv has no header
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Gets the position of the first column named `name`
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|h| headers::matches(h, name, self.ignore_case))
+    }
+
+}
+
+/// A type parsed with a format, from `#[csv(date_format = "...")]`
+pub trait FromFormat: Sized {
+    /// Parses `value` according to `format`
+    fn from_format(value: &str, format: &str) -> ::std::result::Result<Self, String>;
+}
+
+#[cfg(feature = "chrono")]
+impl FromFormat for ::chrono::NaiveDate {
+    fn from_format(value: &str, format: &str) -> ::std::result::Result<Self, String> {
+        ::chrono::NaiveDate::parse_from_str(value, format).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromFormat for ::chrono::NaiveDateTime {
+    fn from_format(value: &str, format: &str) -> ::std::result::Result<Self, String> {
+        ::chrono::NaiveDateTime::parse_from_str(value, format).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromFormat for ::chrono::NaiveTime {
+    fn from_format(value: &str, format: &str) -> ::std::result::Result<Self, String> {
+        ::chrono::NaiveTime::parse_from_str(value, format).map_err(|e| e.to_string())
+    }
+}
+
+/// Finds the column of a field, used by derived `CsvRecord`s
+///
+/// `names` are the field header followed by its aliases.
+#[doc(hidden)]
+pub fn column(headers: &Headers, names: &[&str], position: usize,
+              index: Option<usize>, optional: bool) -> Result<Option<usize>> {
+    if index.is_some() {
+        return Ok(index);
+    }
+    if headers.is_empty() {
+        return Ok(Some(position));
+    }
+    match names.iter().filter_map(|n| headers.find(n)).min() {
+        Some(i) => Ok(Some(i)),
+        None if optional && !headers.strict => Ok(None),
+        None => Err(Error::UnknownColumn(names[0].to_string())),
+    }
+}
+
+/// Gets the value of a field, `None` if missing
+#[doc(hidden)]
+pub fn value<'a>(cols: &Columns<'a>, column: Option<usize>) -> Option<&'a str> {
+    column.and_then(|i| cols.get(i))
+}
+
+/// Gets the columns after all `columns`, for `#[csv(rest)]` fields
+#[doc(hidden)]
+pub fn rest<'a>(cols: &Columns<'a>, columns: &[Option<usize>]) -> Vec<&'a str> {
+    let start = columns.iter().filter_map(|&c| c).max().map_or(0, |c| c + 1);
+    (start..).map_while(|i| cols.get(i)).collect()
+}
+
+#[doc(hidden)]
+pub fn required<'a>(value: Option<&'a str>, field: &str) -> Result<&'a str> {
+    value.ok_or_else(|| Error::Decode(format!("Missing column for field '{}'", field)))
+}
+
+#[doc(hidden)]
+pub fn parse<T>(value: &str, field: &str) -> Result<T>
+    where T: FromStr, T::Err: Display
+{
+    value.parse().map_err(|e| Error::Decode(format!("Cannot parse '{}' for field '{}': {}", value, field, e)))
+}
+
+#[doc(hidden)]
+pub fn parse_format<T: FromFormat>(value: &str, format: &str, field: &str) -> Result<T> {
+    T::from_format(value, format).map_err(|e| Error::Decode(format!(
+        "Cannot parse '{}' with format '{}' for field '{}': {}", value, format, field, e)))
+}
+
+/// Iterator over rows decoded as `T`
+///
+/// Created with `Csv::records`.
+pub struct Records<B: BufRead, T> {
+    csv: Csv<B>,
+    /// columns of fields, once headers are read
+    columns: Option<Vec<Option<usize>>>,
+    /// set once the columns cannot be found
+    done: bool,
+    /// headers must match the fields exactly
+    strict: bool,
+    record: PhantomData<T>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining rows decoded as `T`
+    pub fn records<T: CsvRecord>(self) -> Records<B, T> {
+        Records {
+            csv: self,
+            columns: None,
+            done: false,
+            strict: false,
+            record: PhantomData,
+        }
+    }
+
+}
+
+impl<B: BufRead, T> Records<B, T> {
+
+    /// Rejects headers not matching the fields exactly
+    ///
+    /// The first iteration fails with `Error::UnknownColumn` if a field,
+    /// even optional, has no column, or with `Error::UnexpectedColumn` if a
+    /// column has no field, columns of a `#[csv(rest)]` field aside.
+    /// There is no check without headers.
+    ///
+    /// Note: default = `false`, missing optional fields and extra columns are ignored
+    pub fn strict(mut self, strict: bool) -> Records<B, T> {
+        self.strict = strict;
+        self
+    }
+
+}
+
+impl<B: BufRead, T: CsvRecord> Records<B, T> {
+
+    /// Finds the columns of the fields given the headers
+    fn find_columns(&mut self) -> Result<Vec<Option<usize>>> {
+        let headers = self.csv.headers();
+        let columns = T::columns(&Headers::new(&headers, self.csv.ignore_case).strict(self.strict))?;
+        if self.strict {
+            // only a `rest` field has no column once strict
+            let rest = columns.iter().any(Option::is_none);
+            let last = columns.iter().filter_map(|&c| c).max();
+            let used = |i: usize| columns.contains(&Some(i)) || rest && last.is_none_or(|last| i > last);
+            let extra = headers.iter().enumerate().find(|&(i, _)| !used(i));
+            if let Some((_, name)) = extra {
+                return Err(Error::UnexpectedColumn(name.clone()));
+            }
+        }
+        Ok(columns)
+    }
+
+}
+
+impl<B: BufRead, T: CsvRecord> Iterator for Records<B, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.done {
+            return None;
+        }
+        if self.columns.is_none() {
+            match self.find_columns() {
+                Ok(columns) => self.columns = Some(columns),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        let columns = self.columns.as_ref().unwrap();
+        self.csv.next().map(|row| row.and_then(|row| T::from_row(&row, columns)))
+    }
+}
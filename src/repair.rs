@@ -0,0 +1,293 @@
+//! Repair mode for broken quoting
+//!
+//! Reads csv files with unbalanced quotes on a best-effort basis, reporting
+//! every fix applied:
+//! - stray quotes are kept as literal quotes: within unquoted columns, or
+//!   within quoted ones when not followed by a delimiter or the line end
+//! - quoted columns left open are closed at the end of their first line,
+//!   if they are not closed within `max_lines` lines or before the end of
+//!   the file. The following lines are read as new records.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::repair::FixKind;
+//!
+//! let mut repair = Csv::from_string("a,b\"c\n\"d,e\nf,g").flexible(true).repair();
+//! let rows = repair.by_ref()
+//!     .map(|r| r.unwrap().columns().unwrap().collect::<Vec<_>>().join("|"))
+//!     .collect::<Vec<_>>();
+//! assert_eq!(rows, vec!["a|b\"c", "d,e", "f|g"]);
+//! let fixes = repair.fixes().iter().map(|f| f.kind).collect::<Vec<_>>();
+//! assert_eq!(fixes, vec![FixKind::StrayQuote { column: 1 }, FixKind::UnclosedQuote { column: 0 }]);
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::mem;
+
+use error::{Error, Result};
+use {Csv, Row};
+
+/// Kind of a fix applied to a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixKind {
+    /// A stray quote was kept as a literal quote
+    StrayQuote {
+        /// position of the column
+        column: usize,
+    },
+    /// An unterminated quoted column was closed at the end of its line
+    UnclosedQuote {
+        /// position of the column
+        column: usize,
+    },
+}
+
+/// A fix applied to a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fix {
+    /// record number, starting at 1 and including the header
+    pub record: usize,
+    /// line number of the record start, starting at 1 on the first repaired line
+    pub line: usize,
+    /// what was fixed
+    pub kind: FixKind,
+}
+
+/// Position within a column of a record being repaired
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+/// A record repaired from its raw bytes
+struct Repaired {
+    line: Vec<u8>,
+    cols: Vec<usize>,
+    fixes: Vec<FixKind>,
+}
+
+/// Splits `raw`, without its line ending, leniently
+///
+/// As with `parser::Splitter`, the end of the last column is not pushed.
+///
+/// Returns `None` if a quoted column is still open at the end and `close`
+/// is false.
+fn repair_record(raw: &[u8], delimiter: u8, close: bool) -> Option<Repaired> {
+    let mut repaired = Repaired {
+        line: Vec::with_capacity(raw.len() + 2),
+        cols: Vec::new(),
+        fixes: Vec::new(),
+    };
+    let mut state = State::FieldStart;
+    // whether the current column was opened with a quote which must be closed
+    let mut opened = false;
+    for &b in raw {
+        let column = repaired.cols.len();
+        match state {
+            State::Quoted if b == b'"' => {
+                state = State::QuoteInQuoted;
+                continue;
+            },
+            State::Quoted => (),
+            State::QuoteInQuoted if b == b'"' => state = State::Quoted,
+            State::QuoteInQuoted if b != delimiter => {
+                // the quoted column goes on, as in `"a "b" c"`
+                repaired.fixes.push(FixKind::StrayQuote { column });
+                repaired.line.push(b'"');
+                state = State::Quoted;
+            },
+            State::FieldStart if b == b'"' => {
+                state = State::Quoted;
+                opened = true;
+            },
+            State::Unquoted if b == b'"' => repaired.fixes.push(FixKind::StrayQuote { column }),
+            _ if b == delimiter => {
+                if opened {
+                    repaired.line.push(b'"');
+                }
+                repaired.cols.push(repaired.line.len());
+                state = State::FieldStart;
+                opened = false;
+            },
+            _ => state = State::Unquoted,
+        }
+        repaired.line.push(b);
+    }
+    if state == State::Quoted {
+        if !close {
+            return None;
+        }
+        repaired.fixes.push(FixKind::UnclosedQuote { column: repaired.cols.len() });
+    }
+    if opened {
+        repaired.line.push(b'"');
+    }
+    Some(repaired)
+}
+
+/// Iterator over repaired rows
+///
+/// Created with `Csv::repair`.
+pub struct Repair<B: BufRead> {
+    csv: Csv<B>,
+    max_lines: usize,
+    /// lines read but not yet part of a record
+    pending: VecDeque<Vec<u8>>,
+    /// number of lines read so far
+    lines: usize,
+    fixes: Vec<Fix>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Iterates the remaining rows, repairing unbalanced quotes
+    ///
+    /// Rows go through the reader options as usual, only quote errors are
+    /// repaired. Other errors are returned without ending the iteration.
+    pub fn repair(self) -> Repair<B> {
+        Repair {
+            csv: self,
+            max_lines: 8,
+            pending: VecDeque::new(),
+            lines: 0,
+            fixes: Vec::new(),
+        }
+    }
+
+}
+
+impl<B: BufRead> Repair<B> {
+
+    /// Sets the maximum number of lines a quoted column may span
+    ///
+    /// Quoted columns still open after `max_lines` lines are closed at the
+    /// end of their first line.
+    ///
+    /// Note: default = 8
+    pub fn max_lines(mut self, max_lines: usize) -> Repair<B> {
+        self.max_lines = max_lines.max(1);
+        self
+    }
+
+    /// Gets all fixes applied so far
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+
+    /// Gets the headers, repaired if read from the first record
+    pub fn headers(&mut self) -> Vec<String> {
+        if self.csv.has_header && self.csv.headers.is_none() {
+            match self.read_row() {
+                Ok(Some(row)) => self.set_headers(row),
+                Ok(None) => (),
+                Err(e) => self.csv.error = Some(e),
+            }
+        }
+        self.csv.headers.clone().unwrap_or_default()
+    }
+
+    fn set_headers(&mut self, row: Row) {
+        let headers = row.columns().map(|c| c.map(|c| c.to_string()).collect())
+            .unwrap_or_else(|_| Vec::new());
+        self.csv.set_headers(headers);
+    }
+
+    /// Gets the next line, pending ones first
+    fn next_line(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some(line) = self.pending.pop_front() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        loop {
+            match self.csv.reader.read_until(b'\n', &mut line) {
+                Ok(0) => return Ok(None),
+                Ok(n) => {
+                    self.csv.position += n as u64;
+                    return Ok(Some(line));
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Reads and repairs the lines of the next record
+    fn next_record(&mut self) -> Result<Option<(usize, Repaired)>> {
+        let delimiter = self.csv.delimiter;
+        let start = self.lines + 1;
+        let mut lines: Vec<Vec<u8>> = Vec::new();
+        let mut raw = Vec::new();
+        loop {
+            let line = match self.next_line()? {
+                Some(line) => line,
+                None if lines.is_empty() => return Ok(None),
+                None => break,
+            };
+            raw.extend_from_slice(&line);
+            lines.push(line);
+            let content = strip_line_ending(&raw);
+            if let Some(repaired) = repair_record(content, delimiter, false) {
+                self.lines += lines.len();
+                return Ok(Some((start, repaired)));
+            }
+            if lines.len() >= self.max_lines {
+                break;
+            }
+        }
+        // closes the quote on the first line and reads the others again
+        self.lines += 1;
+        for line in lines.drain(1..).rev() {
+            self.pending.push_front(line);
+        }
+        let repaired = repair_record(strip_line_ending(&lines[0]), delimiter, true);
+        Ok(repaired.map(|r| (start, r)))
+    }
+
+    /// Reads the next row kept by filters, including the header row
+    fn read_row(&mut self) -> Result<Option<Row>> {
+        if let Some(e) = self.csv.error.take() {
+            return Err(e);
+        }
+        loop {
+            let (line, mut repaired) = match self.next_record()? {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            let kept = self.csv.finish_record(&mut repaired.line, &mut repaired.cols, 0, 0);
+            let record = self.csv.current_line;
+            self.fixes.extend(repaired.fixes.drain(..).map(|kind| Fix { record, line, kind }));
+            if kept? {
+                return Ok(Some(Row {
+                    valid_utf8: self.csv.valid_utf8(&repaired.line),
+                    line: repaired.line,
+                    cols: repaired.cols,
+                    decode_options: self.csv.decode_options.clone(),
+                    raw: false,
+                    nulls: mem::take(&mut self.csv.nulls),
+                    spans: Vec::new(),
+                }));
+            }
+        }
+    }
+
+}
+
+fn strip_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+impl<B: BufRead> Iterator for Repair<B> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        self.headers();
+        self.read_row().transpose()
+    }
+}
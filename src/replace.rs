@@ -0,0 +1,156 @@
+//! Value replacement module
+//!
+//! Replaces configured column values as records are read
+
+use std::borrow::Cow;
+
+use decode::Column;
+use dialect::{unescape_column, Backslash};
+use headers::Duplicates;
+use writer::{write_column, write_escaped, Escape};
+
+/// A value and its replacement
+type Rule = (Vec<u8>, Vec<u8>);
+
+/// How the columns of a record are stored
+///
+/// Records are stored unescaped, quoted with `"` when needed, unless raw:
+/// they then keep the quotes and escapes of the source.
+pub(crate) struct Quoting {
+    pub delimiter: u8,
+    pub raw: bool,
+    pub quote: u8,
+    pub escape: Escape,
+    pub backslash: Backslash,
+}
+
+impl Quoting {
+
+    /// Gets the value of a stored column
+    fn value<'a>(&self, field: &'a [u8]) -> Cow<'a, [u8]> {
+        let quote = if self.raw { self.quote } else { b'"' };
+        if self.raw && self.backslash != Backslash::Plain {
+            return Cow::Owned(unescape_column(field, self.backslash));
+        }
+        if field.len() < 2 || field[0] != quote {
+            return Cow::Borrowed(field);
+        }
+        let inner = &field[1..field.len() - 1];
+        if !self.raw {
+            return Cow::Borrowed(inner);
+        }
+        let mut value = Vec::with_capacity(inner.len());
+        let mut escaped = false;
+        for &b in inner {
+            let escaping = match self.escape {
+                Escape::Double => b == quote,
+                Escape::Backslash => b == b'\\',
+            };
+            if escaping && !escaped {
+                escaped = true;
+                continue;
+            }
+            escaped = false;
+            value.push(b);
+        }
+        Cow::Owned(value)
+    }
+
+    /// Appends `value` to `line`, stored as read columns are
+    fn push(&self, line: &mut Vec<u8>, value: &[u8]) {
+        // writing to a Vec never fails
+        if !self.raw {
+            let delimiter = self.delimiter;
+            let quote = value.iter().any(|&b| b == delimiter || b == b'"' || b == b'\r' || b == b'\n');
+            if quote { line.push(b'"'); }
+            line.extend_from_slice(value);
+            if quote { line.push(b'"'); }
+        } else if self.backslash != Backslash::Plain {
+            write_escaped(line, value, self.delimiter, self.backslash).unwrap();
+        } else {
+            write_column(line, value, self.delimiter, self.quote, self.escape, false).unwrap();
+        }
+    }
+
+}
+
+/// Replacement rules, applied on all records
+#[derive(Clone, Default)]
+pub(crate) struct Replacements {
+    /// rules as defined by the user
+    rules: Vec<(Column, Rule)>,
+    /// rules by column position, once headers are known
+    resolved: Vec<Vec<Rule>>,
+}
+
+impl Replacements {
+
+    /// Adds a new rule
+    pub fn add(&mut self, column: Column, from: &str, to: &str,
+               headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.rules.push((column, (from.as_bytes().to_vec(), to.as_bytes().to_vec())));
+        self.resolve(headers, duplicates, ignore_case);
+    }
+
+    /// Finds the column position of all rules
+    pub fn resolve(&mut self, headers: &[String], duplicates: Duplicates, ignore_case: bool) {
+        self.resolved.clear();
+        for (column, rule) in &self.rules {
+            for i in column.indices(headers, duplicates, ignore_case) {
+                if self.resolved.len() <= i {
+                    self.resolved.resize(i + 1, Vec::new());
+                }
+                self.resolved[i].push(rule.clone());
+            }
+        }
+    }
+
+    /// Gets the replacement of the stored `field` of the `i`th column,
+    /// first rule wins
+    ///
+    /// The value of the field is only read for columns with rules.
+    fn find(&self, i: usize, field: &[u8], quoting: &Quoting) -> Option<&[u8]> {
+        let rules = self.resolved.get(i).filter(|rules| !rules.is_empty())?;
+        let value = quoting.value(field);
+        rules.iter().find(|r| r.0 == *value).map(|r| &r.1[..])
+    }
+
+    /// Replaces values of the record starting at `base`/`col_base`
+    ///
+    /// Records without any value to replace are left untouched, without
+    /// allocating.
+    pub fn apply(&self, buf: &mut Vec<u8>, cols: &mut [usize],
+                 base: usize, col_base: usize, quoting: &Quoting) {
+        let len = cols.len() - col_base;
+        let column_start = |cols: &[usize], k: usize| if k == 0 { 0 } else { cols[col_base + k - 1] + 1 };
+        let first = (0..len.min(self.resolved.len())).find(|&k| {
+            let field = &buf[base + column_start(cols, k)..base + cols[col_base + k]];
+            self.find(k, field, quoting).is_some()
+        });
+        let first = match first {
+            Some(first) => first,
+            None => return,
+        };
+
+        // columns before the first replaced one are kept, with its delimiter
+        let mut start = column_start(cols, first);
+        let mut line = Vec::with_capacity(buf.len() - base);
+        line.extend_from_slice(&buf[base..base + start]);
+        for k in first..len {
+            let end = cols[col_base + k];
+            if k > first {
+                line.push(quoting.delimiter);
+            }
+            let field = &buf[base + start..base + end];
+            match self.find(k, field, quoting) {
+                Some(to) => quoting.push(&mut line, to),
+                None => line.extend_from_slice(field),
+            }
+            start = end + 1;
+            cols[col_base + k] = line.len();
+        }
+        buf.truncate(base);
+        buf.extend_from_slice(&line);
+    }
+
+}
@@ -0,0 +1,218 @@
+//! Reverse record iteration
+//!
+//! Iterates the records of a seekable source from its end backwards,
+//! newest entries first, without reading the whole source forward.
+//!
+//! Record boundaries are found by scanning chunks backwards: a line ending
+//! of the `terminator` ends a record only if it is followed by an even
+//! number of quotes up to the end of the source. This is exact for well
+//! formed csv, where every quoted field is closed, but not with backslash
+//! escapes, which `reverse` rejects.
+//!
+//! # Example
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! let csv = quick_csv::Csv::from_reader(Cursor::new("ts,msg\n1,\"a\nb\"\n2,c\n"))
+//!     .has_header(true);
+//! let rows = csv.reverse().unwrap()
+//!     .map(|r| r.unwrap().decode::<(u32, String)>().unwrap())
+//!     .collect::<Vec<_>>();
+//! assert_eq!(rows, vec![(2, "c".to_string()), (1, "a\nb".to_string())]);
+//! ```
+
+use std::io::{BufRead, Seek, SeekFrom};
+use std::mem;
+
+use error::{Error, Result};
+use writer::Escape;
+use {Csv, Row, Terminator};
+
+/// Number of bytes read at once when scanning backwards
+const CHUNK: usize = 64 * 1024;
+
+/// Iterator over the records of a csv, from the last one to the first
+///
+/// Created with `Csv::reverse`.
+pub struct Reverse<B: BufRead + Seek> {
+    csv: Csv<B>,
+    /// offset where the iteration stops, after the header if any
+    start: u64,
+    /// offset of the first byte of `buf`
+    pos: u64,
+    /// bytes between `pos` and the end of the next record
+    buf: Vec<u8>,
+    /// bytes at the end of `buf` already scanned for a record boundary
+    scanned: usize,
+    /// whether the scanned bytes end inside a quoted field
+    in_quote: bool,
+    done: bool,
+}
+
+impl<B: BufRead + Seek> Csv<B> {
+
+    /// Iterates the remaining records from the last one backwards
+    ///
+    /// Iteration stops at the current position, so headers already read
+    /// with `has_header` are not returned. All reader options apply, the
+    /// column count is taken from the header or else from the last row.
+    ///
+    /// Offsets are taken from the start of the reader, which must be
+    /// positioned at the start of the source when the Csv is created.
+    /// Spans of `track_spans` are offsets in the source as well.
+    ///
+    /// Fails with `Error::Parse` for `Escape::Backslash` and backslash
+    /// dialects, whose escapes cannot be told apart backwards.
+    pub fn reverse(mut self) -> Result<Reverse<B>> {
+        if self.escape == Escape::Backslash || self.backslash != ::dialect::Backslash::Plain {
+            return Err(Error::Parse("Backslash escapes cannot be read in reverse".to_string()));
+        }
+        let start = self.position;
+        let pos = self.reader.seek(SeekFrom::End(0))?;
+        let mut reverse = Reverse {
+            csv: self,
+            start,
+            pos: ::std::cmp::max(pos, start),
+            buf: Vec::new(),
+            scanned: 0,
+            in_quote: false,
+            done: false,
+        };
+        reverse.read_chunk()?;
+        if reverse.buf.last().is_some_and(|&b| reverse.csv.terminator.ends(b)) {
+            reverse.pop_terminator()?;
+        }
+        reverse.done = reverse.buf.is_empty() && reverse.pos == reverse.start;
+        Ok(reverse)
+    }
+
+    /// Reads the last `n` records, in file order
+    ///
+    /// Only the end of the source is read, scanning backwards as `reverse`.
+    pub fn tail(self, n: usize) -> Result<Vec<Row>> {
+        let mut budget = self.budget();
+        let mut rows = Vec::new();
+        for row in self.reverse()?.take(n) {
+            let row = row?;
+            budget.charge(row.line.len() + row.cols.len() * ::std::mem::size_of::<usize>())?;
+            rows.push(row);
+        }
+        rows.reverse();
+        Ok(rows)
+    }
+
+}
+
+impl<B: BufRead + Seek> Reverse<B> {
+
+    /// Gets the headers read by the underlying Csv
+    pub fn headers(&mut self) -> Vec<String> {
+        self.csv.headers()
+    }
+
+    /// Prepends the chunk preceding `buf`, returns false at `start`
+    fn read_chunk(&mut self) -> Result<bool> {
+        let n = ::std::cmp::min(CHUNK as u64, self.pos - self.start) as usize;
+        if n == 0 {
+            return Ok(false);
+        }
+        self.pos -= n as u64;
+        self.csv.reader.seek(SeekFrom::Start(self.pos))?;
+        let mut chunk = vec![0; n];
+        self.csv.reader.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&self.buf);
+        self.buf = chunk;
+        Ok(true)
+    }
+
+    /// Removes the line ending at the end of `buf`, a `\r\n` as a whole
+    /// with `Terminator::Any`
+    fn pop_terminator(&mut self) -> Result<()> {
+        if self.buf.pop() == Some(b'\n') && self.csv.terminator == Terminator::Any {
+            if self.buf.is_empty() {
+                self.read_chunk()?;
+            }
+            if self.buf.ends_with(b"\r") {
+                self.buf.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the last record off `buf`, without its line ending, along
+    /// with its offset
+    fn next_record(&mut self) -> Result<(u64, Vec<u8>)> {
+        let (quote, terminator) = (self.csv.quote, self.csv.terminator);
+        loop {
+            let end = self.buf.len() - self.scanned;
+            for i in (0..end).rev() {
+                let b = self.buf[i];
+                if b == quote {
+                    self.in_quote = !self.in_quote;
+                } else if !self.in_quote && terminator.ends(b) {
+                    let offset = self.pos + i as u64 + 1;
+                    let record = self.buf.split_off(i + 1);
+                    self.pop_terminator()?;
+                    self.scanned = 0;
+                    return Ok((offset, record));
+                }
+            }
+            self.scanned = self.buf.len();
+            if !self.read_chunk()? {
+                self.done = true;
+                return Ok((self.start, mem::take(&mut self.buf)));
+            }
+        }
+    }
+
+    /// Reads the previous row kept by filters, if any
+    fn read_row(&mut self) -> Result<Option<Row>> {
+        if let Some(e) = self.csv.error.take() {
+            return Err(e);
+        }
+        while !self.done {
+            let (offset, record) = self.next_record()?;
+            let mut line = Vec::with_capacity(record.len());
+            let mut cols = self.csv.len.map_or_else(Vec::new, Vec::with_capacity);
+            let (splitter, limits) = (self.csv.splitter(), self.csv.limits);
+            let read = ::read_line(&mut &record[..], &mut line, splitter, &mut cols, limits, None, None)?;
+            if read < record.len() {
+                // an unbalanced quote, the forward split disagreeing
+                return Err(Error::Parse(format!("Record at offset {} has an unclosed quote", offset)));
+            }
+            let spans = if self.csv.track_spans { ::source_spans(&record, splitter, offset) } else { Vec::new() };
+            if self.csv.finish_record(&mut line, &mut cols, 0, 0)? {
+                let valid_utf8 = self.csv.valid_utf8(&line);
+                return Ok(Some(Row {
+                    line,
+                    cols,
+                    decode_options: self.csv.decode_options.clone(),
+                    valid_utf8,
+                    raw: self.csv.raw,
+                    nulls: mem::take(&mut self.csv.nulls),
+                    spans,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+}
+
+impl<B: BufRead + Seek> Iterator for Reverse<B> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        if self.done || self.csv.exit {
+            return None;
+        }
+        match self.read_row() {
+            Ok(row) => row.map(Ok),
+            Err(e) => {
+                self.csv.exit = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Sharded writer for parallel producers
+//!
+//! Spreads rows written from many threads over several `Writer`s, each
+//! behind its own lock, so that producers rarely wait for each other.
+//! Rows of a shard keep the order they were written in, there is no order
+//! between shards.
+//!
+//! # Example
+//!
+//! ```
+//! use std::thread;
+//! use quick_csv::shard::ShardedWriter;
+//! use quick_csv::writer::Writer;
+//!
+//! let writer = ShardedWriter::new((0..2).map(|_| Writer::from_writer(Vec::new())).collect()).unwrap();
+//! thread::scope(|s| {
+//!     for t in 0..4 {
+//!         let writer = &writer;
+//!         s.spawn(move || writer.write([t.to_string(), "x".to_string()]).unwrap());
+//!     }
+//! });
+//! let shards = writer.into_inner().unwrap();
+//! assert_eq!(shards.iter().map(|s| s.len()).sum::<usize>(), 16);
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use error::{Error, Result};
+use writer::Writer;
+
+/// Writer spreading rows over several shards
+pub struct ShardedWriter<W: Write> {
+    shards: Vec<Mutex<Writer<W>>>,
+    /// shard tried first by the next `write`
+    next: AtomicUsize,
+}
+
+fn poisoned() -> Error {
+    Error::Io(io::Error::other("a thread panicked while writing to the shard"))
+}
+
+impl<W: Write> ShardedWriter<W> {
+
+    /// Creates a writer out of its shards
+    ///
+    /// Returns an `Error::Parse` if there is no shard.
+    pub fn new(shards: Vec<Writer<W>>) -> Result<ShardedWriter<W>> {
+        if shards.is_empty() {
+            return Err(Error::Parse("A sharded writer needs at least one shard".to_string()));
+        }
+        Ok(ShardedWriter {
+            shards: shards.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Gets the number of shards
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Writes a new row to any shard
+    ///
+    /// Shards are tried in turn from a different one on each call, the
+    /// first one not in use gets the row. If all are in use, waits for the
+    /// first one tried.
+    pub fn write<I, S>(&self, columns: I) -> Result<()>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<[u8]>
+    {
+        let n = self.shards.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % n;
+        let free = (0..n).map(|i| &self.shards[(start + i) % n]).find_map(|s| match s.try_lock() {
+            Ok(shard) => Some(Ok(shard)),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(_)) => Some(Err(poisoned())),
+        });
+        let mut shard = match free {
+            Some(shard) => shard?,
+            None => self.lock(start)?,
+        };
+        shard.write(columns)
+    }
+
+    /// Writes a new row to shard `shard`, e.g. chosen by a key hash
+    ///
+    /// # Panics
+    ///
+    /// If `shard` is not less than the number of shards.
+    pub fn write_to<I, S>(&self, shard: usize, columns: I) -> Result<()>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<[u8]>
+    {
+        self.lock(shard)?.write(columns)
+    }
+
+    /// Flushes all shards
+    pub fn flush(&self) -> Result<()> {
+        for i in 0..self.shards.len() {
+            self.lock(i)?.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all shards and unwraps them, returning the underlying writers
+    pub fn into_inner(self) -> Result<Vec<W>> {
+        self.shards.into_iter()
+            .map(|s| s.into_inner().map_err(|_| poisoned()).and_then(Writer::into_inner))
+            .collect()
+    }
+
+    fn lock(&self, shard: usize) -> Result<MutexGuard<'_, Writer<W>>> {
+        self.shards[shard].lock().map_err(|_| poisoned())
+    }
+
+}
+
+impl ShardedWriter<File> {
+
+    /// Creates `n` shard files in `dir`, named `<prefix>-<i>.csv`
+    ///
+    /// The directory is created if needed.
+    pub fn from_dir<P: AsRef<Path>>(dir: P, prefix: &str, n: usize) -> Result<ShardedWriter<File>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let shards = (0..n)
+            .map(|i| Writer::from_file(dir.join(format!("{}-{}.csv", prefix, i))))
+            .collect::<Result<Vec<_>>>()?;
+        ShardedWriter::new(shards)
+    }
+
+}
@@ -0,0 +1,290 @@
+//! Spilling of buffered rows to disk
+//!
+//! Operations sorting or grouping rows (`transform::sort`, `group_by`) keep
+//! their data in memory up to the `Csv::memory_budget`. Once `Csv::spill_to`
+//! is set, they then write sorted runs to temporary csv files, merged back
+//! once all rows are read, so that they work on data much larger than
+//! memory. Runs are gzip compressed if requested, with the `flate2` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::spill::Spill;
+//! use quick_csv::transform;
+//! use quick_csv::writer::Writer;
+//!
+//! let mut csv = Csv::from_string("k,v\nb,1\na,2\nc,3\na,4").has_header(true)
+//!     .memory_budget(64)
+//!     .spill_to(Spill::new(std::env::temp_dir()));
+//! let mut writer = Writer::from_writer(Vec::new());
+//! transform::sort(&mut csv, &mut writer, &["k"]).unwrap();
+//! assert_eq!(writer.into_inner().unwrap(), &b"k,v\na,2\na,4\nb,1\nc,3\n"[..]);
+//! ```
+
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::vec;
+
+#[cfg(feature = "flate2")] use flate2::Compression;
+#[cfg(feature = "flate2")] use flate2::read::GzDecoder;
+#[cfg(feature = "flate2")] use flate2::write::GzEncoder;
+
+use budget::Budget;
+use error::{Error, Result};
+use writer::Writer;
+use Csv;
+
+/// Number of runs created by this process, making file names unique
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Where and how rows are spilled
+#[derive(Debug, Clone)]
+pub struct Spill {
+    dir: PathBuf,
+    #[cfg(feature = "flate2")]
+    compress: bool,
+}
+
+impl Spill {
+
+    /// Spills rows to temporary files in `dir`
+    ///
+    /// Files are removed once merged, or when the operation fails.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Spill {
+        Spill {
+            dir: dir.into(),
+            #[cfg(feature = "flate2")]
+            compress: false,
+        }
+    }
+
+    /// Defines whether spilled files are gzip compressed
+    ///
+    /// Note: default = `false`
+    #[cfg(feature = "flate2")]
+    pub fn compress(mut self, compress: bool) -> Spill {
+        self.compress = compress;
+        self
+    }
+
+}
+
+/// The columns of a row being sorted
+pub(crate) type Record = Vec<Vec<u8>>;
+
+/// Estimated memory used by a record
+fn record_size(record: &Record) -> usize {
+    mem::size_of::<Record>() + record.iter().map(|c| c.len() + mem::size_of::<Vec<u8>>()).sum::<usize>()
+}
+
+/// A temporary file holding a sorted run, removed on drop
+struct Run {
+    path: PathBuf,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn write_run<W: Write>(writer: W, records: &[Record]) -> Result<W> {
+    let mut writer = Writer::from_writer(writer);
+    for record in records {
+        writer.write(record)?;
+    }
+    writer.into_inner()
+}
+
+/// External sort of records, kept in memory within the budget
+pub(crate) struct Sorter<F> {
+    spill: Option<Spill>,
+    limit: usize,
+    budget: Budget,
+    compare: F,
+    records: Vec<Record>,
+    runs: Vec<Run>,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Sets where rows are spilled once buffering operations exceed the `memory_budget`
+    ///
+    /// Note: default = no spilling, operations fail with `Error::MemoryBudget`
+    pub fn spill_to(mut self, spill: Spill) -> Csv<B> {
+        self.spill = Some(spill);
+        self
+    }
+
+    /// Creates a sorter with the memory budget and spill options of this Csv
+    pub(crate) fn sorter<F>(&self, compare: F) -> Sorter<F>
+        where F: Fn(&Record, &Record) -> Ordering
+    {
+        Sorter {
+            spill: self.spill.clone(),
+            limit: self.memory_budget,
+            budget: self.budget(),
+            compare,
+            records: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+}
+
+impl<F: Fn(&Record, &Record) -> Ordering> Sorter<F> {
+
+    /// Adds a record, spilling the ones in memory if it does not fit
+    ///
+    /// Without spill options, fails with `Error::MemoryBudget` instead.
+    pub fn push(&mut self, record: Record) -> Result<()> {
+        let size = record_size(&record);
+        if !self.budget.fits(size) {
+            match self.spill {
+                None => return Err(Error::MemoryBudget(self.limit)),
+                Some(_) if !self.records.is_empty() => {
+                    self.write_run()?;
+                    self.budget = Budget::new(self.limit);
+                },
+                // a record bigger than the budget gets a run of its own
+                Some(_) => (),
+            }
+        }
+        let _ = self.budget.charge(size);
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Sorts the records in memory and writes them as a new run
+    fn write_run(&mut self) -> Result<()> {
+        let spill = match self.spill {
+            Some(ref spill) => spill,
+            None => return Ok(()),
+        };
+        let compare = &self.compare;
+        self.records.sort_by(|a, b| compare(a, b));
+        let n = RUNS.fetch_add(1, AtomicOrdering::Relaxed);
+        let run = Run { path: spill.dir.join(format!("quick-csv-{}-{}.csv", process::id(), n)) };
+        let file = File::create(&run.path)?;
+        #[cfg(feature = "flate2")]
+        {
+            if spill.compress {
+                write_run(GzEncoder::new(file, Compression::fast()), &self.records)?.finish()?;
+            } else {
+                write_run(file, &self.records)?;
+            }
+        }
+        #[cfg(not(feature = "flate2"))]
+        write_run(file, &self.records)?;
+        self.records.clear();
+        self.runs.push(run);
+        Ok(())
+    }
+
+    /// Gets all records in order
+    ///
+    /// Records comparing equal keep the order they were pushed in.
+    pub fn finish(mut self) -> Result<Sorted<F>> {
+        if self.runs.is_empty() {
+            let compare = &self.compare;
+            self.records.sort_by(|a, b| compare(a, b));
+            return Ok(Sorted {
+                records: mem::take(&mut self.records).into_iter(),
+                runs: Vec::new(),
+                heads: Vec::new(),
+                compare: self.compare,
+            });
+        }
+        if !self.records.is_empty() {
+            self.write_run()?;
+        }
+        let mut readers = Vec::with_capacity(self.runs.len());
+        for run in &self.runs {
+            let file = File::open(&run.path)?;
+            let reader: Box<dyn Read> = match self.spill {
+                #[cfg(feature = "flate2")]
+                Some(ref spill) if spill.compress => Box::new(GzDecoder::new(file)),
+                _ => Box::new(file),
+            };
+            readers.push(Csv::from_reader(BufReader::new(reader)).flexible(true));
+        }
+        let mut sorted = Sorted {
+            records: Vec::new().into_iter(),
+            runs: readers.into_iter().zip(mem::take(&mut self.runs)).collect(),
+            heads: Vec::new(),
+            compare: self.compare,
+        };
+        for i in 0..sorted.runs.len() {
+            let head = sorted.read(i)?;
+            sorted.heads.push(head);
+        }
+        Ok(sorted)
+    }
+
+}
+
+type RunReader = Csv<BufReader<Box<dyn Read>>>;
+
+/// Records of a `Sorter`, in order
+pub(crate) struct Sorted<F> {
+    /// records never spilled
+    records: vec::IntoIter<Record>,
+    /// readers of the runs to merge
+    runs: Vec<(RunReader, Run)>,
+    /// next record of every run
+    heads: Vec<Option<Record>>,
+    compare: F,
+}
+
+impl<F: Fn(&Record, &Record) -> Ordering> Sorted<F> {
+
+    fn read(&mut self, run: usize) -> Result<Option<Record>> {
+        match self.runs[run].0.next() {
+            Some(row) => Ok(Some(row?.bytes_columns().map(|c| c.to_vec()).collect())),
+            None => Ok(None),
+        }
+    }
+
+    /// Takes the smallest head, the one of the earliest run if several are equal
+    fn merge(&mut self) -> Result<Option<Record>> {
+        let mut min: Option<usize> = None;
+        for (i, head) in self.heads.iter().enumerate() {
+            let head = match *head {
+                Some(ref head) => head,
+                None => continue,
+            };
+            let smaller = match min {
+                Some(m) => (self.compare)(head, self.heads[m].as_ref().unwrap()) == Ordering::Less,
+                None => true,
+            };
+            if smaller {
+                min = Some(i);
+            }
+        }
+        match min {
+            Some(i) => {
+                let next = self.read(i)?;
+                Ok(mem::replace(&mut self.heads[i], next))
+            },
+            None => Ok(None),
+        }
+    }
+
+}
+
+impl<F: Fn(&Record, &Record) -> Ordering> Iterator for Sorted<F> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        if self.runs.is_empty() {
+            return self.records.next().map(Ok);
+        }
+        self.merge().transpose()
+    }
+}
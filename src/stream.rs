@@ -0,0 +1,169 @@
+//! Asynchronous rows
+//!
+//! Reads rows from any `futures::io::AsyncBufRead`, whatever the runtime
+//! (async-std, smol, tokio through its compatibility layer...), as a
+//! `futures::Stream`. Requires the `futures` feature.
+//!
+//! The reader options are set on a `Csv::detached`, which has no source of
+//! its own, before binding it to the asynchronous reader.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate futures_core;
+//! extern crate quick_csv;
+//!
+//! use std::pin::Pin;
+//! use std::task::{Context, Poll, Waker};
+//! use futures_core::Stream;
+//! use quick_csv::Csv;
+//!
+//! # fn main() {
+//! // `&[u8]` implements `AsyncBufRead`
+//! let mut rows = Csv::detached().has_header(true).into_stream(&b"a,b\nc,d\n"[..]);
+//! let mut cx = Context::from_waker(Waker::noop());
+//! match Pin::new(&mut rows).poll_next(&mut cx) {
+//!     Poll::Ready(Some(Ok(row))) => assert_eq!(row.as_str().unwrap(), "c,d"),
+//!     _ => unreachable!(),
+//! }
+//! assert_eq!(rows.headers(), vec!["a", "b"]);
+//! # }
+//! ```
+
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_io::AsyncBufRead;
+
+use error::{Error, Result};
+use parser::Splitter;
+use {Csv, Row};
+
+/// Stream of the rows of an asynchronous reader
+///
+/// Created with `Csv::into_stream`.
+pub struct CsvStream<R> {
+    csv: Csv<io::Empty>,
+    reader: R,
+    splitter: Splitter,
+    /// content and column ends of the record being read
+    buf: Vec<u8>,
+    cols: Vec<usize>,
+    /// whether some bytes of the record were read
+    pending: bool,
+}
+
+impl Csv<io::Empty> {
+
+    /// Creates a Csv without source, to be configured then bound to an
+    /// asynchronous reader with `into_stream`
+    pub fn detached() -> Csv<io::Empty> {
+        Csv::from_reader(io::empty())
+    }
+
+    /// Reads rows from `reader` with the options of this Csv
+    pub fn into_stream<R: AsyncBufRead + Unpin>(self, reader: R) -> CsvStream<R> {
+        CsvStream {
+            splitter: self.splitter(),
+            csv: self,
+            reader,
+            buf: Vec::new(),
+            cols: Vec::new(),
+            pending: false,
+        }
+    }
+
+}
+
+impl<R: AsyncBufRead + Unpin> CsvStream<R> {
+
+    /// Gets the headers, once the header row is read
+    pub fn headers(&self) -> Vec<String> {
+        self.csv.headers.clone().unwrap_or_default()
+    }
+
+    /// Polls the reader until a complete record is in `buf` and `cols`
+    ///
+    /// Returns false on end of file without any record.
+    fn poll_record(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool>> {
+        loop {
+            let (used, done) = {
+                let available = match Pin::new(&mut self.reader).poll_fill_buf(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(Error::from(e))),
+                    Poll::Ready(Ok(available)) => available,
+                };
+                if available.is_empty() {
+                    return Poll::Ready(Ok(mem::replace(&mut self.pending, false)));
+                }
+                self.splitter.split(available, &mut self.buf, 0, &mut self.cols)?
+            };
+            Pin::new(&mut self.reader).consume(used);
+            self.csv.position += used as u64;
+            self.pending = true;
+            if self.buf.len() > self.csv.limits.record {
+                return Poll::Ready(Err(Error::RecordTooLarge(self.csv.limits.record)));
+            }
+            if done {
+                self.pending = false;
+                return Poll::Ready(Ok(true));
+            }
+        }
+    }
+
+    /// Polls the next row kept by filters, the header row being consumed
+    fn poll_row(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Row>>> {
+        loop {
+            match self.poll_record(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(false)) => return Poll::Ready(None),
+                Poll::Ready(Ok(true)) => (),
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+            }
+            self.splitter.reset();
+            let (mut line, mut cols) = (mem::take(&mut self.buf), mem::take(&mut self.cols));
+            let is_header = self.csv.has_header && self.csv.headers.is_none();
+            match self.csv.finish_record(&mut line, &mut cols, 0, 0) {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+            let row = Row {
+                valid_utf8: self.csv.valid_utf8(&line),
+                line,
+                cols,
+                decode_options: self.csv.decode_options.clone(),
+                raw: self.csv.raw,
+                nulls: mem::take(&mut self.csv.nulls),
+                spans: Vec::new(),
+            };
+            if !is_header {
+                return Poll::Ready(Some(Ok(row)));
+            }
+            let headers = row.columns().map(|c| c.map(|c| c.to_string()).collect())
+                .unwrap_or_else(|_| Vec::new());
+            self.csv.set_headers(headers);
+        }
+    }
+
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for CsvStream<R> {
+    type Item = Result<Row>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Row>>> {
+        let this = self.get_mut();
+        if this.csv.exit {
+            return Poll::Ready(None);
+        }
+        let row = this.poll_row(cx);
+        if let Poll::Ready(Some(Err(_))) = row {
+            this.csv.exit = true;
+        }
+        row
+    }
+}
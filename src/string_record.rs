@@ -0,0 +1,132 @@
+//! Owned records
+//!
+//! A `StringRecord` keeps the columns of a row as `String`s: it is
+//! cloneable, hashable and independent of the reader, to be stored in
+//! collections or sent to other threads.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashSet;
+//! use quick_csv::Csv;
+//! use quick_csv::string_record::StringRecord;
+//!
+//! let unique = Csv::from_string("a,b\na,b\nc,d")
+//!     .map(|row| StringRecord::from_row(&row?))
+//!     .collect::<Result<HashSet<_>, _>>()
+//!     .unwrap();
+//! assert_eq!(unique.len(), 2);
+//! assert!(unique.contains(&StringRecord::from(vec!["c", "d"])));
+//! ```
+
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+use std::vec;
+
+use error::Result;
+use Row;
+
+/// Owned columns of a row
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StringRecord {
+    fields: Vec<String>,
+}
+
+impl StringRecord {
+
+    /// Creates a record without column
+    pub fn new() -> StringRecord {
+        StringRecord::default()
+    }
+
+    /// Copies the columns of a row, failing if not valid UTF-8
+    pub fn from_row(row: &Row) -> Result<StringRecord> {
+        Ok(row.columns()?.collect())
+    }
+
+    /// Gets the `i`th column
+    pub fn get(&self, i: usize) -> Option<&str> {
+        self.fields.get(i).map(|f| &**f)
+    }
+
+    /// Gets the columns count
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether there is no column
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Appends a column
+    pub fn push<S: Into<String>>(&mut self, field: S) {
+        self.fields.push(field.into());
+    }
+
+    /// Gets an iterator over columns
+    pub fn iter(&self) -> slice::Iter<'_, String> {
+        self.fields.iter()
+    }
+
+    /// Gets the columns
+    pub fn as_slice(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Converts into the columns
+    pub fn into_vec(self) -> Vec<String> {
+        self.fields
+    }
+
+    /// Converts into a row, as if read from a csv with a `,` delimiter
+    pub fn to_row(&self) -> Row {
+        Row::from_fields(&self.fields)
+    }
+
+}
+
+impl<S: Into<String>> From<Vec<S>> for StringRecord {
+    fn from(fields: Vec<S>) -> StringRecord {
+        fields.into_iter().collect()
+    }
+}
+
+impl From<StringRecord> for Row {
+    fn from(record: StringRecord) -> Row {
+        record.to_row()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for StringRecord {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> StringRecord {
+        StringRecord { fields: iter.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl IntoIterator for StringRecord {
+    type Item = String;
+    type IntoIter = vec::IntoIter<String>;
+
+    fn into_iter(self) -> vec::IntoIter<String> {
+        self.fields.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StringRecord {
+    type Item = &'a String;
+    type IntoIter = slice::Iter<'a, String>;
+
+    fn into_iter(self) -> slice::Iter<'a, String> {
+        self.fields.iter()
+    }
+}
+
+impl Index<usize> for StringRecord {
+    type Output = str;
+
+    fn index(&self, i: usize) -> &str {
+        &self.fields[i]
+    }
+}
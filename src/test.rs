@@ -1,5 +1,19 @@
-use Csv;
-use std::io::{self, Read, Seek};
+use {ColumnPolicy, Csv, EmptyRecord, LineEndings, Row, Terminator, Utf8Validation};
+use decode::Percent;
+use error::Error;
+use headers::{Duplicates, Normalization};
+use index::Index;
+use rustc_serialize::{Decodable, Decoder};
+use quickcheck::{Arbitrary, Gen, QuickCheck};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, Write};
+use std::path::PathBuf;
+use writer::{Escape, Writer};
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("quick-csv-{}-{}", ::std::process::id(), name))
+}
 
 fn assert_svec_eq<S, T>(got: Vec<Vec<S>>, expected: Vec<Vec<T>>)
         where S: AsRef<str>, T: AsRef<str> {
@@ -105,6 +119,12 @@ parses_to!(empty_string_no_headers, "", vec![]);
 parses_to!(empty_string_headers, "", vec![],
             |rdr: Csv<_>| rdr.has_header(true));
 parses_to!(empty_lines, "\n\n\n\n", vec![vec![""], vec![""], vec![""], vec![""]]);
+parses_to!(empty_lines_no_columns, "\n\r\n", vec![vec![], vec![]],
+           |rdr: Csv<_>| rdr.empty_record(EmptyRecord::NoColumns));
+parses_to!(empty_lines_interspersed_no_columns, "a,b\n\nc,d\n\"\",",
+           vec![vec!["a", "b"], vec![], vec!["c", "d"], vec!["", ""]],
+           |rdr: Csv<_>| rdr.empty_record(EmptyRecord::NoColumns));
+fail_parses_to!(empty_lines_interspersed_one_column, "a,b\n\nc,d", vec![]);
 
 // The following tests are done in xml-rs and left commented because 
 // the expected behavior is not clear
@@ -135,6 +155,8 @@ parses_to!(quote_space, "\" \"", vec![vec![" "]]);
 parses_to!(quote_inner_space, "\" a \"", vec![vec![" a "]]);
 fail_parses_to!(quote_outer_space, "  \"a\"  ", vec![vec!["  \"a\"  "]]);
 parses_to!(quote_inner_quote, "a,b,\"c\"\"d\",e", vec![vec!["a", "b", "c\"d", "e"]]);
+parses_to!(quote_unescaped, "\"\"\"\"\"\",\"\"\"a,\nb\"\"\",\"c\"\"\"\"\"",
+           vec![vec!["\"\"", "\"a,\nb\"", "c\"\""]]);
 fail_parses_to!(inner_quote_without_quoted_column, "a,b,c\"\"d,e", vec![vec!["a", "b", "c\"d", "e"]]);
 
 parses_to!(delimiter_tabs, "a\tb", vec![vec!["a", "b"]],
@@ -150,17 +172,40 @@ parses_to!(flexible_rows, "a\nx,y", vec![vec!["a"], vec!["x", "y"]],
 parses_to!(flexible_rows2, "a,b\nx", vec![vec!["a", "b"], vec!["x"]],
            |rdr: Csv<_>| rdr.flexible(true));
 
+parses_to!(pad_rows, "a,b,c\nx\n\"y\",z", vec![vec!["a", "b", "c"], vec!["x", "", ""], vec!["y", "z", ""]],
+           |rdr: Csv<_>| rdr.column_policy(ColumnPolicy::Pad));
+fail_parses_to!(pad_rows_too_many, "a,b\nx,y,z", vec![], |rdr: Csv<_>| rdr.column_policy(ColumnPolicy::Pad));
+
+parses_to!(truncate_rows, "a,b\nx,y,z\nu,\"v\",w,t", vec![vec!["a", "b"], vec!["x", "y"], vec!["u", "v"]],
+           |rdr: Csv<_>| rdr.column_policy(ColumnPolicy::Truncate));
+fail_parses_to!(truncate_rows_too_few, "a,b\nx", vec![], |rdr: Csv<_>| rdr.column_policy(ColumnPolicy::Truncate));
+
+parses_to!(flexible_policy, "a\nx,y", vec![vec!["a"], vec!["x", "y"]],
+           |rdr: Csv<_>| rdr.column_policy(ColumnPolicy::Flexible));
+
 fail_parses_to!(nonflexible, "a\nx,y", vec![]);
 fail_parses_to!(nonflexible2, "a,b\nx", vec![]);
 
-#[derive(Debug, RustcDecodable, RustcEncodable, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 enum Val { Unsigned(usize), Signed(isize), Bool(bool) }
 
+impl Decodable for Val {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Val, D::Error> {
+        d.read_enum("Val", |d| {
+            d.read_enum_variant(&["Unsigned", "Signed", "Bool"], |d, i| match i {
+                0 => d.read_enum_variant_arg(0, Decodable::decode).map(Val::Unsigned),
+                1 => d.read_enum_variant_arg(0, Decodable::decode).map(Val::Signed),
+                _ => d.read_enum_variant_arg(0, Decodable::decode).map(Val::Bool),
+            })
+        })
+    }
+}
+
 decodes_to!(decode_int, "1", (usize,), vec![(1usize,)]);
 decodes_to!(decode_many_int, "1,2", (usize, i16), vec![(1usize, 2i16)]);
 decodes_to!(decode_float, "1,1.0,1.5",
             (f64, f64, f64), vec![(1f64, 1.0, 1.5)]);
-decodes_to!(decode_char, "a", (char), vec![('a')]);
+decodes_to!(decode_char, "a", char, vec!['a']);
 decodes_to!(decode_str, "abc", (String,), vec![("abc".into(),)]);
 
 decodes_to!(decode_opt_int, "a", (Option<usize>,), vec![(None,)]);
@@ -175,8 +220,18 @@ decodes_to!(decode_opt_val, "1.0", (Option<Val>,), vec![(None,)]);
 decodes_to!(decode_tail, "abc,1,2,3,4", (String, Vec<usize>),
             vec![("abc".into(), vec![1usize, 2, 3, 4])]);
 
-#[derive(Debug, RustcDecodable, RustcEncodable, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 enum MyEnum { Enum1, Enum2 }
+
+impl Decodable for MyEnum {
+    fn decode<D: Decoder>(d: &mut D) -> Result<MyEnum, D::Error> {
+        d.read_enum("MyEnum", |d| {
+            d.read_enum_variant(&["Enum1", "Enum2"], |_, i| {
+                Ok(if i == 0 { MyEnum::Enum1 } else { MyEnum::Enum2 })
+            })
+        })
+    }
+}
 decodes_to!(decode_myenum, "Enum1,Enum1,Enum2", (MyEnum, MyEnum, MyEnum),
             vec![(MyEnum::Enum1, MyEnum::Enum1, MyEnum::Enum2)]);
 #[test]
@@ -254,3 +309,2571 @@ fn seeking() {
         assert_eq!(vals, vec!((1, 2), (3, 4), (5, 6)));
     }
 }
+
+#[cfg(feature = "rayon")]
+#[test]
+fn decode_parallel_keeps_order() {
+    let data = (0..10000).map(|i| format!("{},{}", i, i * 2)).collect::<Vec<_>>().join("\n");
+    let rows = Csv::from_string(&data).decode_parallel::<(usize, usize)>().unwrap();
+    assert_eq!(rows.len(), 10000);
+    assert!(rows.iter().enumerate().all(|(i, &(a, b))| a == i && b == i * 2));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn decode_parallel_error() {
+    let csv = Csv::from_string("1,2\n3,x\n5,6");
+    assert!(csv.decode_parallel::<(usize, usize)>().is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn decode_pipeline_keeps_order() {
+    let data = (0..10000).map(|i| format!("{},{}", i, i * 2)).collect::<Vec<_>>().join("\n");
+    let rows = Csv::from_reader(io::Cursor::new(data.clone())).decode_pipeline::<(usize, usize)>(2)
+        .collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows.len(), 10000);
+    assert!(rows.iter().enumerate().all(|(i, &(a, b))| a == i && b == i * 2));
+
+    let data = data + "\n1,x\n2,3";
+    let mut rows = Csv::from_reader(io::Cursor::new(data)).decode_pipeline::<(usize, usize)>(1);
+    assert_eq!(rows.by_ref().take_while(|r| r.is_ok()).count(), 10000);
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn checkpoint_resume() {
+    let data = &b"\xef\xbb\xbf1,2\n3,4\n5,6\n"[..];
+    let mut d = Csv::from_reader(io::Cursor::new(data));
+    d.next().unwrap().unwrap();
+    let checkpoint = d.checkpoint();
+    assert_eq!(checkpoint.offset(), 7);
+    assert_eq!(checkpoint.records(), 1);
+
+    let d = Csv::from_checkpoint(io::Cursor::new(data), checkpoint).unwrap();
+    assert_eq!(d.current_line(), 1);
+    let vals = d.map(|r| r.unwrap().decode::<(usize, usize)>().unwrap()).collect::<Vec<_>>();
+    assert_eq!(vals, vec!((3, 4), (5, 6)));
+}
+
+#[test]
+fn checkpoint_start() {
+    let data = &b"\xef\xbb\xbf1,2\n3,4\n"[..];
+    let checkpoint = Csv::from_reader(io::Cursor::new(data)).checkpoint();
+    assert_eq!(checkpoint.offset(), 3);
+    let d = Csv::from_checkpoint(io::Cursor::new(data), checkpoint).unwrap();
+    assert_eq!(d.count(), 2);
+}
+
+#[test]
+fn index_save_load() {
+    let source = temp_path("index.csv");
+    let path = temp_path("index.csv.idx");
+    File::create(&source).unwrap().write_all(b"a,b\n\"c\nd\",e\nf,g\n").unwrap();
+
+    let index = Index::build(&source, b',').unwrap();
+    assert_eq!(index.len(), 3);
+    assert_eq!(index.offset(2), Some(12));
+    index.save(&path).unwrap();
+    assert_eq!(Index::load(&path, &source).unwrap(), index);
+
+    let file = BufReader::new(File::open(&source).unwrap());
+    let mut csv = Csv::from_checkpoint(file, index.checkpoint(2).unwrap()).unwrap();
+    let row = csv.next().unwrap().unwrap().decode::<(String, String)>().unwrap();
+    assert_eq!(row, ("f".to_string(), "g".to_string()));
+
+    File::create(&source).unwrap().write_all(b"a,b\n").unwrap();
+    assert!(Index::load(&path, &source).is_err());
+    assert_eq!(Index::load_or_build(&path, &source, b',').unwrap().len(), 1);
+    assert_eq!(Index::load(&path, &source).unwrap().len(), 1);
+
+    // corrupt or truncated index files
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    fs::write(&path, &bytes).unwrap();
+    match Index::load(&path, &source) {
+        Err(Error::Index(_)) => (),
+        r => panic!("unexpected {:?}", r),
+    }
+    fs::write(&path, &bytes[..20]).unwrap();
+    match Index::load(&path, &source) {
+        Err(Error::Index(ref e)) if e == "truncated index file" => (),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    File::create(&source).unwrap().write_all(b"a;'b\rc'\rd;e\r").unwrap();
+    let dialect = ::dialect::Dialect { delimiter: b';', quote: b'\'', terminator: Terminator::Cr,
+                                       ..::dialect::Dialect::Rfc4180 };
+    let index = Index::build_with_dialect(&source, dialect).unwrap();
+    assert_eq!((index.len(), index.offset(1)), (2, Some(8)));
+
+    fs::remove_file(source).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn row_as_str() {
+    let mut d = Csv::from_string("a,\"b,c\",d\r\ne,f,g");
+    let r = d.next().unwrap().unwrap();
+    assert_eq!(r.as_bytes(), b"a,\"b,c\",d");
+    assert_eq!(r.as_str().unwrap(), "a,\"b,c\",d");
+    let r = d.next().unwrap().unwrap();
+    assert_eq!(r.as_str().unwrap(), "e,f,g");
+}
+
+#[test]
+fn row_as_str_invalid_utf8() {
+    let mut d = Csv::from_reader(&b"a\xffbc,xyz"[..]);
+    let r = d.next().unwrap().unwrap();
+    assert_eq!(r.as_bytes(), b"a\xffbc,xyz");
+    assert!(r.as_str().is_err());
+}
+
+#[test]
+fn row_is_empty() {
+    let mut d = Csv::from_string("\na").empty_record(EmptyRecord::NoColumns);
+    let r = d.next().unwrap().unwrap();
+    assert!(r.is_empty());
+    assert_eq!(r.len(), 0);
+    assert!(!d.next().unwrap().unwrap().is_empty());
+    assert!(!Csv::from_string("\n").next().unwrap().unwrap().is_empty());
+}
+
+#[test]
+fn writer_escape_double() {
+    let mut w = Writer::from_writer(Vec::new());
+    w.write(["a", "", "b c", "d,e", "f\"g", "h\ni", "j\\k"]).unwrap();
+    assert_eq!(w.into_inner().unwrap(), &b"a,,b c,\"d,e\",\"f\"\"g\",\"h\ni\",j\\k\n"[..]);
+}
+
+#[test]
+fn writer_escape_backslash() {
+    let mut w = Writer::from_writer(Vec::new()).escape(Escape::Backslash);
+    w.write(["a", "f\"g", "j\\k"]).unwrap();
+    assert_eq!(w.into_inner().unwrap(), &b"a,\"f\\\"g\",\"j\\\\k\"\n"[..]);
+}
+
+#[test]
+fn writer_delimiter() {
+    let mut w = Writer::from_writer(Vec::new()).delimiter(b';');
+    w.write(["a,b", "c;d"]).unwrap();
+    assert_eq!(w.into_inner().unwrap(), &b"a,b;\"c;d\"\n"[..]);
+}
+
+#[test]
+fn writer_round_trip() {
+    let data = "a,\"b,c\",\"d\"\"e\"\nf,g,h\n";
+    let mut w = Writer::from_writer(Vec::new());
+    for row in Csv::from_string(data) {
+        w.write_row(&row.unwrap()).unwrap();
+    }
+    assert_eq!(w.into_inner().unwrap(), data.as_bytes());
+
+    // raw columns are written as read
+    let data = "a,\"b,c\",\"d\"\"e\"\n\"\",f,\n";
+    let mut w = Writer::from_writer(Vec::new());
+    for row in Csv::from_string(data).raw(true) {
+        w.write_row(&row.unwrap()).unwrap();
+    }
+    assert_eq!(w.into_inner().unwrap(), data.as_bytes());
+    let mut w = Writer::from_writer(Vec::new());
+    ::transform::copy(&mut Csv::from_string(data).raw(true), &mut w).unwrap();
+    assert_eq!(w.into_inner().unwrap(), data.as_bytes());
+}
+
+#[test]
+fn writer_crlf() {
+    let mut w = Writer::from_writer(Vec::new()).crlf(true);
+    w.write(["a", "b\r\nc"]).unwrap();
+    w.write(["d", "e"]).unwrap();
+    let data = w.into_inner().unwrap();
+    assert_eq!(data, &b"a,\"b\r\nc\"\r\nd,e\r\n"[..]);
+    let rows = Csv::from_reader(&*data).map(|r| r.unwrap().decode::<Vec<String>>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![vec!["a", "b\r\nc"], vec!["d", "e"]]);
+}
+
+#[test]
+fn writer_flush_every() {
+    let mut w = Writer::with_capacity(Vec::new(), 1024).flush_every(2);
+    assert_eq!(w.capacity(), 1024);
+    w.write(["a", "b"]).unwrap();
+    assert!(w.get_ref().is_empty());
+    w.write(["c", "d"]).unwrap();
+    assert_eq!(w.get_ref(), b"a,b\nc,d\n");
+    w.write(["e", "f"]).unwrap();
+    assert_eq!(w.get_ref().len(), 8);
+    w.flush().unwrap();
+    assert_eq!(w.get_ref(), b"a,b\nc,d\ne,f\n");
+}
+
+#[test]
+fn field_hook_by_index() {
+    let csv = Csv::from_string("1 kg,a\n2 kg,b")
+        .field_hook(0, |s| s.trim_end_matches(" kg").to_string());
+    let rows = csv.map(|r| r.unwrap().decode::<(u8, String)>().unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, "a".to_string()), (2, "b".to_string())]);
+}
+
+#[test]
+fn field_hook_by_name() {
+    let mut csv = Csv::from_string("a,b\n1,N/A\n")
+        .field_hook("b", |s| if s == "N/A" { String::new() } else { s.to_string() })
+        .has_header(true);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u8, Option<u8>)>().unwrap(), (1, None));
+    assert_eq!(row.columns().unwrap().nth(1), Some("N/A"));
+}
+
+#[test]
+fn thousands_separator() {
+    let mut csv = Csv::from_string("\"1,234,567\",\"-1,234.5\",\"a,b\"").thousands_separator(',');
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u32, f64, String)>().unwrap(), (1234567, -1234.5, "a,b".to_string()));
+
+    let mut csv = Csv::from_string("1 234 567;12").delimiter(b';').thousands_separator(' ');
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u64, Option<i8>)>().unwrap(), (1234567, Some(12)));
+}
+
+#[test]
+fn decimal_separator() {
+    let mut csv = Csv::from_string("2,5;1.234,5;a,b").delimiter(b';')
+        .decimal_separator(',')
+        .thousands_separator('.');
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(f64, f32, String)>().unwrap(), (2.5, 1234.5, "a,b".to_string()));
+}
+
+#[test]
+fn currency_symbols() {
+    let mut csv = Csv::from_string("\"$1,200.50\",USD 3,+€ 4,EUR,5")
+        .currency_symbols(&["$", "€", "USD"])
+        .thousands_separator(',');
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(f64, u8, i8, String, u8)>().unwrap(),
+               (1200.5, 3, 4, "EUR".to_string(), 5));
+}
+
+#[test]
+fn percent_fraction() {
+    let mut csv = Csv::from_string("12.5%,5 %,-150%,a%,0,5%").percent(Percent::Fraction);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(f64, f64, f64, String, f64, f64)>().unwrap(),
+               (0.125, 0.05, -1.5, "a%".to_string(), 0.0, 0.05));
+}
+
+#[test]
+fn percent_number() {
+    let mut csv = Csv::from_string("12,5%;40%").delimiter(b';')
+        .decimal_separator(',')
+        .percent(Percent::Number);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(f32, u8)>().unwrap(), (12.5, 40));
+}
+
+#[test]
+fn normalize_headers() {
+    let mut csv = Csv::from_string("\u{feff} First Name ,lastName,ZIP-code\na,b,1")
+        .has_header(true)
+        .normalize_headers(Normalization::all())
+        .field_hook("zip_code", |s| format!("{}0", s));
+    assert_eq!(csv.headers(), vec!["first_name", "last_name", "zip_code"]);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(String, String, u8)>().unwrap().2, 10);
+}
+
+#[test]
+fn normalize_headers_trim_lowercase() {
+    let mut csv = Csv::from_string(" First Name , E-Mail")
+        .normalize_headers(Normalization { trim: true, lowercase: true, ..Default::default() })
+        .has_header(true);
+    assert_eq!(csv.headers(), vec!["first name", "e-mail"]);
+}
+
+#[test]
+fn duplicate_headers_policies() {
+    let data = "a,b,a\n1,2,3";
+    let csv = Csv::from_string(data).has_header(true);
+    assert_eq!(csv.column_index("a"), Some(0));
+    assert_eq!(csv.column_index("c"), None);
+    let csv = Csv::from_string(data).has_header(true).duplicate_headers(Duplicates::KeepLast);
+    assert_eq!(csv.column_indices("a"), vec![2]);
+    let csv = Csv::from_string(data).has_header(true).duplicate_headers(Duplicates::All);
+    assert_eq!(csv.column_indices("a"), vec![0, 2]);
+
+    let mut csv = Csv::from_string(data).has_header(true)
+        .duplicate_headers(Duplicates::All)
+        .field_hook("a", |s| format!("-{}", s));
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(i8, i8, i8)>().unwrap(), (-1, 2, -3));
+}
+
+#[test]
+fn duplicate_headers_error() {
+    let mut csv = Csv::from_string("a,b,a\n1,2,3").duplicate_headers(Duplicates::Error).has_header(true);
+    match csv.next() {
+        Some(Err(Error::DuplicateHeader(ref name))) if name == "a" => (),
+        _ => panic!("expecting a duplicate header error"),
+    }
+    assert!(csv.next().is_none());
+    assert!(Csv::from_string("a,b\n1,2").has_header(true)
+            .duplicate_headers(Duplicates::Error).next().unwrap().is_ok());
+}
+
+#[test]
+fn pad_row_content() {
+    let mut csv = Csv::from_string("a,b,c\nx\r\n").column_policy(ColumnPolicy::Pad);
+    csv.next();
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.len(), 3);
+    assert_eq!(row.as_str().unwrap(), "x,,");
+}
+
+#[test]
+fn truncate_callback() {
+    use std::sync::{Arc, Mutex};
+    let truncated = Arc::new(Mutex::new(Vec::new()));
+    let t = truncated.clone();
+    let mut csv = Csv::from_string("a,b\nc,d\nx,y,z\n")
+        .column_policy(ColumnPolicy::Truncate)
+        .on_truncate(move |line, expected, found| t.lock().unwrap().push((line, expected, found)));
+    csv.next();
+    csv.next();
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.as_str().unwrap(), "x,y");
+    assert_eq!(*truncated.lock().unwrap(), vec![(3, 2, 3)]);
+}
+
+#[test]
+fn max_record_size() {
+    let mut csv = Csv::from_string("abc,def\nabc,defg\n").max_record_size(7);
+    assert!(csv.next().unwrap().is_ok());
+    match csv.next() {
+        Some(Err(Error::RecordTooLarge(7))) => (),
+        _ => panic!("expecting a record too large error"),
+    }
+    assert!(csv.next().is_none());
+}
+
+#[test]
+fn max_record_size_unclosed_quote() {
+    let data = format!("a,\"b{}", "c\n".repeat(100_000));
+    let reader = BufReader::with_capacity(16, data.as_bytes());
+    let mut csv = Csv::from_reader(reader).max_record_size(1024);
+    match csv.next() {
+        Some(Err(Error::RecordTooLarge(1024))) => (),
+        _ => panic!("expecting a record too large error"),
+    }
+}
+
+#[test]
+fn max_field_size() {
+    let mut csv = Csv::from_string("abc,de\nab,\"cd\"\nabcde,f").max_field_size(4);
+    assert!(csv.next().unwrap().is_ok());
+    assert!(csv.next().unwrap().is_ok());
+    match csv.next() {
+        Some(Err(Error::FieldTooLarge(4))) => (),
+        _ => panic!("expecting a field too large error"),
+    }
+
+    let reader = BufReader::with_capacity(4, &b"a,\"bcdefghijklmnopqrstuvwxyz"[..]);
+    match Csv::from_reader(reader).max_field_size(8).next() {
+        Some(Err(Error::FieldTooLarge(8))) => (),
+        _ => panic!("expecting a field too large error"),
+    }
+}
+
+#[test]
+fn utf8_validation_per_record() {
+    let mut d = Csv::from_reader(&b"abc,xyz\na\xffbc,xyz"[..]).utf8_validation(Utf8Validation::PerRecord);
+    let r = d.next().unwrap().unwrap();
+    assert_eq!(r.columns().unwrap().collect::<Vec<_>>(), vec!["abc", "xyz"]);
+    let r = d.next().unwrap().unwrap();
+    assert!(r.columns().is_err());
+    assert_eq!(r.bytes_columns().next(), Some(&b"a\xffbc"[..]));
+}
+
+#[test]
+fn utf8_validation_per_chunk() {
+    // `\u{e9}` and `\u{20ac}` are split between 4 byte chunks
+    let data = "ab\u{e9},x\na\u{20ac},\n".as_bytes().iter()
+        .chain(b"\xff,y\nz,\xe2\x82\no,k\n,\xe2\x82")
+        .cloned().collect::<Vec<_>>();
+    let d = Csv::from_reader(BufReader::with_capacity(4, &data[..])).utf8_validation(Utf8Validation::PerChunk);
+    let valid = d.map(|r| r.unwrap().columns().is_ok()).collect::<Vec<_>>();
+    assert_eq!(valid, vec![true, true, false, false, true, false]);
+
+    // rows of other front-ends are validated as a whole
+    let data = &b"a,b\n1,\xff\xfe"[..];
+    let csv = || Csv::from_reader(io::Cursor::new(data)).utf8_validation(Utf8Validation::PerChunk);
+    let mut rows = csv().reverse().unwrap();
+    assert!(rows.next().unwrap().unwrap().as_str().is_err());
+    assert!(rows.next().unwrap().unwrap().as_str().is_ok());
+    assert!(csv().has_header(true).reverse().unwrap().next().unwrap().unwrap().as_str().is_err());
+    assert!(csv().tail(1).unwrap()[0].as_str().is_err());
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn utf8_validation_per_chunk_stream() {
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    let mut stream = Csv::detached().utf8_validation(Utf8Validation::PerChunk).into_stream(&b"1,\xff\xfe"[..]);
+    match Pin::new(&mut stream).poll_next(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(Some(Ok(row))) => assert!(row.as_str().is_err()),
+        _ => panic!("expected a row"),
+    }
+}
+
+#[test]
+fn utf8_assumed() {
+    let mut d = unsafe { Csv::from_string("abc,\u{e9}").assume_utf8() };
+    let r = d.next().unwrap().unwrap();
+    assert_eq!(r.decode::<(String, String)>().unwrap(), ("abc".to_string(), "\u{e9}".to_string()));
+}
+
+#[test]
+fn interned_strings() {
+    use intern::{Interned, Interner};
+    use std::sync::Arc;
+
+    let rows = Csv::from_string("a,FR\nb,\nc,FR")
+        .map(|r| r.unwrap().decode::<(String, Option<Interned>)>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows[1].1, None);
+    assert!(Arc::ptr_eq(&rows[0].1.as_ref().unwrap().0, &rows[2].1.as_ref().unwrap().0));
+
+    // decoding interns borrowed columns, in a pool of its own if scoped
+    let (len, distinct) = Interned::scoped(|| {
+        let rows = Csv::from_string("FR\nUS\nFR").map(|r| r.unwrap().decode::<Interned>().unwrap()).collect::<Vec<_>>();
+        (rows.len(), Interned::pool_len())
+    });
+    assert_eq!((len, distinct), (3, 2));
+    assert!(Interned::pool_len() >= 1);
+    Interned::clear_pool();
+    assert_eq!(Interned::pool_len(), 0);
+
+    // the outer pool is restored when `f` panics
+    Csv::from_string("FR").next().unwrap().unwrap().decode::<Interned>().unwrap();
+    let panicked = ::std::panic::catch_unwind(|| Interned::scoped(|| {
+        Csv::from_string("US\nDE").for_each(|r| drop(r.unwrap().decode::<Interned>().unwrap()));
+        panic!("in scope");
+    }));
+    assert!(panicked.is_err());
+    assert_eq!(Interned::pool_len(), 1);
+    Interned::clear_pool();
+
+    let mut interner = Interner::new();
+    let a = interner.intern("x");
+    assert!(Arc::ptr_eq(&a, &interner.intern("x")));
+    assert!(!Arc::ptr_eq(&a, &interner.intern("y")));
+    assert_eq!(interner.len(), 2);
+}
+
+#[test]
+fn arena_batches() {
+    use arena::Arena;
+
+    let mut csv = Csv::from_string("a,\"b\"\"c\"\nd,e\nx\nf,g").column_policy(ColumnPolicy::Pad);
+    let mut arena = Arena::with_capacity(64, 2);
+    assert_eq!(csv.read_batch(&mut arena, 2).unwrap(), 2);
+    let rows = arena.rows().map(|r| r.decode::<Vec<String>>().unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows, vec![vec!["a", "b\"c"], vec!["d", "e"]]);
+
+    assert_eq!(csv.read_batch(&mut arena, 10).unwrap(), 2);
+    assert_eq!(arena.get(0).unwrap().as_str().unwrap(), "x,");
+    assert_eq!(arena.get(1).unwrap().bytes_columns().collect::<Vec<_>>(), vec![b"f", b"g"]);
+    assert!(arena.get(2).is_none());
+    assert_eq!(csv.read_batch(&mut arena, 10).unwrap(), 0);
+    assert!(arena.is_empty());
+}
+
+#[test]
+fn arena_error() {
+    use arena::Arena;
+
+    let mut csv = Csv::from_string("a,b\nc,d\ne");
+    let mut arena = Arena::new();
+    assert!(csv.read_batch(&mut arena, 10).is_err());
+    assert_eq!(arena.len(), 2);
+    assert_eq!(arena.get(1).unwrap().as_str().unwrap(), "c,d");
+}
+
+#[test]
+fn read_row_reuses_buffers() {
+    use Row;
+
+    let mut csv = Csv::from_string("abc,def\n\"g\"\"h\",i\nj,k");
+    let mut row = Row::default();
+    assert!(csv.read_row(&mut row).unwrap());
+    let ptr = row.as_bytes().as_ptr();
+    assert!(csv.read_row(&mut row).unwrap());
+    assert_eq!(row.decode::<(String, String)>().unwrap(), ("g\"h".to_string(), "i".to_string()));
+    assert!(csv.read_row(&mut row).unwrap());
+    assert_eq!(row.as_bytes().as_ptr(), ptr);
+    assert_eq!(row.as_str().unwrap(), "j,k");
+    assert!(!csv.read_row(&mut row).unwrap());
+}
+
+#[test]
+fn columns_next_back() {
+    let row = Csv::from_string("a,\"b,c\",d,\"\"\"e\"").next().unwrap().unwrap();
+    let mut cols = row.columns().unwrap();
+    assert_eq!(cols.next_back(), Some("\"e"));
+    assert_eq!(cols.next(), Some("a"));
+    assert_eq!(cols.len(), 2);
+    assert_eq!(cols.next_back(), Some("d"));
+    assert_eq!(cols.next_back(), Some("b,c"));
+    assert_eq!(cols.next_back(), None);
+    assert_eq!(cols.next(), None);
+
+    let rev = row.bytes_columns().rev().collect::<Vec<_>>();
+    assert_eq!(rev, vec![&b"\"e"[..], b"d", b"b,c", b"a"]);
+    assert_eq!(row.bytes_columns().next_back(), Some(&b"\"e"[..]));
+}
+
+#[test]
+fn columns_exact_size() {
+    fn assert_exact<I: ExactSizeIterator>(it: &I, n: usize) {
+        assert_eq!(it.len(), n);
+        assert_eq!(it.size_hint(), (n, Some(n)));
+    }
+
+    let row = Csv::from_string("a,\"b\",c").next().unwrap().unwrap();
+    let mut cols = row.columns().unwrap();
+    assert_exact(&cols, 3);
+    cols.next();
+    assert_exact(&cols, 2);
+    cols.next_back();
+    assert_exact(&cols, 1);
+
+    let mut bytes = row.bytes_columns();
+    assert_exact(&bytes, row.len());
+    bytes.by_ref().for_each(drop);
+    assert_exact(&bytes, 0);
+}
+
+#[test]
+fn columns_get() {
+    let row = Csv::from_string("a,\"b,c\",,d").next().unwrap().unwrap();
+    let mut cols = row.columns().unwrap();
+    cols.next();
+    assert_eq!(cols.get(0), Some("a"));
+    assert_eq!(cols.get(1), Some("b,c"));
+    assert_eq!(cols.get(2), Some(""));
+    assert_eq!(cols.get(3), Some("d"));
+    assert_eq!(cols.get(4), None);
+
+    let bytes = row.bytes_columns();
+    assert_eq!(bytes.get(3), Some(&b"d"[..]));
+    assert_eq!(bytes.get(1), Some(&b"b,c"[..]));
+    assert_eq!(bytes.get(10), None);
+}
+
+#[test]
+fn reverse_records() {
+    use std::io::Cursor;
+
+    let data = "\u{feff}a,b\n1,\"x\ny\"\n2,\"\"\"q\"\"\"\n\n3,z\r\n";
+    let rows = Csv::from_reader(Cursor::new(data)).has_header(true)
+        .column_policy(ColumnPolicy::Pad).reverse().unwrap()
+        .map(|r| r.unwrap().decode::<(String, String)>().unwrap())
+        .collect::<Vec<_>>();
+    let expected = vec![("3", "z"), ("", ""), ("2", "\"q\""), ("1", "x\ny")];
+    assert_eq!(rows, expected.into_iter()
+               .map(|(a, b)| (a.to_string(), b.to_string())).collect::<Vec<_>>());
+
+    let rows = Csv::from_reader(Cursor::new("\u{feff}c,d")).reverse().unwrap()
+        .map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(rows, vec!["c,d"]);
+    assert_eq!(Csv::from_reader(Cursor::new("a,b\n")).has_header(true)
+               .reverse().unwrap().count(), 0);
+    assert_eq!(Csv::from_reader(Cursor::new("")).reverse().unwrap().count(), 0);
+
+    let mut rev = Csv::from_reader(Cursor::new("a,b\nc\nd,e")).reverse().unwrap();
+    assert!(rev.next().unwrap().is_ok());
+    assert!(rev.next().unwrap().is_err());
+    assert!(rev.next().is_none());
+
+    // reader options split records
+    let rows = Csv::from_reader(Cursor::new("1,'a\rb'\r2,'c''d'\r")).terminator(Terminator::Cr).quote(b'\'')
+        .track_spans(true).reverse().unwrap().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows.iter().map(|r| r.columns().unwrap().collect::<Vec<_>>()).collect::<Vec<_>>(),
+               vec![vec!["2", "c'd"], vec!["1", "a\rb"]]);
+    assert_eq!((rows[0].span(1), rows[1].span(1)), (Some((10, 16)), Some((2, 7))));
+    let rows = Csv::from_reader(Cursor::new("1\r\n2\r3\n4\r\n")).terminator(Terminator::Any).reverse().unwrap()
+        .map(|r| r.unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(rows, vec!["4", "3", "2", "1"]);
+    assert!(Csv::from_reader(Cursor::new("a")).escape(Escape::Backslash).reverse().is_err());
+}
+
+#[test]
+fn reverse_across_chunks() {
+    use std::io::Cursor;
+
+    let mut data = String::new();
+    for i in 0..20000 {
+        data.push_str(&format!("{},\"multi\nline {}\"\n", i, i));
+    }
+    let rows = Csv::from_reader(Cursor::new(data)).reverse().unwrap()
+        .map(|r| r.unwrap().decode::<(usize, String)>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 20000);
+    for (n, (i, s)) in rows.into_iter().enumerate() {
+        assert_eq!(i, 19999 - n);
+        assert_eq!(s, format!("multi\nline {}", i));
+    }
+}
+
+#[test]
+fn tail() {
+    use std::io::Cursor;
+
+    let mut data = "id,name\n".to_string();
+    for i in 0..1000 {
+        data.push_str(&format!("{},\"n,{}\"\n", i, i));
+    }
+    let rows = Csv::from_reader(Cursor::new(&data[..])).has_header(true).tail(3).unwrap();
+    let rows = rows.iter().map(|r| r.decode::<(u32, String)>().unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows, vec![(997, "n,997".to_string()), (998, "n,998".to_string()),
+                          (999, "n,999".to_string())]);
+    assert_eq!(Csv::from_reader(Cursor::new(&data[..])).has_header(true)
+               .tail(5000).unwrap().len(), 1000);
+    assert!(Csv::from_reader(Cursor::new(&data[..])).tail(0).unwrap().is_empty());
+}
+
+#[test]
+fn follow_growing_source() {
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    // returns each chunk in turn, with an end of file after each of them
+    struct Growing(VecDeque<Option<&'static [u8]>>);
+
+    impl Read for Growing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.pop_front() {
+                Some(Some(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    Ok(chunk.len())
+                },
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let chunks = vec![Some(&b"a,b\n1,"[..]), None, Some(b"\"x"), None, Some(b"\ny\"\r\n2,z"),
+                      None, None, Some(b"\n3\n")];
+    let source = BufReader::new(Growing(chunks.into_iter().collect()));
+    let mut follow = Csv::from_reader(source).has_header(true).follow(Duration::from_millis(1));
+    assert_eq!(follow.headers(), vec!["a", "b"]);
+    let row = follow.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u32, String)>().unwrap(), (1, "x\ny".to_string()));
+    let row = follow.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u32, String)>().unwrap(), (2, "z".to_string()));
+    match follow.next() {
+        Some(Err(Error::ColumnMismatch(2, 1))) => (),
+        r => panic!("unexpected {:?}", r.map(|r| r.map(|r| r.len()))),
+    }
+    assert!(follow.next().is_none());
+
+    // bare `\r` line endings and custom quotes
+    let chunks = vec![Some(&b"1,'x\r"[..]), None, Some(b"y'\r2,"), None, Some(b"'a''b'\r3,c\r")];
+    let source = BufReader::new(Growing(chunks.into_iter().collect()));
+    let mut follow = Csv::from_reader(source).terminator(Terminator::Cr).quote(b'\'').track_spans(true)
+        .follow(Duration::from_millis(1));
+    let rows = follow.by_ref().take(3).map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows.iter().map(|r| r.decode::<(u32, String)>().unwrap()).collect::<Vec<_>>(),
+               vec![(1, "x\ry".to_string()), (2, "a'b".to_string()), (3, "c".to_string())]);
+    assert_eq!(rows[1].span(1), Some((10, 16)));
+}
+
+#[test]
+fn glob_files() {
+    let dir = temp_path("glob");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("2024-02.csv"), "\u{feff}id,name\r\n3,c\n").unwrap();
+    fs::write(dir.join("2024-01.csv"), "id,name\n1,a\n2,\"b\nb\"").unwrap();
+    fs::write(dir.join("2024-03.csv"), "id,name\n").unwrap();
+    fs::write(dir.join("2023-12.csv"), "id,name\n0,z\n").unwrap();
+    fs::write(dir.join("2024-01.txt"), "other").unwrap();
+
+    let csv = Csv::from_glob(dir.join("2024-*.csv"), true).unwrap();
+    let mut csv = csv.column_policy(ColumnPolicy::Strict);
+    assert_eq!(csv.headers(), vec!["id", "name"]);
+    let rows = csv.map(|r| r.unwrap().decode::<(u32, String)>().unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, "a".to_string()), (2, "b\nb".to_string()), (3, "c".to_string())]);
+
+    let paths = ::glob::glob(dir.join("202?-0?.*")).unwrap();
+    let names = paths.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["2024-01.csv", "2024-01.txt", "2024-02.csv", "2024-03.csv"]);
+
+    fs::write(dir.join("2024-04.csv"), "id,label\n4,d\n").unwrap();
+    let csv = Csv::from_glob(dir.join("2024-*.csv"), true).unwrap();
+    let results = csv.collect::<Vec<_>>();
+    match results.last() {
+        Some(&Err(Error::Io(ref e))) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        _ => panic!("expected a header mismatch"),
+    }
+
+    // headerless partitions keep all their rows
+    fs::write(dir.join("part-1.csv"), "1,a\n2,b\n").unwrap();
+    fs::write(dir.join("part-2.csv"), "1,a\n3,c").unwrap();
+    fs::write(dir.join("part-3.csv"), "4,d\n").unwrap();
+    let rows = Csv::from_glob(dir.join("part-*.csv"), false).unwrap()
+        .map(|r| r.unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(rows, vec!["1,a", "2,b", "1,a", "3,c", "4,d"]);
+
+    assert!(Csv::from_glob(dir.join("*.json"), true).is_err());
+    assert!(Csv::from_glob(dir.join("*").join("a.csv"), true).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pivot_unpivot() {
+    use transform;
+
+    let long = "id,key,val\n1,a,x\n1,b,y\n2,b,z\n1,a,w\n3,c,\"v,v\"";
+    let mut csv = Csv::from_string(long).has_header(true);
+    let mut writer = Writer::from_writer(Vec::new());
+    assert_eq!(transform::pivot(&mut csv, &mut writer, &["id"], "key", "val").unwrap(), 3);
+    let wide = writer.into_inner().unwrap();
+    assert_eq!(&wide[..], &b"id,a,b,c\n1,w,y,\n2,,z,\n3,,,\"v,v\"\n"[..]);
+
+    let mut csv = Csv::from_reader(&wide[..]).has_header(true);
+    let mut writer = Writer::from_writer(Vec::new());
+    assert_eq!(transform::unpivot(&mut csv, &mut writer, &["id"], "key", "val").unwrap(), 9);
+    let melted = writer.into_inner().unwrap();
+    assert!(melted.starts_with(b"id,key,val\n1,a,w\n1,b,y\n1,c,\n2,a,\n"));
+    assert!(melted.ends_with(b"3,c,\"v,v\"\n"));
+
+    let mut csv = Csv::from_string(long).has_header(true);
+    let mut writer = Writer::from_writer(Vec::new());
+    match transform::pivot(&mut csv, &mut writer, &["nope"], "key", "val") {
+        Err(Error::UnknownColumn(ref name)) if name == "nope" => (),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn group_by_aggregate() {
+    use group::{Aggregate, Group};
+
+    let data = "region,shop,sales\nnorth,a,10\nsouth,b,\nnorth,c,2.5\nsouth,d,4\neast,e,";
+    let aggregates = vec![Aggregate::Count, Aggregate::Sum("sales".into()),
+                          Aggregate::Min("sales".into()), Aggregate::Max("sales".into()),
+                          Aggregate::Mean("sales".into())];
+    let mut csv = Csv::from_string(data).has_header(true);
+    let groups = csv.group_by(&["region"]).aggregate(&aggregates).unwrap();
+    assert_eq!(groups, vec![
+        Group { key: vec!["north".into()],
+                values: vec![Some(2.), Some(12.5), Some(2.5), Some(10.), Some(6.25)] },
+        Group { key: vec!["south".into()], values: vec![Some(2.), Some(4.), Some(4.), Some(4.), Some(4.)] },
+        Group { key: vec!["east".into()], values: vec![Some(1.), None, None, None, None] },
+    ]);
+
+    let sorted = "k,v\na,1\na,2\nb,3\na,4";
+    let mut csv = Csv::from_string(sorted).has_header(true);
+    let mut writer = Writer::from_writer(Vec::new());
+    let aggregates = [Aggregate::Count, Aggregate::Sum("v".into())];
+    assert_eq!(csv.group_by(&["k"]).sorted(true).write_to(&aggregates, &mut writer).unwrap(), 3);
+    assert_eq!(writer.into_inner().unwrap(), &b"k,count,sum(v)\na,2,3\nb,1,3\na,1,4\n"[..]);
+
+    let mut csv = Csv::from_string("k,v\na,x").has_header(true);
+    assert!(csv.group_by(&["k"]).aggregate(&[Aggregate::Sum("v".into())]).is_err());
+    let mut csv = Csv::from_string("k,v\na,1").has_header(true);
+    assert!(csv.group_by(&["z"]).aggregate(&[Aggregate::Count]).is_err());
+}
+
+#[test]
+fn rename_headers() {
+    use transform;
+
+    let csv = Csv::from_string("Id,Full Name,x\n1,\"a,b\",2").has_header(true)
+        .rename_header("x", "value");
+    let mut csv = csv.normalize_headers(Normalization::all()).rename_header("full_name", "name");
+    assert_eq!(csv.headers(), vec!["id", "name", "value"]);
+    assert_eq!(csv.column_index("name"), Some(1));
+    let mut writer = Writer::from_writer(Vec::new());
+    assert_eq!(transform::copy(&mut csv, &mut writer).unwrap(), 1);
+    assert_eq!(writer.into_inner().unwrap(), &b"id,name,value\n1,\"a,b\",2\n"[..]);
+}
+
+#[test]
+fn replace_values() {
+    use transform;
+
+    let data = "id,sex,note\n1,M,N/A\n2,F,\"N/A\"\n3,X,\"a,b\"";
+    let mut csv = Csv::from_string(data)
+        .replace_value(2, "N/A", "")
+        .has_header(true)
+        .replace_value("sex", "M", "male")
+        .replace_value("sex", "X", "\"x\", unknown")
+        .replace_value("note", "a,b", "c");
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(u32, String, Option<String>)>().unwrap(), (1, "male".to_string(), None));
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["2", "F", ""]);
+    let mut writer = Writer::from_writer(Vec::new());
+    transform::copy(&mut csv, &mut writer).unwrap();
+    assert_eq!(writer.into_inner().unwrap(),
+               &b"id,sex,note\n3,\"\"\"x\"\", unknown\",c\n"[..]);
+
+    let mut csv = Csv::from_string("a,b\nb,a").has_header(true).replace_value(0, "a", "z");
+    assert_eq!(csv.headers(), vec!["a", "b"]);
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "b,a");
+
+    let mut csv = Csv::from_string("a,xx,\"b,c\"\nd,e,f").replace_value(1, "xx", "y");
+    assert_eq!(csv.next().unwrap().unwrap().columns().unwrap().collect::<Vec<_>>(), vec!["a", "y", "b,c"]);
+    assert_eq!(csv.next().unwrap().unwrap().columns().unwrap().collect::<Vec<_>>(), vec!["d", "e", "f"]);
+
+    // raw rows keep the escaping of the source
+    let mut csv = Csv::from_string("'it''s';x\ny;'a;b'").delimiter(b';').quote(b'\'').raw(true)
+        .replace_value(0, "it's", "a'b;\"c")
+        .replace_value(1, "a;b", "q");
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "'a''b;\"c';x");
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "y;q");
+    let mut csv = Csv::from_string("\"a\\\"b\",c").escape(Escape::Backslash).raw(true)
+        .replace_value(0, "a\"b", "\"");
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "\"\\\"\",c");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn regex_filters() {
+    use filter::Filter;
+    use regex::bytes::Regex;
+    use std::io::Cursor;
+
+    let data = "level,msg\nINFO,start\nERROR,\"disk, full\"\nDEBUG,tick\nWARN,slow\nERROR,debug me";
+    let rows = |csv: Csv<&[u8]>| csv.map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>();
+    let csv = Csv::from_string(data).has_header(true)
+        .filter_regex("level", Regex::new("^(ERROR|WARN)$").unwrap(), Filter::Keep);
+    assert_eq!(rows(csv), vec!["ERROR,\"disk, full\"", "WARN,slow", "ERROR,debug me"]);
+
+    let csv = Csv::from_string(data).has_header(true)
+        .filter_regex_any(Regex::new("(?i)debug").unwrap(), Filter::Drop)
+        .filter_regex(1, Regex::new("^s").unwrap(), Filter::Drop);
+    assert_eq!(rows(csv), vec!["ERROR,\"disk, full\""]);
+
+    let csv = Csv::from_reader(Cursor::new(data)).has_header(true)
+        .filter_regex("msg", Regex::new("^disk").unwrap(), Filter::Keep);
+    let rev = csv.reverse().unwrap().map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(rev, vec!["ERROR,\"disk, full\""]);
+}
+
+#[test]
+fn query() {
+    use query::Query;
+
+    let data = "name,age,\"home town\"\nbob,42,Paris\nalice,37,\"Lyon, FR\"\neve,12,Paris\nmallory,,Nice\ntrent,100,Nice";
+    let run = |q: &str| {
+        let mut csv = Csv::from_string(data).has_header(true);
+        let mut writer = Writer::from_writer(Vec::new());
+        Query::parse(q).and_then(|q| q.run(&mut csv, &mut writer))
+            .map(|n| (n, String::from_utf8(writer.into_inner().unwrap()).unwrap()))
+    };
+
+    assert_eq!(run("select * limit 1").unwrap(), (1, "name,age,home town\nbob,42,Paris\n".to_string()));
+    assert_eq!(run("SELECT name, \"home town\" WHERE age > 20 AND NOT (\"home town\" = 'Paris')").unwrap(),
+               (2, "name,home town\nalice,\"Lyon, FR\"\ntrent,Nice\n".to_string()));
+    assert_eq!(run("select name where age < 40 or name = 'mallory' order by age desc").unwrap(),
+               (3, "name\nmallory\nalice\neve\n".to_string()));
+    assert_eq!(run("select name, age order by \"home town\", age desc limit 3").unwrap(),
+               (3, "name,age\nalice,37\nmallory,\ntrent,100\n".to_string()));
+    assert_eq!(run("select name where name >= 'c' and age != 12").unwrap(),
+               (2, "name\nmallory\ntrent\n".to_string()));
+    assert_eq!(run("select name where name = 'o''neil'").unwrap(), (0, "name\n".to_string()));
+
+    // numbers sort before text, whatever the mix
+    let mut csv = Csv::from_string("v\n1a\n10\nNaN\n9\nb\n-1").has_header(true);
+    let mut writer = Writer::from_writer(Vec::new());
+    Query::parse("select v order by v").unwrap().run(&mut csv, &mut writer).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), &b"v\n-1\n9\n10\nNaN\n1a\nb\n"[..]);
+    let mut csv = Csv::from_string(data).has_header(true).memory_budget(64);
+    let mut writer = Writer::from_writer(Vec::new());
+    match Query::parse("select name order by age").unwrap().run(&mut csv, &mut writer) {
+        Err(Error::MemoryBudget(64)) => (),
+        r => panic!("unexpected {:?}", r),
+    }
+
+    assert!(run("select").is_err());
+    assert!(run("select name where").is_err());
+    assert!(run("select name limit x").is_err());
+    assert!(run("select name where (age > 1").is_err());
+    match run("select nope") {
+        Err(Error::UnknownColumn(ref n)) if n == "nope" => (),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[cfg(feature = "polars")]
+#[test]
+fn to_dataframe() {
+    use polars::prelude::DataType;
+
+    let data = "name,age,score,ok,note\nbob,42,1.5,true,\nalice,,2,false,x\neve,7,3,true,y,extra";
+    let mut csv = Csv::from_string(data).has_header(true).flexible(true);
+    let df = csv.to_dataframe().unwrap();
+    assert_eq!(df.shape(), (3, 6));
+    let types = df.dtypes();
+    assert_eq!(types, vec![DataType::String, DataType::Int64, DataType::Float64,
+                           DataType::Boolean, DataType::String, DataType::String]);
+    assert_eq!(df.column("age").unwrap().null_count(), 1);
+    assert_eq!(df.column("column_5").unwrap().null_count(), 2);
+
+    // values are coerced as by `Csv::coerce`, mixed columns keep their text
+    let data = "n,mixed\n\"1,000\",true\n2,\"1,5\"";
+    let df = Csv::from_string(data).has_header(true).thousands_separator(',').to_dataframe().unwrap();
+    assert_eq!(df.dtypes(), vec![DataType::Int64, DataType::String]);
+    assert_eq!(df.column("n").unwrap().i64().unwrap().get(0), Some(1000));
+    assert_eq!(df.column("mixed").unwrap().str().unwrap().get(1), Some("1,5"));
+
+    let df = Csv::from_string("w\n42 kg").has_header(true).field_hook("w", |s| s.trim_end_matches(" kg").to_string())
+        .to_dataframe().unwrap();
+    assert_eq!(df.column("w").unwrap().i64().unwrap().get(0), Some(42));
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_rows() {
+    use serde_json::json;
+
+    let data = "name,age,score,ok,note\nbob,42,1.5,true,\n\"a,b\",x,1e400,false,\"\"\"q\"\"\",extra";
+    let rows = Csv::from_string(data).has_header(true).flexible(true).json_rows()
+        .infer_types(true).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rows[0], json!({"name": "bob", "age": 42, "score": 1.5, "ok": true, "note": null}));
+    assert_eq!(rows[1], json!({"name": "a,b", "age": "x", "score": "1e400", "ok": false,
+                               "note": "\"q\"", "column_5": "extra"}));
+
+    let rows = Csv::from_string("1,true").json_rows().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rows, vec![json!({"column_0": "1", "column_1": "true"})]);
+
+    // values are coerced as by `Csv::coerce`
+    let rows = Csv::from_string("n\n\" 1,000 \"").has_header(true).thousands_separator(',').json_rows()
+        .infer_types(true).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rows, vec![json!({"n": 1000})]);
+
+    let mut rows = Csv::from_string("a,b,a\n1,2,3").has_header(true).json_rows();
+    assert!(matches!(rows.next(), Some(Err(Error::DuplicateHeader(ref h))) if h == "a"));
+    assert!(rows.next().is_none());
+
+    let mut rows = Csv::from_string("column_1\n1\n2,3").has_header(true).flexible(true).json_rows();
+    assert_eq!(rows.next().unwrap().unwrap(), json!({"column_1": "1"}));
+    assert!(matches!(rows.next(), Some(Err(Error::DuplicateHeader(ref h))) if h == "column_1"));
+
+    let rows = Csv::from_string("w\n42 kg").has_header(true).field_hook("w", |s| s.trim_end_matches(" kg").to_string())
+        .json_rows().infer_types(true).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rows, vec![json!({"w": 42})]);
+}
+
+#[test]
+fn parse_record() {
+    use std::borrow::Cow;
+    use parse_record;
+
+    let cols = parse_record("a;\"b;c\";;\"\"\r\n", b';').unwrap();
+    assert_eq!(cols, vec!["a", "b;c", "", ""]);
+    assert!(cols.iter().all(|c| matches!(*c, Cow::Borrowed(_))));
+
+    let cols = parse_record("é,\"x\"\"y\",\"multi\nline\"", b',').unwrap();
+    assert_eq!(cols, vec!["é", "x\"y", "multi\nline"]);
+    assert_eq!(parse_record("", b',').unwrap(), vec![""]);
+    assert!(parse_record("a,b\nc,d", b',').is_err());
+}
+
+#[test]
+fn push_parser() {
+    use parser::{Handler, Parser};
+
+    #[derive(Default)]
+    struct Events(Vec<String>);
+
+    impl Handler for Events {
+        fn field(&mut self, field: &[u8]) {
+            self.0.push(String::from_utf8(field.to_vec()).unwrap());
+        }
+        fn record(&mut self) {
+            self.0.push("|".to_string());
+        }
+    }
+
+    let data = b"a;\"b;\"\"c\"\"\";\r\n\n\"multi\nline\";x\r\n;\"\"";
+    let expected = vec!["a", "b;\"c\"", "", "|", "", "|", "multi\nline", "x", "|", "", "", "|"];
+    for size in 1..data.len() + 1 {
+        let mut parser = Parser::new().delimiter(b';');
+        let mut events = Events::default();
+        for chunk in data.chunks(size) {
+            parser.feed(chunk, &mut events).unwrap();
+        }
+        parser.finish(&mut events).unwrap();
+        assert_eq!(events.0, expected, "chunks of {}", size);
+    }
+
+    let mut parser = Parser::new();
+    let mut events = Events::default();
+    match parser.feed(b"a,b\"c\nd", &mut events) {
+        Err(Error::UnexpextedQuote) => (),
+        r => panic!("unexpected {:?}", r),
+    }
+    assert!(parser.feed(b"\"x\"y\n", &mut events).is_err());
+    parser.feed(b"e,f\n", &mut events).unwrap();
+    parser.finish(&mut events).unwrap();
+    assert_eq!(events.0, vec!["e", "f", "|"]);
+
+    // records following a failing one in the same chunk are kept
+    let mut parser = Parser::new();
+    let mut events = Events::default();
+    assert!(matches!(parser.feed(b"a,b\nc,d\"x\ne,f\ng,h\n", &mut events), Err(Error::UnexpextedQuote)));
+    assert_eq!(events.0, vec!["a", "b", "|", "e", "f", "|", "g", "h", "|"]);
+
+    let mut events = Events::default();
+    parser.feed(b"i,\"j\nk", &mut events).unwrap();
+    assert!(matches!(parser.finish(&mut events), Err(Error::Parse(_))));
+    assert!(events.0.is_empty());
+}
+
+#[test]
+fn row_parser_would_block() {
+    use std::collections::VecDeque;
+    use std::io::{self, Read};
+    use parser::RowParser;
+
+    /// yields its chunks, `None` being a `WouldBlock` error
+    struct NonBlocking(VecDeque<Option<&'static [u8]>>);
+
+    impl Read for NonBlocking {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.pop_front() {
+                Some(Some(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    Ok(chunk.len())
+                },
+                Some(None) => Err(io::ErrorKind::WouldBlock.into()),
+                None => Ok(0),
+            }
+        }
+    }
+
+    let columns = |parser: &mut RowParser| parser.by_ref()
+        .map(|r| r.columns().unwrap().map(|c| c.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut reader = NonBlocking(vec![Some(&b"a;\"b"[..]), None, Some(&b";c\"\r\nd"[..]),
+                                      Some(&b";e\n"[..]), None, Some(&b"f"[..])].into());
+    let mut parser = RowParser::new().delimiter(b';');
+    assert!(!parser.read_from(&mut reader).unwrap());
+    assert_eq!(parser.available(), 0);
+    assert!(!parser.read_from(&mut reader).unwrap());
+    assert_eq!(columns(&mut parser), vec![vec!["a", "b;c"], vec!["d", "e"]]);
+    assert!(parser.read_from(&mut reader).unwrap());
+    assert!(parser.is_finished());
+    assert_eq!(columns(&mut parser), vec![vec!["f"]]);
+
+    // rows after a malformed one, within the same chunk, are queued
+    let mut reader = NonBlocking(vec![Some(&b"a;b\nc;d\"x\ne;f\ng"[..]), Some(&b";h\n"[..])].into());
+    let mut parser = RowParser::new().delimiter(b';');
+    assert!(matches!(parser.read_from(&mut reader), Err(Error::UnexpextedQuote)));
+    assert!(parser.read_from(&mut reader).unwrap());
+    assert_eq!(columns(&mut parser), vec![vec!["a", "b"], vec!["e", "f"], vec!["g", "h"]]);
+
+    let mut parser = RowParser::new();
+    parser.feed(b"\"open").unwrap();
+    assert!(matches!(parser.finish(), Err(Error::Parse(_))));
+    assert_eq!(parser.available(), 0);
+}
+
+#[test]
+fn quotes_across_buffer_boundaries() {
+    let data = "\"a\"\"b\",\"\"\r\n\"c\",d\"\"\n\"\",\"e\"\"\"";
+    for capacity in 1..data.len() + 1 {
+        let reader = BufReader::with_capacity(capacity, data.as_bytes());
+        let rows = Csv::from_reader(reader).flexible(true)
+            .map(|r| r.map(|r| r.columns().unwrap().map(|c| c.to_string()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2, "capacity {}", capacity);
+        assert_eq!(rows[0].as_ref().unwrap(), &vec!["a\"b", ""], "capacity {}", capacity);
+        match rows[1] {
+            Err(Error::UnexpextedQuote) => (),
+            ref r => panic!("capacity {}: unexpected {:?}", capacity, r),
+        }
+    }
+    let reader = BufReader::with_capacity(1, &b"\"\",\"e\"\"\""[..]);
+    let row = Csv::from_reader(reader).next().unwrap().unwrap();
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["", "e\""]);
+}
+
+#[test]
+fn raw_columns() {
+    let data = "a,\"b,\"\"c\"\"\",\"\"\r\n\"d\"\"\",e";
+    let mut csv = Csv::from_string(data).flexible(true).raw(true);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.as_bytes(), &b"a,\"b,\"\"c\"\"\",\"\""[..]);
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a", "\"b,\"\"c\"\"\"", "\"\""]);
+    assert_eq!(row.bytes_columns().next_back(), Some(&b"\"\""[..]));
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(String, String)>().unwrap(), ("\"d\"\"\"".to_string(), "e".to_string()));
+
+    let row = Csv::from_string(data).next().unwrap().unwrap();
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a", "b,\"c\"", ""]);
+}
+
+/// Rows of arbitrary bytes written with an arbitrary delimiter
+#[derive(Debug, Clone)]
+struct WriterInput {
+    rows: Vec<Vec<Vec<u8>>>,
+    delimiter: u8,
+    crlf: bool,
+}
+
+impl Arbitrary for WriterInput {
+    fn arbitrary(g: &mut Gen) -> WriterInput {
+        // favour bytes with a special meaning
+        let bytes = [b'a', b'b', b' ', b',', b';', b'\t', b'"', b'\r', b'\n', b'\\', 0xef, 0xbb, 0xbf];
+        let delimiter = *g.choose(b",;\t|").unwrap();
+        let rows = (0..usize::arbitrary(g) % 8).map(|_| {
+            (0..1 + usize::arbitrary(g) % 5).map(|_| {
+                (0..usize::arbitrary(g) % 6).map(|_| {
+                    if bool::arbitrary(g) { *g.choose(&bytes).unwrap() } else { u8::arbitrary(g) }
+                }).collect()
+            }).collect()
+        }).collect();
+        WriterInput { rows, delimiter, crlf: bool::arbitrary(g) }
+    }
+}
+
+fn write_rows(input: &WriterInput) -> Vec<u8> {
+    let mut w = Writer::from_writer(Vec::new()).delimiter(input.delimiter).crlf(input.crlf);
+    for row in &input.rows {
+        w.write(row).unwrap();
+    }
+    w.into_inner().unwrap()
+}
+
+#[test]
+fn writer_read_write_round_trip() {
+    fn prop(input: WriterInput) -> bool {
+        let data = write_rows(&input);
+        let rows = Csv::from_reader(&*data).delimiter(input.delimiter).flexible(true)
+            .map(|r| r.unwrap().bytes_columns().map(|c| c.to_vec()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        rows == input.rows
+    }
+    QuickCheck::new().tests(2000).quickcheck(prop as fn(WriterInput) -> bool);
+}
+
+#[test]
+fn writer_minimal_quoting() {
+    fn prop(input: WriterInput) -> bool {
+        let d = input.delimiter;
+        input.rows.iter().flatten().all(|col| {
+            let input = WriterInput { rows: vec![vec![col.clone(), Vec::new()]], delimiter: d, crlf: false };
+            let data = write_rows(&input);
+            let special = col.iter().any(|&b| b == d || b == b'"' || b == b'\r' || b == b'\n')
+                || col.starts_with(b"\xef\xbb\xbf");
+            data.starts_with(b"\"") == special
+        })
+    }
+    QuickCheck::new().tests(2000).quickcheck(prop as fn(WriterInput) -> bool);
+}
+
+#[test]
+fn canonicalize() {
+    use transform;
+
+    let inputs = ["\u{feff}a;\"b\";\"c \"\"d\"\"\"\n\"e;f\";g;\"\"\n",
+                  "a,b,\"c \"\"d\"\"\"\r\n\"e;f\",g,\r\n",
+                  "\"a\",\"b\",\"c \"\"d\"\"\"\r\n\"e;f\",\"g\",\"\""];
+    let delimiters = [b';', b',', b','];
+    for (input, &delimiter) in inputs.iter().zip(delimiters.iter()) {
+        let mut csv = Csv::from_string(input).delimiter(delimiter);
+        let canonical = transform::canonicalize(&mut csv, Vec::new()).unwrap();
+        assert_eq!(canonical, &b"a,b,\"c \"\"d\"\"\"\r\ne;f,g,\r\n"[..], "{:?}", input);
+    }
+}
+
+#[test]
+fn validate_report() {
+    use validate::{Issue, IssueKind};
+
+    let data = &b"a,b\r\nc,\"d\ne\",f\ng\"h\"\n\"i\"j,k\nl,\xffm\n\"n,o\r\n"[..];
+    let report = Csv::from_reader(data).has_header(true).validate().unwrap();
+    assert_eq!(report.rows, 5);
+    assert!(!report.is_valid());
+    let issue = |record, line, offset, kind| Issue { record, line, offset, kind };
+    assert_eq!(report.issues, vec![
+        issue(2, 1, 5, IssueKind::ColumnCount { expected: 2, found: 3 }),
+        issue(3, 3, 15, IssueKind::UnexpectedQuote),
+        issue(4, 4, 20, IssueKind::UnescapedQuote),
+        issue(5, 5, 27, IssueKind::InvalidUtf8 { column: 1 }),
+        issue(6, 6, 32, IssueKind::UnclosedQuote),
+    ]);
+
+    let report = Csv::from_string("a;b\n\n;\n").delimiter(b';').empty_record(EmptyRecord::NoColumns)
+        .validate().unwrap();
+    assert_eq!(report.rows, 3);
+    assert!(report.is_valid());
+}
+
+#[test]
+fn repair_quotes() {
+    use repair::{Fix, FixKind};
+
+    let data = "h1,h2\na\"b,\"c\"d\"\n\"e\nf\",g\n\"h,i\nj,k\n\"l\"\"m\",\"n\r\n";
+    let mut repair = Csv::from_string(data).has_header(true).flexible(true).repair().max_lines(2);
+    assert_eq!(repair.headers(), vec!["h1", "h2"]);
+    let rows = repair.by_ref()
+        .map(|r| r.unwrap().columns().unwrap().map(|c| c.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![vec!["a\"b", "c\"d"], vec!["e\nf", "g"], vec!["h,i"], vec!["j", "k"],
+                          vec!["l\"m", "n"]]);
+    let fix = |record, line, kind| Fix { record, line, kind };
+    assert_eq!(repair.fixes(), &[
+        fix(2, 1, FixKind::StrayQuote { column: 0 }),
+        fix(2, 1, FixKind::StrayQuote { column: 1 }),
+        fix(4, 4, FixKind::UnclosedQuote { column: 0 }),
+        fix(6, 6, FixKind::UnclosedQuote { column: 1 }),
+    ][..]);
+}
+
+#[test]
+fn scan_utf8_offsets() {
+    use validate::Utf8Issue;
+
+    let data = &b"a,\xff\r\n\"b\"\"\xc3\",c\xe2\x82x\xfe\nd\"\xf0,e\nf,g"[..];
+    let issues = Csv::from_reader(data).scan_utf8().unwrap();
+    let issue = |record, line, column, offset, len| Utf8Issue { record, line, column, offset, len };
+    assert_eq!(issues, vec![
+        issue(1, 1, Some(1), 2, 1),
+        issue(2, 2, Some(0), 9, 1),
+        issue(2, 2, Some(1), 13, 2),
+        issue(2, 2, Some(1), 16, 1),
+        issue(3, 3, None, 20, 1),
+    ]);
+    assert_eq!(data[9], 0xc3);
+    assert_eq!(data[20], 0xf0);
+}
+
+#[test]
+fn metrics_hook() {
+    use metrics::Metrics;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Log(Mutex<Vec<String>>);
+
+    impl Metrics for Log {
+        fn record(&self, bytes: u64) {
+            self.0.lock().unwrap().push(bytes.to_string());
+        }
+        fn error(&self, error: &Error) {
+            self.0.lock().unwrap().push(error.to_string());
+        }
+    }
+
+    let log = Arc::new(Log::default());
+    let csv = Csv::from_string("h\r\nab\n\"c\nd\"\ne\"").has_header(true).metrics(log.clone());
+    assert_eq!(csv.count(), 3);
+    assert_eq!(*log.0.lock().unwrap(), vec!["3".to_string(), "6".to_string(),
+                                            Error::UnexpextedQuote.to_string()]);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_events() {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::{Event, Metadata, Subscriber};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+
+    /// collects event messages along with the name of their span
+    struct Collect {
+        events: Arc<Mutex<Vec<String>>>,
+        spans: Mutex<Vec<String>>,
+        entered: Mutex<Vec<u64>>,
+    }
+
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Collect {
+        fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span.metadata().name().to_string());
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            let span = self.entered.lock().unwrap().last()
+                .map_or_else(String::new, |&id| self.spans.lock().unwrap()[id as usize - 1].clone());
+            self.events.lock().unwrap().push(format!("{}: {}", span, message.0));
+        }
+        fn enter(&self, id: &Id) {
+            self.entered.lock().unwrap().push(id.into_u64());
+        }
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let collect = Collect { events: events.clone(), spans: Mutex::default(), entered: Mutex::default() };
+    tracing::subscriber::with_default(collect, || {
+        let csv = Csv::from_string("h\na\nb\nc\"").has_header(true).trace_progress(2);
+        assert_eq!(csv.count(), 3);
+    });
+    assert_eq!(*events.lock().unwrap(), vec!["csv: csv headers parsed", "csv: csv progress",
+                                             "csv: csv read error"]);
+}
+
+#[test]
+fn cancel_reading() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut csv = Csv::from_string("a\nb\nc").cancel_on(cancel.clone());
+    assert!(csv.next().unwrap().is_ok());
+    cancel.store(true, Ordering::Relaxed);
+    match csv.next() {
+        Some(Err(Error::Cancelled)) => (),
+        r => panic!("unexpected {:?}", r.map(|r| r.map(|_| ()))),
+    }
+    assert!(csv.next().is_none());
+}
+
+#[test]
+fn read_timeout() {
+    use std::thread;
+    use std::time::Duration;
+
+    /// yields its first chunk immediately, then stalls before the next one
+    struct Stalling(Vec<&'static [u8]>);
+
+    impl Read for Stalling {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            if self.0.len() == 1 {
+                thread::sleep(Duration::from_millis(500));
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    let mut csv = Csv::from_reader_timeout(Stalling(vec![b"a\nb", b"\nc"]), Duration::from_millis(50));
+    assert!(csv.next().unwrap().is_ok());
+    match csv.next() {
+        Some(Err(Error::Io(ref e))) if e.kind() == io::ErrorKind::TimedOut => (),
+        r => panic!("unexpected {:?}", r.map(|r| r.map(|_| ()))),
+    }
+    assert!(csv.next().is_none());
+
+    let csv = Csv::from_reader_timeout(Stalling(vec![b"a\nb", b"\nc"]), Duration::from_secs(5));
+    assert_eq!(csv.count(), 3);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn async_stream() {
+    use futures_core::Stream;
+    use futures_io::{AsyncBufRead, AsyncRead};
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// yields its chunks one at a time, pending before each of them
+    struct Chunks(Vec<&'static [u8]>, bool);
+
+    impl AsyncRead for Chunks {
+        fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut [u8]) -> Poll<io::Result<usize>> {
+            unreachable!()
+        }
+    }
+
+    impl AsyncBufRead for Chunks {
+        fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+            let this = self.get_mut();
+            if !this.1 && !this.0.is_empty() {
+                this.1 = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(Ok(this.0.first().cloned().unwrap_or(b"")))
+        }
+        fn consume(self: Pin<&mut Self>, amt: usize) {
+            let this = self.get_mut();
+            this.0[0] = &this.0[0][amt..];
+            if this.0[0].is_empty() {
+                this.0.remove(0);
+                this.1 = false;
+            }
+        }
+    }
+
+    let reader = Chunks(vec![b"h1;h2\r", b"\na;\"b", b"\n\"\"c\"\nd", b";e"], false);
+    let mut stream = Csv::detached().delimiter(b';').has_header(true).into_stream(reader);
+    let mut cx = Context::from_waker(Waker::noop());
+    let (mut rows, mut pending) = (Vec::new(), 0);
+    loop {
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Pending => pending += 1,
+            Poll::Ready(Some(row)) => rows.push(row.unwrap().decode::<(String, String)>().unwrap()),
+            Poll::Ready(None) => break,
+        }
+    }
+    assert_eq!(stream.headers(), vec!["h1", "h2"]);
+    assert_eq!(rows, vec![("a".to_string(), "b\n\"c".to_string()), ("d".to_string(), "e".to_string())]);
+    assert_eq!(pending, 4);
+}
+
+#[test]
+fn sharded_writer() {
+    use shard::ShardedWriter;
+    use std::thread;
+    use writer::Writer;
+
+    let shards = (0..3).map(|_| Writer::from_writer(Vec::new())).collect();
+    let writer = ShardedWriter::new(shards).unwrap();
+    thread::scope(|s| {
+        for t in 0..4 {
+            let writer = &writer;
+            s.spawn(move || for i in 0..50 {
+                let (t, i) = (t.to_string(), i.to_string());
+                writer.write([&t, &i]).unwrap();
+                writer.write_to(i.len() - 1, ["key", &i]).unwrap();
+            });
+        }
+    });
+    let shards = writer.into_inner().unwrap();
+    let mut rows = Vec::new();
+    for shard in &shards {
+        let mut last = ::std::collections::HashMap::new();
+        for row in Csv::from_reader(&shard[..]) {
+            let (t, i) = row.unwrap().decode::<(String, usize)>().unwrap();
+            // rows of a thread keep their order within a shard
+            if t != "key" {
+                assert!(last.insert(t.clone(), i).is_none_or(|prev| prev < i));
+            }
+            rows.push((t, i));
+        }
+    }
+    rows.sort();
+    let mut expected = (0..4).flat_map(|t| (0..50).map(move |i| (t.to_string(), i))).collect::<Vec<_>>();
+    expected.extend((0..4).flat_map(|_| (0..50).map(|i| ("key".to_string(), i))));
+    expected.sort();
+    assert_eq!(rows, expected);
+    assert!(ShardedWriter::<Vec<u8>>::new(Vec::new()).is_err());
+}
+
+#[test]
+fn memory_budget() {
+    use arena::Arena;
+    use group::Aggregate;
+
+    let data = "k,v\na,1\nb,2\nc,3\nd,4";
+    let mut csv = Csv::from_string(data).has_header(true).memory_budget(10);
+    match csv.group_by(&["k"]).aggregate(&[Aggregate::Count]) {
+        Err(Error::MemoryBudget(10)) => (),
+        r => panic!("unexpected {:?}", r),
+    }
+    let mut csv = Csv::from_string(data).has_header(true).memory_budget(10);
+    assert_eq!(csv.group_by(&["k"]).sorted(true).aggregate(&[Aggregate::Count]).unwrap().len(), 4);
+
+    let mut writer = Writer::from_writer(Vec::new());
+    let mut csv = Csv::from_string(data).has_header(true).memory_budget(4);
+    assert!(::transform::pivot(&mut csv, &mut writer, &["k"], "v", "v").is_err());
+    // 3 bytes and 2 column ends per row
+    let row_size = 3 + 2 * ::std::mem::size_of::<usize>();
+    let csv = Csv::from_reader(io::Cursor::new(data)).memory_budget(2 * row_size);
+    assert_eq!(csv.tail(2).unwrap().len(), 2);
+    let csv = Csv::from_reader(io::Cursor::new(data)).memory_budget(2 * row_size);
+    assert!(csv.tail(3).is_err());
+
+    let mut csv = Csv::from_string(data).memory_budget(5);
+    let mut arena = Arena::new();
+    assert_eq!(csv.read_batch(&mut arena, 10).unwrap(), 2);
+    assert_eq!(csv.read_batch(&mut arena, 10).unwrap(), 2);
+    assert_eq!(csv.read_batch(&mut arena, 10).unwrap(), 1);
+}
+
+#[test]
+fn spill_to_disk() {
+    use group::Aggregate;
+    use spill::Spill;
+
+    let dir = env::temp_dir().join("quick-csv-spill-test");
+    fs::create_dir_all(&dir).unwrap();
+    let data = (0..500).map(|i| format!("k{},{}", (i * 7) % 13, i)).collect::<Vec<_>>().join("\n");
+    let data = format!("k,v\n{}", data);
+    #[cfg(feature = "flate2")]
+    let spill = Spill::new(&dir).compress(true);
+    #[cfg(not(feature = "flate2"))]
+    let spill = Spill::new(&dir);
+
+    let sort = |budget: usize, spill: Option<Spill>| {
+        let mut csv = Csv::from_string(&data).has_header(true).memory_budget(budget);
+        if let Some(spill) = spill {
+            csv = csv.spill_to(spill);
+        }
+        let mut writer = Writer::from_writer(Vec::new());
+        ::transform::sort(&mut csv, &mut writer, &["k"]).map(|n| (n, writer.into_inner().unwrap()))
+    };
+    let (n, sorted) = sort(usize::MAX, None).unwrap();
+    assert_eq!(n, 500);
+    assert!(sorted.starts_with(b"k,v\nk0,0\nk0,13\n"));
+    assert_eq!(sort(1000, Some(spill.clone())).unwrap(), (n, sorted));
+    assert!(sort(1000, None).is_err());
+
+    let aggregates = [Aggregate::Count, Aggregate::Sum("v".into()), Aggregate::Min("v".into())];
+    let group = |budget: usize| {
+        Csv::from_string(&data).has_header(true).memory_budget(budget).spill_to(spill.clone())
+            .group_by(&["k"]).aggregate(&aggregates).unwrap()
+    };
+    let groups = group(usize::MAX);
+    assert_eq!(groups.len(), 13);
+    assert_eq!(group(100), groups);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn coerce_chain() {
+    use coerce::{Counts, Type, Value};
+
+    let csv = Csv::from_string("a,b,c\n1,1 000,true\n2.5,,x\n2.5,,true\nn/a,3,false")
+        .has_header(true)
+        .thousands_separator(' ');
+    let mut rows = csv.coerce(&[Type::Int, Type::Float, Type::Str]).column(2, &[Type::Bool]);
+    assert_eq!(rows.next().unwrap().unwrap(), vec![Value::Int(1), Value::Int(1000), Value::Bool(true)]);
+    assert!(rows.next().unwrap().is_err());
+    assert_eq!(rows.next().unwrap().unwrap(), vec![Value::Float(2.5), Value::Null, Value::Bool(true)]);
+    assert_eq!(rows.next().unwrap().unwrap(),
+               vec![Value::Str("n/a".to_string()), Value::Int(3), Value::Bool(false)]);
+    assert_eq!(rows.counts()[0], Counts { float: 1, int: 1, str: 1, ..Counts::default() });
+    assert_eq!(rows.counts()[0].winner(), Some(Type::Int));
+    assert_eq!(rows.counts()[1].winner(), Some(Type::Int));
+    assert_eq!(rows.counts()[1].null, 1);
+}
+
+#[test]
+fn profile_nulls() {
+    use profile::ColumnProfile;
+
+    let mut csv = Csv::from_string("a,b\n1,null\n,2\nNA\n4,5,6").has_header(true).flexible(true);
+    let profile = csv.profile().null_tokens(&["NA", "null"]).run().unwrap();
+    assert_eq!(profile.rows, 4);
+    assert_eq!(profile.columns, vec![
+        ColumnProfile { name: Some("a".to_string()), values: 2, empty: 1, null_tokens: 1, missing: 0, distinct: None },
+        ColumnProfile { name: Some("b".to_string()), values: 2, empty: 0, null_tokens: 1, missing: 1, distinct: None },
+        ColumnProfile { name: None, values: 1, empty: 0, null_tokens: 0, missing: 3, distinct: None },
+    ]);
+    assert_eq!(profile.columns[1].nulls(), 2);
+}
+
+#[test]
+fn profile_cardinality() {
+    use profile::{Cardinality, HyperLogLog};
+
+    let data = (0..20000).map(|i| format!("{},{}", i % 7, i)).collect::<Vec<_>>().join("\n");
+    let mut csv = Csv::from_string(&data);
+    let profile = csv.profile().distinct(Cardinality::Exact).run().unwrap();
+    assert_eq!(profile.columns.iter().map(|c| c.distinct).collect::<Vec<_>>(), vec![Some(7), Some(20000)]);
+
+    let mut csv = Csv::from_string(&data);
+    let profile = csv.profile().distinct(Cardinality::Approximate(12)).run().unwrap();
+    assert_eq!(profile.columns[0].distinct, Some(7));
+    let estimate = profile.columns[1].distinct.unwrap() as f64;
+    assert!((estimate / 20000. - 1.).abs() < 0.05, "{}", estimate);
+
+    let mut csv = Csv::from_string(&data).memory_budget(1000);
+    assert!(csv.profile().distinct(Cardinality::Exact).run().is_err());
+    assert_eq!(HyperLogLog::new(4).count(), 0);
+}
+
+#[test]
+fn codegen_struct() {
+    let data = "Cust. ID,firstName,type,2nd,ok,x,x\n1,a,t,1,true,1,\n2,,t,1.5,false,a,";
+    let mut csv = Csv::from_string(data).has_header(true);
+    let source = csv.codegen("Record").generate().unwrap();
+    assert_eq!(source, "\
+#[derive(Debug, Deserialize)]
+pub struct Record {
+    #[serde(rename = \"Cust. ID\")]
+    pub cust_id: i64,
+    #[serde(rename = \"firstName\")]
+    pub first_name: Option<String>,
+    #[serde(rename = \"type\")]
+    pub type_: String,
+    #[serde(rename = \"2nd\")]
+    pub _2nd: f64,
+    pub ok: bool,
+    pub x: String,
+    #[serde(rename = \"x\")]
+    pub x_2: Option<String>,
+}
+");
+
+    let mut csv = Csv::from_string("a,b\n1,\n2,c").has_header(true);
+    let source = csv.codegen("R").sample(1).derive(&["Debug"]).generate().unwrap();
+    assert!(source.starts_with("#[derive(Debug)]\n"));
+    assert!(source.contains("pub a: i64,\n    pub b: Option<String>,"));
+    let row = csv.next().unwrap().unwrap().decode::<(i64, Option<String>)>().unwrap();
+    assert_eq!(row, (2, Some("c".to_string())));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_csv_record() {
+    use CsvRecord;
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Order {
+        #[csv(name = "Cust. ID")]
+        id: u64,
+        total: Option<f64>,
+        #[csv(default)]
+        note: String,
+        #[csv(index = 0)]
+        first: String,
+        #[cfg(feature = "chrono")]
+        #[csv(date_format = "%d/%m/%Y")]
+        date: Option<::chrono::NaiveDate>,
+    }
+
+    let csv = Csv::from_string("total,Cust. ID,date\n2.5,1,31/12/2024\n,2,").has_header(true);
+    let orders = csv.records::<Order>().collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(orders[0].id, 1);
+    assert_eq!(orders[0].total, Some(2.5));
+    assert_eq!(orders[1].total, None);
+    assert_eq!((&*orders[0].note, &*orders[0].first), ("", "2.5"));
+    #[cfg(feature = "chrono")]
+    assert_eq!(orders[0].date, ::chrono::NaiveDate::from_ymd_opt(2024, 12, 31));
+
+    #[derive(CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Missing {
+        #[allow(dead_code)]
+        nope: u8,
+    }
+    let mut records = Csv::from_string("a\n1").has_header(true).records::<Missing>();
+    match records.next() {
+        Some(Err(Error::UnknownColumn(ref c))) if c == "nope" => (),
+        _ => panic!("expected an unknown column"),
+    }
+    assert!(records.next().is_none());
+    assert!(Order::columns(&::record::Headers::new(&[], false)).unwrap().iter().all(Option::is_some));
+}
+
+#[test]
+fn case_insensitive_headers() {
+    use decode::Column;
+    use headers;
+
+    let mut csv = Csv::from_string("Email,NAME\na@b.c,ann")
+        .has_header(true)
+        .case_insensitive_headers(true)
+        .replace_value("name", "ann", "Ann");
+    assert_eq!(csv.headers(), vec!["Email".to_string(), "NAME".to_string()]);
+    assert_eq!((csv.column_index("EMAIL"), csv.column_index("Name")), (Some(0), Some(1)));
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "a@b.c,Ann");
+
+    let csv = Csv::from_string("email,Email\n1,2").has_header(true);
+    assert_eq!(csv.column_index("EMAIL"), None);
+    let mut csv = csv.case_insensitive_headers(true).duplicate_headers(Duplicates::Error);
+    match csv.next() {
+        Some(Err(Error::DuplicateHeader(ref h))) if h == "Email" => (),
+        _ => panic!("expected a duplicate header"),
+    }
+
+    let names = ["ID".to_string()];
+    let ids = ::record::Headers::new(&names, true);
+    assert_eq!((ids.find("id"), ::record::Headers::new(&names, false).find("id")), (Some(0), None));
+
+    let names = ["Stra\u{df}e".to_string(), "\u{c9}T\u{c9}".to_string(), "\u{e9}t\u{e9}".to_string()];
+    let column = Column::from("\u{e9}T\u{e9}");
+    assert_eq!((column.index(&names, true), column.index(&names, false)), (Some(1), None));
+    assert_eq!(column.indices(&names, Duplicates::All, true), vec![1, 2]);
+    assert_eq!(Duplicates::KeepLast.find(&names, "\u{c9}t\u{c9}", true), vec![2]);
+    assert_eq!((headers::find_duplicate(&names, true), headers::find_duplicate(&names, false)), (Some("\u{e9}t\u{e9}"), None));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_serde_rename() {
+    use CsvRecord;
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Customer {
+        #[serde(rename = "Cust. ID ", default)]
+        id: u64,
+        #[serde(alias = "mail", alias = "E-mail")]
+        email: String,
+        #[serde(rename(serialize = "n", deserialize = "Full Name"))]
+        #[csv(name = "name")]
+        name: String,
+    }
+
+    let csv = Csv::from_string("E-mail,name,Cust. ID 
+a@b.c,Ann,7").has_header(true);
+    let customers = csv.records::<Customer>().collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(customers, vec![Customer { id: 7, email: "a@b.c".to_string(), name: "Ann".to_string() }]);
+}
+
+#[test]
+fn line_terminators() {
+    let rows = |csv: Csv<&[u8]>| csv.map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+    let csv = Csv::from_string("a,\"b\nc\"\rd,e\r").detect_terminator().has_header(true);
+    assert_eq!(csv.line_endings(), Some(LineEndings { lf: 0, crlf: 0, cr: 1 }));
+    assert_eq!(csv.headers, Some(vec!["a".to_string(), "b\nc".to_string()]));
+    assert_eq!(rows(csv), vec!["d,e"]);
+
+    let csv = Csv::from_string("a\r\nb\rc\nd").terminator(Terminator::Any);
+    assert_eq!(rows(csv), vec!["a", "b", "c", "d"]);
+    assert_eq!(rows(Csv::from_string("a\r\nb\rc\nd")), vec!["a", "b\rc", "d"]);
+
+    // `\r\n` split across reads
+    let reader = BufReader::with_capacity(2, io::Cursor::new("a\r\nb\r\n"));
+    let csv = Csv::from_reader(reader).terminator(Terminator::Any);
+    assert_eq!(csv.map(|r| r.unwrap().as_str().unwrap().to_string()).collect::<Vec<_>>(), vec!["a", "b"]);
+
+    let endings = LineEndings::sniff(b"a\nb\r\nc\rd");
+    assert!(endings.is_mixed());
+    assert_eq!(endings.terminator(), Terminator::Any);
+    assert_eq!(LineEndings::sniff(b"a\r\nb\n").terminator(), Terminator::Lf);
+}
+
+#[test]
+fn bind_fields() {
+    struct Row3 {
+        name: String,
+        a: u8,
+        extra: Option<u8>,
+    }
+    impl Decodable for Row3 {
+        fn decode<D: Decoder>(d: &mut D) -> Result<Row3, D::Error> {
+            d.read_struct("Row3", 3, |d| Ok(Row3 {
+                name: d.read_struct_field("name", 0, Decodable::decode)?,
+                a: d.read_struct_field("a", 1, Decodable::decode)?,
+                extra: d.read_struct_field("extra", 2, Decodable::decode)?,
+            }))
+        }
+    }
+
+    let mut csv = Csv::from_string("a,Name,\n1,x,3").has_header(true)
+        .case_insensitive_headers(true)
+        .bind_field("name", "name")
+        .bind_field("extra", 2);
+    let row = csv.next().unwrap().unwrap().decode::<Row3>().unwrap();
+    assert_eq!((&*row.name, row.a), ("x", 1));
+    assert_eq!(row.extra, Some(3));
+
+    let mut csv = Csv::from_string("a,b\n1,2").has_header(true).bind_field("name", "nope");
+    match csv.next().unwrap().unwrap().decode::<Row3>() {
+        Err(Error::UnknownColumn(ref c)) if c == "nope" => (),
+        _ => panic!("expected an unknown column"),
+    }
+}
+
+#[test]
+fn decode_arrays() {
+    let mut csv = Csv::from_string("1,2,3\n4,5\n1,x,3").flexible(true).field_hook(0, |s| format!("{}0", s));
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<[f64; 3]>().unwrap(), [10., 2., 3.]);
+    assert_eq!(row.decode_array::<u8, 3>().unwrap(), [10, 2, 3]);
+    let row = csv.next().unwrap().unwrap();
+    assert!(row.decode::<[f64; 3]>().is_err());
+    match row.decode_array::<f64, 3>() {
+        Err(Error::Decode(ref e)) if e == "Expected 3 columns, found 2" => (),
+        _ => panic!("expected a length error"),
+    }
+    assert!(csv.next().unwrap().unwrap().decode_array::<u8, 3>().is_err());
+
+    let line = (0..40).map(|i| i.to_string()).collect::<Vec<_>>().join(",");
+    let row = Csv::from_string(&line).next().unwrap().unwrap();
+    assert_eq!(row.decode_array::<usize, 40>().unwrap()[39], 39);
+}
+
+#[test]
+fn tail_columns() {
+    let row = Csv::from_string("a,1,2,3").next().unwrap().unwrap();
+    assert_eq!(row.decode::<(String, Vec<u8>)>().unwrap(), ("a".to_string(), vec![1, 2, 3]));
+
+    #[cfg(feature = "derive")]
+    {
+        use CsvRecord;
+
+        #[derive(CsvRecord)]
+        #[csv(crate = "crate")]
+        struct Sample {
+            station: String,
+            #[csv(name = "at")]
+            time: u32,
+            #[csv(rest)]
+            values: Vec<f64>,
+        }
+        let csv = Csv::from_string("at,station,m1,m2\n10,x,1.5,2\n11,y,,3").has_header(true);
+        let mut samples = csv.records::<Sample>();
+        let sample = samples.next().unwrap().unwrap();
+        assert_eq!((&*sample.station, sample.time, sample.values), ("x", 10, vec![1.5, 2.]));
+        assert!(samples.next().unwrap().is_err());
+    }
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn record_unknown_columns() {
+    use CsvRecord;
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let csv = Csv::from_string("id,y,color,x,z\n1,2,red,3,4").has_header(true);
+    let points = csv.records::<Point>().collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(points, vec![Point { x: 3, y: 2 }]);
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn record_strict() {
+    use CsvRecord;
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Point {
+        x: i32,
+        y: Option<i32>,
+    }
+
+    let strict = |data| Csv::from_string(data).has_header(true).records::<Point>().strict(true).next().unwrap();
+    assert_eq!(strict("y,x\n1,2").unwrap(), Point { x: 2, y: Some(1) });
+    match strict("x,y,z\n1,2,3") {
+        Err(Error::UnexpectedColumn(ref c)) if c == "z" => (),
+        _ => panic!("expected an unexpected column"),
+    }
+    match strict("x\n1") {
+        Err(Error::UnknownColumn(ref c)) if c == "y" => (),
+        _ => panic!("expected an unknown column"),
+    }
+    let point = Csv::from_string("x\n1").has_header(true).records::<Point>().next().unwrap();
+    assert_eq!(point.unwrap(), Point { x: 1, y: None });
+
+    #[derive(CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Series {
+        #[allow(dead_code)]
+        id: u8,
+        #[csv(rest)]
+        values: Vec<u8>,
+    }
+    let mut series = Csv::from_string("id,a,b\n1,2,3").has_header(true).records::<Series>().strict(true);
+    assert_eq!(series.next().unwrap().unwrap().values, vec![2, 3]);
+    let mut series = Csv::from_string("a,id,b\n1,2,3").has_header(true).records::<Series>().strict(true);
+    assert!(series.next().unwrap().is_err());
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn record_serde_defaults() {
+    use CsvRecord;
+
+    fn unit() -> String {
+        "kg".to_string()
+    }
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    struct Weight {
+        value: f64,
+        #[serde(default = "unit")]
+        unit: String,
+        #[serde(default)]
+        note: String,
+    }
+
+    let csv = Csv::from_string("value,unit,note\n1.5,g,ok\n2\n3,,").has_header(true).flexible(true);
+    let weights = csv.records::<Weight>().collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(weights[0], Weight { value: 1.5, unit: "g".to_string(), note: "ok".to_string() });
+    assert_eq!(weights[1], Weight { value: 2., unit: "kg".to_string(), note: String::new() });
+    assert_eq!((weights[2].value, &*weights[2].unit), (3., "kg"));
+
+    #[derive(Debug, PartialEq, CsvRecord)]
+    #[csv(crate = "crate")]
+    #[serde(default)]
+    struct Config {
+        name: String,
+        retries: u8,
+    }
+    impl Default for Config {
+        fn default() -> Config {
+            Config { name: "none".to_string(), retries: 3 }
+        }
+    }
+    let csv = Csv::from_string("name,retries\nx").has_header(true).flexible(true);
+    let config = csv.records::<Config>().next().unwrap().unwrap();
+    assert_eq!(config, Config { name: "x".to_string(), retries: 3 });
+}
+
+#[test]
+fn row_get_as() {
+    let row = Csv::from_string("7,x,\"2.5\"").next().unwrap().unwrap();
+    assert_eq!(row.get_as::<u64>(0).unwrap(), 7);
+    assert_eq!(row.get_as::<f32>(2).unwrap(), 2.5);
+    match row.get_as::<u8>(1) {
+        Err(Error::Decode(ref e)) if e == "Cannot parse column 1 ('x'): invalid digit found in string" => (),
+        r => panic!("unexpected {:?}", r),
+    }
+    match row.get_as::<u8>(3) {
+        Err(Error::Decode(ref e)) if e == "No column 3, the row has 3 columns" => (),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+
+#[test]
+fn row_fields() {
+    let row = Csv::from_string("a,\"b,c\",d").next().unwrap().unwrap();
+    let fields = row.fields().unwrap().filter(|&(i, _)| i != 1).collect::<Vec<_>>();
+    assert_eq!(fields, vec![(0, "a"), (2, "d")]);
+    assert_eq!(row.bytes_fields().nth(1), Some((1, &b"b,c"[..])));
+    assert_eq!(row.bytes_fields().next_back(), Some((2, &b"d"[..])));
+}
+
+#[test]
+fn row_named_columns() {
+    let mut csv = Csv::from_string("a,b,c\n1,2\n").has_header(true).flexible(true);
+    let headers = csv.headers();
+    let row = csv.next().unwrap().unwrap();
+    let named = row.named_columns(&headers).unwrap();
+    assert_eq!(named.size_hint(), (2, Some(2)));
+    let map = named.collect::<::std::collections::HashMap<_, _>>();
+    assert_eq!((map["a"], map["b"], map.get("c")), ("1", "2", None));
+}
+
+#[test]
+fn row_write_csv() {
+    let data = "a,\"b\nc\",,\"\"\"\"\n\n";
+    let mut csv = Csv::from_string(data).flexible(true);
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.to_string(), "a,\"b\nc\",,\"\"\"\"");
+    assert_eq!(format!("{}", csv.next().unwrap().unwrap()), "\"\"");
+
+    let mut out = Vec::new();
+    let row = Csv::from_string("a,\"b;c\"").raw(true).next().unwrap().unwrap();
+    row.write_csv(&mut out, b';').unwrap();
+    assert_eq!(out, b"a;\"b;c\"");
+    let reparsed = Csv::from_string(&row.to_string()).next().unwrap().unwrap();
+    assert_eq!(reparsed.as_str().unwrap(), "a,\"b;c\"");
+}
+
+#[test]
+fn row_from_fields() {
+    let row = Row::from_fields(vec!["a".to_string(), "\"q\"".to_string(), String::new()]);
+    assert_eq!(row.len(), 3);
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["a", "\"q\"", ""]);
+    assert_eq!(row, Csv::from_string("a,\"\"\"q\"\"\",").next().unwrap().unwrap());
+    assert_eq!(row.to_string(), "a,\"\"\"q\"\"\",");
+    assert_eq!(format!("{:?}", row), "Row([\"a\", \"\\\"q\\\"\", \"\"])");
+    assert!(Row::from_fields(Vec::<&str>::new()).is_empty());
+    assert_eq!(Row::from_fields([""]).to_string(), "\"\"");
+
+    let mut writer = Writer::from_writer(Vec::new());
+    writer.write_row(&Row::from_fields([&b"x"[..], b"1\n2"])).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), b"x,\"1\n2\"\n");
+}
+
+#[test]
+fn row_edit() {
+    let mut row = Csv::from_string("a,\"b,c\",d").next().unwrap().unwrap();
+    row.set(0, "long value");
+    row.set(1, "");
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["long value", "", "d"]);
+    row.remove(1);
+    row.push("\"e\"");
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["long value", "d", "\"e\""]);
+    row.remove(2);
+    row.remove(0);
+    assert_eq!((row.len(), row.as_str().unwrap()), (1, "d"));
+    row.remove(0);
+    assert!(row.is_empty());
+    row.push("x");
+    assert_eq!(row, Row::from_fields(["x"]));
+
+    let mut row = Csv::from_string("a,\"b\"\"c\"").raw(true).next().unwrap().unwrap();
+    row.set(0, "q\"");
+    assert_eq!(row.as_str().unwrap(), "\"q\"\"\",\"b\"\"c\"");
+    row.set(1, vec![0xff]);
+    assert!(row.as_str().is_err());
+    row.set(1, "ok");
+    assert_eq!(row.as_str().unwrap(), "\"q\"\"\",ok");
+}
+
+#[test]
+fn record_builder() {
+    use writer::RecordBuilder;
+
+    let row = RecordBuilder::new().field("a\"b").option(Some(1.5)).field(true).bytes(b"x").build();
+    assert_eq!(row, Row::from_fields(["a\"b", "1.5", "true", "x"]));
+    let mut writer = Writer::from_writer(Vec::new()).delimiter(b';');
+    RecordBuilder::new().field("1;2").float(1., 0).write_to(&mut writer).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), b"\"1;2\";1\n");
+}
+
+#[test]
+fn string_record() {
+    use string_record::StringRecord;
+
+    let row = Csv::from_string("a,\"b,c\"").next().unwrap().unwrap();
+    let record = StringRecord::from_row(&row).unwrap();
+    let copy = record.clone();
+    let handle = ::std::thread::spawn(move || copy.iter().map(|f| f.len()).sum::<usize>());
+    assert_eq!(handle.join().unwrap(), 4);
+    assert_eq!((&record[1], record.get(2), record.len()), ("b,c", None, 2));
+    assert_eq!(Row::from(record.clone()), row);
+
+    let mut other = StringRecord::new();
+    other.push("a");
+    other.push(String::from("b,c"));
+    assert_eq!(other, record);
+    assert_eq!(other.into_vec(), vec!["a", "b,c"]);
+
+    let row = Csv::from_reader(&b"\xff"[..]).next().unwrap().unwrap();
+    assert!(StringRecord::from_row(&row).is_err());
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv_compat() {
+    use std::convert::TryFrom;
+
+    let row = Csv::from_string("a,\"b\"\"c\",").next().unwrap().unwrap();
+    let bytes = ::csv::ByteRecord::from(&row);
+    assert_eq!(bytes, vec![&b"a"[..], b"b\"c", b""]);
+    assert_eq!(Row::from(&bytes), row);
+    let record = ::csv::StringRecord::try_from(&row).unwrap();
+    let owned = ::string_record::StringRecord::from(record.clone());
+    assert_eq!(owned.as_slice(), ["a", "b\"c", ""]);
+    assert_eq!(::csv::StringRecord::from(owned), record);
+
+    let invalid = Csv::from_reader(&b"\xff"[..]).next().unwrap().unwrap();
+    assert!(::csv::StringRecord::try_from(&invalid).is_err());
+}
+
+#[test]
+fn excel_dialect() {
+    use dialect::Dialect;
+
+    let rows = |csv: Csv<&[u8]>| csv.map(|r| r.unwrap().columns().unwrap().map(String::from).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let csv = Csv::from_string("\u{feff}a;\"b,c\";d,e\r\n1;2,5;3\r\n").dialect(Dialect::Excel);
+    assert_eq!(rows(csv), vec![vec!["a", "b,c", "d,e"], vec!["1", "2,5", "3"]]);
+    let csv = Csv::from_string("sep=\t\na\tb,c").dialect(Dialect::Excel);
+    assert_eq!(rows(csv), vec![vec!["a", "b,c"]]);
+    let csv = Csv::from_string("a,b;c").dialect(Dialect::Excel);
+    assert_eq!(rows(csv), vec![vec!["a", "b;c"]]);
+
+    let mut writer = Writer::from_writer(Vec::new()).dialect(Dialect::Excel).delimiter(b';');
+    writer.write(["a", "1,5"]).unwrap();
+    writer.write(["b", "2"]).unwrap();
+    let written = writer.into_inner().unwrap();
+    assert_eq!(written, "\u{feff}sep=;\r\na;1,5\r\nb;2\r\n".as_bytes());
+    let mut csv = Csv::from_reader(&written[..]).dialect(Dialect::Excel);
+    assert_eq!(csv.next().unwrap().unwrap().as_str().unwrap(), "a;1,5");
+}
+
+#[test]
+fn sep_line() {
+    let rows = |csv: Csv<&[u8]>| csv.map(|r| r.unwrap().columns().unwrap().map(String::from).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let csv = Csv::from_string("\u{feff}sep=|\r\na|b,c\r\n").detect_sep_line();
+    assert_eq!(csv.checkpoint().offset(), 10);
+    assert_eq!(rows(csv), vec![vec!["a", "b,c"]]);
+    let mut csv = Csv::from_string("sep=;\na;b").detect_sep_line().has_header(true);
+    assert_eq!(csv.headers(), vec!["a".to_string(), "b".to_string()]);
+    let csv = Csv::from_string("sep=;").detect_sep_line();
+    assert_eq!(csv.count(), 0);
+    let csv = Csv::from_string("sep=;x\na;b").detect_sep_line().flexible(true);
+    assert_eq!(rows(csv), vec![vec!["sep=;x"], vec!["a;b"]]);
+}
+
+#[test]
+fn dialects() {
+    use dialect::Dialect;
+
+    let write = |dialect| {
+        let mut writer = Writer::from_writer(Vec::new()).dialect(dialect);
+        writer.write(["a", "b\"c"]).unwrap();
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    };
+    assert_eq!(write(Dialect::Rfc4180), "a,\"b\"\"c\"\r\n");
+    assert_eq!(write(Dialect::Excel), "\u{feff}sep=,\r\na,\"b\"\"c\"\r\n");
+    assert_eq!(write(Dialect::Unix), "\"a\",\"b\"\"c\"\n");
+    assert_eq!(write(Dialect::MySql), "a\tb\"c\n");
+    assert_eq!(write(Dialect::Postgres), "a,\"b\"\"c\"\n");
+
+    let mut writer = Writer::from_writer(Vec::new()).dialect(Dialect::Excel).delimiter(b';').crlf(false);
+    writer.write(["a", "b"]).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), "\u{feff}sep=;\na;b\n".as_bytes());
+
+    for dialect in [Dialect::Rfc4180, Dialect::Excel, Dialect::Unix, Dialect::Postgres] {
+        let mut csv = Csv::from_string("\"a\",b\r\nc,d\n").dialect(dialect);
+        assert_eq!(csv.next().unwrap().unwrap().columns().unwrap().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(csv.next().unwrap().unwrap().columns().unwrap().collect::<Vec<_>>(), vec!["c", "d"]);
+    }
+    let mut csv = Csv::from_string("a,b\tc\n").dialect(Dialect::MySql);
+    assert_eq!(csv.next().unwrap().unwrap().columns().unwrap().collect::<Vec<_>>(), vec!["a,b", "c"]);
+}
+
+#[test]
+fn mysql_escapes() {
+    use dialect::Dialect;
+
+    let dump = "1\ta\\tb\\\nc\t\\N\t\"q\"\n2\t\\\\N\t\t\\0\\Z,\\\\\n";
+    let rows = Csv::from_string(dump).dialect(Dialect::MySql).collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].columns().unwrap().collect::<Vec<_>>(), vec!["1", "a\tb\nc", "", "\"q\""]);
+    assert!(rows[0].is_null(2) && !rows[0].is_null(1));
+    assert_eq!(rows[1].columns().unwrap().collect::<Vec<_>>(), vec!["2", "\\N", "", "\0\u{1a},\\"]);
+    assert!(!rows[1].is_null(1) && !rows[1].is_null(2));
+    let (_, _, null, quoted): (u8, String, Option<String>, String) = rows[0].decode().unwrap();
+    assert_eq!((null, quoted), (None, "\"q\"".to_string()));
+
+    let mut writer = Writer::from_writer(Vec::new()).dialect(Dialect::MySql);
+    for row in &rows {
+        writer.write_row(row).unwrap();
+    }
+    let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+    assert_eq!(written, "1\ta\\tb\\nc\t\\N\t\"q\"\n2\t\\\\N\t\t\\0\u{1a},\\\\\n");
+    let again = Csv::from_string(&written).dialect(Dialect::MySql).collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(again, rows);
+    assert!(again[0].is_null(2));
+
+    let mut row = rows[0].clone();
+    row.remove(0);
+    assert!(row.is_null(1));
+    row.set(1, "x");
+    assert!(!row.is_null(1));
+}
+
+#[test]
+fn postgres_text() {
+    use dialect::Dialect;
+
+    let copy = "1\ta\\tb\\nc\t\\N\n2\t\\101\\x42\\7\\f\\v\\Z\\0\t\"q\"\r\n";
+    let rows = Csv::from_string(copy).dialect(Dialect::PostgresText).collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows[0].columns().unwrap().collect::<Vec<_>>(), vec!["1", "a\tb\nc", ""]);
+    assert!(rows[0].is_null(2));
+    assert_eq!(rows[1].columns().unwrap().collect::<Vec<_>>(), vec!["2", "AB\u{7}\u{c}\u{b}Z\0", "\"q\""]);
+    let (id, text, null): (u32, String, Option<String>) = rows[0].decode().unwrap();
+    assert_eq!((id, text, null), (1, "a\tb\nc".to_string(), None));
+
+    let mut writer = Writer::from_writer(Vec::new()).dialect(Dialect::PostgresText);
+    for row in &rows {
+        writer.write_row(row).unwrap();
+    }
+    let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+    assert_eq!(written, "1\ta\\tb\\nc\t\\N\n2\tAB\u{7}\\f\\vZ\0\t\"q\"\n");
+    let again = Csv::from_string(&written).dialect(Dialect::PostgresText).collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(again, rows);
+}
+
+#[test]
+fn dialect_round_trip() {
+    use dialect::{Backslash, Dialect};
+    use writer::Escape;
+
+    let custom = Dialect {
+        delimiter: b';',
+        quote: b'\'',
+        escape: Escape::Backslash,
+        terminator: Terminator::Cr,
+        null: Some("NULL"),
+        ..Dialect::Rfc4180
+    };
+    let rows: &[&[Option<&str>]] = &[
+        &[Some("a"), Some("b,c;d"), Some("e\"f'g\\h")],
+        &[Some("line\nbreak\r\n"), None, Some("")],
+        &[Some("NULL"), Some("\\N"), Some("\tx\u{8}")],
+    ];
+    let dialects = [Dialect::Rfc4180, Dialect::Excel, Dialect::Unix, Dialect::MySql, Dialect::Postgres,
+                    Dialect::PostgresText, custom,
+                    Dialect { backslash: Backslash::MySql, null: Some("\\NULL"), ..custom }];
+    for &dialect in &dialects {
+        let mut writer = Writer::from_writer(Vec::new()).dialect(dialect);
+        for row in rows {
+            writer.write_nullable(row.iter().cloned()).unwrap();
+        }
+        let written = writer.into_inner().unwrap();
+        let read = Csv::from_reader(&written[..]).dialect(dialect).collect::<::error::Result<Vec<_>>>().unwrap();
+        assert_eq!(read.len(), rows.len(), "{:?}", dialect);
+        for (row, expected) in read.iter().zip(rows) {
+            let nullable = row.fields().unwrap().map(|(i, c)| if row.is_null(i) { None } else { Some(c) });
+            let nullable = nullable.collect::<Vec<_>>();
+            if dialect.null.is_some() || dialect.backslash != Backslash::Plain {
+                assert_eq!(&nullable[..], *expected, "{:?}", dialect);
+            } else {
+                let expected = expected.iter().map(|c| Some(c.unwrap_or(""))).collect::<Vec<_>>();
+                assert_eq!(nullable, expected, "{:?}", dialect);
+            }
+        }
+    }
+}
+
+#[test]
+fn quoted_empty_columns() {
+    let rows = Csv::from_string("\"\",,\"a\"\n,\"\",b").collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!((rows[0].is_quoted(0), rows[0].is_quoted(1), rows[0].is_quoted(2)), (true, false, true));
+    assert_eq!((rows[1].is_quoted(0), rows[1].is_quoted(1), rows[1].is_quoted(3)), (false, true, false));
+
+    let mut writer = Writer::from_writer(Vec::new());
+    for row in &rows {
+        writer.write_row(row).unwrap();
+    }
+    assert_eq!(writer.into_inner().unwrap(), b"\"\",,a\n,\"\",b\n");
+    assert_eq!(rows[1].to_string(), ",\"\",b");
+
+    let row = Csv::from_string("'',x,''").quote(b'\'').next().unwrap().unwrap();
+    assert_eq!(row.columns().unwrap().collect::<Vec<_>>(), vec!["", "x", ""]);
+    assert!(row.is_quoted(0) && !row.is_quoted(1) && row.is_quoted(2));
+}
+
+#[test]
+fn field_spans() {
+    let data = "\u{feff}a,\"b \"\"c\"\"\",\r\n\"x,y\",,z\n";
+    let rows = Csv::from_string(data).track_spans(true).collect::<::error::Result<Vec<_>>>().unwrap();
+    let sources = |row: &::Row| (0..row.len()).map(|i| {
+        let (start, end) = row.span(i).unwrap();
+        &data.as_bytes()[start as usize..end as usize]
+    }).collect::<Vec<_>>();
+    assert_eq!(sources(&rows[0]), vec![&b"a"[..], b"\"b \"\"c\"\"\"", b""]);
+    assert_eq!(sources(&rows[1]), vec![&b"\"x,y\""[..], b"", b"z"]);
+    assert_eq!((rows[0].span(0), rows[0].span(2)), (Some((3, 4)), Some((15, 15))));
+    assert_eq!(rows[1].span(3), None);
+
+    let row = Csv::from_string("a,b").next().unwrap().unwrap();
+    assert_eq!(row.span(0), None);
+}
+
+#[test]
+fn diagnostics() {
+    use diagnostic::{Diagnostic, Severity};
+
+    let report = Csv::from_string("a,b\nc,d\"\ne\n").validate().unwrap();
+    let diagnostics = report.diagnostics();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!((diagnostics[0].record, diagnostics[0].span), (Some(2), Some((4, 4))));
+    assert_eq!(diagnostics[1].message, "Expected 2 columns, found 1");
+    assert_eq!(diagnostics[1].suggestion.as_deref(),
+               Some("add the missing columns or read with `ColumnPolicy::Pad`"));
+
+    let mut csv = Csv::from_string("a,b\nc,\"d\"e\n");
+    let error = csv.by_ref().filter_map(|r| r.err()).next().unwrap();
+    let diagnostic = csv.diagnose(&error);
+    assert_eq!((diagnostic.severity, diagnostic.record, diagnostic.span), (Severity::Error, Some(2), Some((4, 4))));
+    assert_eq!(diagnostic.to_string(), "error: A CSV column has an unescaped quote (record 2)\n  \
+                                        help: double quotes within quoted columns");
+
+    let diagnostic = Diagnostic::warning("Trailing spaces").record(3).field(1);
+    assert_eq!(diagnostic.to_string(), "warning: Trailing spaces (record 3, field 1)");
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn diagnostics_json() {
+    use diagnostic::Diagnostic;
+    use serde_json::json;
+
+    let diagnostic = Diagnostic::error("Bad value").record(2).span(4, 9).suggestion("fix it");
+    assert_eq!(diagnostic.to_json(), json!({"severity": "error", "message": "Bad value", "record": 2,
+                                            "field": null, "span": {"start": 4, "end": 9},
+                                            "suggestion": "fix it"}));
+}
+
+#[test]
+fn column_widths() {
+    use width::display_width;
+
+    assert_eq!(display_width("e\u{301}t\u{e9}"), 3);
+    assert_eq!(display_width("ｆｕｌｌ"), 8);
+    assert_eq!(display_width("a\tb"), 2);
+
+    let mut csv = Csv::from_string("id,label\n1,caf\u{e9}\n22,日本語,x\n333").flexible(true).has_header(true);
+    let widths = csv.widths().unwrap();
+    assert_eq!(widths.rows, 3);
+    assert_eq!(widths.columns.len(), 3);
+    assert_eq!((widths.columns[0].max, widths.columns[0].avg()), (3, 2.));
+    assert_eq!((widths.columns[1].max, widths.columns[1].count), (6, 2));
+    assert_eq!((widths.columns[2].name.as_deref(), widths.columns[2].max), (None, 1));
+    assert_eq!(widths.pad(0, "1"), "1  ");
+    assert_eq!(widths.pad(1, "日本語"), "日本語");
+    assert_eq!(widths.pad(5, "x"), "x");
+}
+
+#[test]
+fn content_checksum() {
+    use std::hash::Hasher;
+    use hash::Fnv64;
+
+    let mut hasher = Fnv64::new();
+    Hasher::write(&mut hasher, b"a");
+    assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+    let checksum = |csv: Csv<&[u8]>| csv.has_header(true).checksum().unwrap();
+    let a = checksum(Csv::from_reader(&b"\xef\xbb\xbfid,name\r\n1,\"bob\"\r\n2,\"a,b\"\r\n"[..]));
+    let b = checksum(Csv::from_reader(&b"id\tname\n1\tbob\n2\ta,b"[..]).delimiter(b'\t'));
+    assert_eq!(a, b);
+    assert_ne!(a, checksum(Csv::from_reader(&b"id,name\n2,\"a,b\"\n1,bob\n"[..])));
+    assert_eq!(a, 0x3bd0_ace5_93de_88af);
+}
+
+#[test]
+fn row_hashing() {
+    use hash::Fnv64;
+
+    let hash = |row: &Row| {
+        let mut hasher = Fnv64::new();
+        row.hash_fields(&mut hasher);
+        ::std::hash::Hasher::finish(&hasher)
+    };
+    let rows = Csv::from_string("a,bc\nab,c\n\"a\",\"bc\"").collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_ne!(hash(&rows[0]), hash(&rows[1]));
+    assert_eq!(hash(&rows[0]), hash(&rows[2]));
+    assert_eq!(hash(&rows[0]), hash(&Row::from_fields(["a", "bc"])));
+    assert_eq!(hash(&rows[0]), 0xba1e_1f0e_0704_d8ea);
+
+    let row = Csv::from_string("1,,\\N").backslash_escapes(::dialect::Backslash::MySql).next().unwrap().unwrap();
+    assert_ne!(row.hash_key(&[1], Fnv64::new()), row.hash_key(&[2], Fnv64::new()));
+    assert_eq!(row.hash_key(&[2], Fnv64::new()), row.hash_key(&[7], Fnv64::new()));
+    assert_eq!(row.hash_key(&[0, 1], Fnv64::new()), Row::from_fields(["1", ""]).hash_key(&[0, 1], Fnv64::new()));
+}
+
+#[test]
+fn bloom_dedup() {
+    use dedup::BloomFilter;
+
+    let mut filter = BloomFilter::new(1000, 0.01);
+    assert_eq!(filter.size(), 1200);
+    assert!(filter.insert(42) && !filter.insert(42) && filter.contains(42));
+    let false_positives = (0..10_000u64).filter(|i| filter.contains(i.wrapping_mul(0x9e37_79b9_7f4a_7c15))).count();
+    assert!(false_positives < 10);
+
+    let data = (0..3000).map(|i| format!("{},x\n", i % 1000)).collect::<String>();
+    let mut rows = Csv::from_string(&data).dedup(1000, 0.01);
+    assert!(rows.by_ref().all(|r| r.is_ok()));
+    // all duplicates are skipped, along with about 1% of unique rows
+    assert!((2000..2030).contains(&rows.duplicates()));
+
+    let mut rows = Csv::from_string(&data).dedup(1000, 0.01).key(&["id"]);
+    assert!(matches!(rows.next(), Some(Err(Error::UnknownColumn(_)))));
+    assert!(rows.next().is_none());
+
+    let mut rows = Csv::from_string(&data).memory_budget(100).dedup(1000, 0.01);
+    assert!(matches!(rows.next(), Some(Err(Error::MemoryBudget(100)))));
+
+    // the budget is charged before the filter is allocated
+    assert_eq!(BloomFilter::size_for(1000, 0.01), 1200);
+    let mut rows = Csv::from_string(&data).memory_budget(1 << 20).dedup(usize::MAX, 1e-12);
+    assert!(matches!(rows.next(), Some(Err(Error::MemoryBudget(_)))));
+}
+
+#[test]
+fn top_k_rows() {
+    use top::{Key, Order};
+
+    let data = "name,score\nd,7\na,10\nc,\nb,7\ne,2.5\nf,10";
+    let top = |k, order, key| {
+        let mut csv = Csv::from_string(data).has_header(true);
+        let rows = csv.top_k("score", k, order).key(key).run().unwrap();
+        rows.iter().map(|r| r.columns().unwrap().next().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(top(3, Order::Largest, Key::Numeric), vec!["a", "f", "d"]);
+    assert_eq!(top(2, Order::Smallest, Key::Numeric), vec!["e", "d"]);
+    assert_eq!(top(2, Order::Largest, Key::Lexicographic), vec!["d", "b"]);
+    assert_eq!(top(2, Order::Smallest, Key::Lexicographic), vec!["c", "a"]);
+    assert_eq!(top(10, Order::Largest, Key::Numeric).len(), 5);
+    assert!(top(0, Order::Largest, Key::Numeric).is_empty());
+    assert_eq!(top(usize::MAX, Order::Smallest, Key::Numeric), vec!["e", "d", "b", "a", "f"]);
+
+    let mut csv = Csv::from_string("n\n1\nx").has_header(true);
+    assert!(matches!(csv.top_k("n", 1, Order::Largest).run(), Err(Error::Decode(_))));
+    let mut csv = Csv::from_string("n\n1").has_header(true);
+    assert!(matches!(csv.top_k("m", 1, Order::Largest).run(), Err(Error::UnknownColumn(_))));
+}
+
+#[test]
+fn summary_expressions() {
+    use group::{Aggregate, Group, Summary};
+
+    assert_eq!("SUM( sales )".parse::<Aggregate>().unwrap(), Aggregate::Sum("sales".into()));
+    assert_eq!("avg(\"unit price\")".parse::<Aggregate>().unwrap(), Aggregate::Mean("unit price".into()));
+    assert_eq!("count(*)".parse::<Aggregate>().unwrap(), Aggregate::Count);
+    for invalid in &["sum", "sum()", "median(x)", "count(x)", "sum(a, b)"] {
+        assert!(matches!(invalid.parse::<Aggregate>(), Err(Error::Parse(_))), "{}", invalid);
+    }
+
+    let summary = Summary::parse("count, max(ms) by region, \"host name\"").unwrap();
+    assert_eq!(summary.aggregates, vec![Aggregate::Count, Aggregate::Max("ms".into())]);
+    assert_eq!(summary.keys, vec!["region", "host name"]);
+    let mut csv = Csv::from_string("region,host name,ms\neu,a,3\neu,a,\neu,b,7").has_header(true);
+    let table = summary.run(&mut csv).unwrap();
+    assert_eq!(table.groups, vec![Group { key: vec!["eu".into(), "a".into()], values: vec![Some(2.), Some(3.)] },
+                                  Group { key: vec!["eu".into(), "b".into()], values: vec![Some(1.), Some(7.)] }]);
+
+    let mut csv = Csv::from_string("ms\n").has_header(true);
+    let table = Summary::parse("count, sum(ms)").unwrap().run(&mut csv).unwrap();
+    assert_eq!(table.to_string(), "count  sum(ms)\n0\n");
+    assert!(Summary::parse("count by").is_err());
+    assert!(Summary::parse("by region").is_err());
+}
+
+#[test]
+fn column_date_formats() {
+    use date::{self, DateTime};
+    use validate::IssueKind;
+
+    assert_eq!(date::parse("29/02/2024 23:59", "%d/%m/%Y %H:%M").unwrap(),
+               DateTime { date: Some((2024, 2, 29)), time: Some((23, 59, 0)) });
+    assert_eq!(date::parse("March 5, 99", "%B %e, %y").unwrap().to_string(), "1999-03-05");
+    assert_eq!(date::parse("12:30:01 AM", "%I:%M:%S %p").unwrap().to_string(), "00:30:01");
+    assert_eq!(date::parse("10%", "%d%%").unwrap_err(), "incomplete date, a year, month and day are needed");
+    assert!(date::parse("29/02/2023", "%d/%m/%Y").is_err());
+    assert!(date::parse("2024-01-01x", "%Y-%m-%d").is_err());
+    assert!(date::parse("13 PM", "%I %p").is_err());
+
+    let data = "id,born,seen\n1,05/11/1990,2024.01.02\n2,,2024.13.01\n3,1990-11-05,2024.01.02";
+    let csv = || Csv::from_string(data).has_header(true).date_format("born", "%d/%m/%Y").date_format(2, "%Y.%m.%d");
+    let rows = csv().collect::<::error::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows[0].decode::<(u32, String, String)>().unwrap(),
+               (1, "1990-11-05".to_string(), "2024-01-02".to_string()));
+    assert_eq!(rows[1].decode::<(u32, Option<String>)>().unwrap(), (2, None));
+    assert!(matches!(rows[2].decode::<(u32, String)>(), Err(Error::Decode(ref e)) if e.contains("'%d/%m/%Y'")));
+    assert_eq!(rows[2].columns().unwrap().nth(1), Some("1990-11-05"));
+
+    let report = csv().validate().unwrap();
+    let kinds = report.issues.iter().map(|i| (i.record, i.kind.clone())).collect::<Vec<_>>();
+    assert_eq!(kinds, vec![(3, IssueKind::InvalidDate { column: 2 }), (4, IssueKind::InvalidDate { column: 1 })]);
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_columns() {
+    use uuid::{UuidField, UuidFormat};
+    use writer::RecordBuilder;
+
+    let data = "67e55044-10b1-426f-9247-bb680e5fe0c8,67E5504410B1426F9247BB680E5FE0C8,\
+                {67e55044-10b1-426f-9247-bb680e5fe0c8},urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8,nope";
+    let row = Csv::from_string(data).next().unwrap().unwrap();
+    let id = row.get_uuid(0).unwrap();
+    assert!((1..4).all(|i| row.get_uuid(i).unwrap() == id));
+    assert!(matches!(row.get_uuid(4), Err(Error::Decode(ref e)) if e.starts_with("Column 4: ")));
+    assert_eq!(row.get_as::<::uuid_crate::Uuid>(1).unwrap(), id);
+    let ids = row.decode::<(UuidField, UuidField, Option<UuidField>)>().unwrap();
+    assert_eq!((*ids.0, *ids.1, ids.2.map(|i| i.0)), (id, id, Some(id)));
+    assert!(row.decode::<Vec<UuidField>>().is_err());
+
+    let formats = [UuidFormat::Hyphenated, UuidFormat::Simple, UuidFormat::Braced, UuidFormat::Urn];
+    let row = formats.iter().fold(RecordBuilder::new(), |b, &f| b.uuid(&id, f)).build();
+    assert_eq!(row.to_string(), data.replace("67E5504410B1426F9247BB680E5FE0C8", "67e5504410b1426f9247bb680e5fe0c8")
+               .trim_end_matches(",nope"));
+}
+
+#[test]
+fn network_address_columns() {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use net::{IpField, SocketAddrField};
+
+    struct Connection {
+        source: IpField,
+        peer: SocketAddrField,
+    }
+
+    impl Decodable for Connection {
+        fn decode<D: Decoder>(d: &mut D) -> ::std::result::Result<Connection, D::Error> {
+            d.read_struct("Connection", 2, |d| Ok(Connection {
+                source: d.read_struct_field("source", 0, Decodable::decode)?,
+                peer: d.read_struct_field("peer", 1, Decodable::decode)?,
+            }))
+        }
+    }
+
+    let mut csv = Csv::from_string("source,peer\n 10.0.0.7 ,[2001:db8::1]:8080\nfe80::1,10.0.0.1\n").has_header(true);
+    let row = csv.next().unwrap().unwrap();
+    let connection = row.decode::<Connection>().unwrap();
+    assert_eq!(*connection.source, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7)));
+    assert_eq!(connection.peer.0, "[2001:db8::1]:8080".parse::<SocketAddr>().unwrap());
+    assert_eq!(row.get_socket_addr(1).unwrap().port(), 8080);
+
+    let row = csv.next().unwrap().unwrap();
+    assert!(row.get_ip(0).unwrap().is_ipv6());
+    match row.decode::<Connection>() {
+        Err(Error::Decode(ref e)) if e == "Field 'peer': Cannot parse '10.0.0.1' as a socket address: \
+                                           invalid socket address syntax" => (),
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+    assert_eq!(row.get_as::<IpAddr>(1).unwrap(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+}
+
+#[test]
+fn binary_columns() {
+    use binary::Encoding;
+    use writer::{RecordBuilder, Writer};
+
+    let payloads: Vec<Vec<u8>> = vec![vec![], vec![0], vec![0xff, 0x10], vec![1, 2, 3], b"hello!!".to_vec()];
+    for bytes in &payloads {
+        for &encoding in &[Encoding::Base64, Encoding::Hex] {
+            assert_eq!(&encoding.decode(&encoding.encode(bytes)).unwrap(), bytes);
+        }
+    }
+    assert_eq!(Encoding::Base64.encode(b"hello!!"), "aGVsbG8hIQ==");
+    assert_eq!(Encoding::Base64.decode("aGVsbG8hIQ").unwrap(), b"hello!!");
+    assert_eq!(Encoding::Hex.decode(" FF10 ").unwrap(), vec![0xff, 0x10]);
+    assert_eq!(Encoding::Hex.decode("f").unwrap_err(), "odd hex length 1");
+    assert_eq!(Encoding::Base64.decode("a$==").unwrap_err(), "invalid base64 character '$' at 1");
+
+    let mut csv = Csv::from_string("name,key,hash\na,AAEC,00ff\nb,,\nc,A,00\n").has_header(true)
+        .binary_column("key", Encoding::Base64)
+        .binary_column(2, Encoding::Hex);
+    let row = csv.next().unwrap().unwrap();
+    let (name, key, hash) = row.decode::<(String, Vec<u8>, Option<Vec<u8>>)>().unwrap();
+    assert_eq!((&*name, key, hash), ("a", vec![0, 1, 2], Some(vec![0, 0xff])));
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row.decode::<(String, Vec<u8>, Option<Vec<u8>>)>().unwrap(), ("b".to_string(), vec![], None));
+    match csv.next().unwrap().unwrap().decode::<(String, Vec<u8>, Vec<u8>)>() {
+        Err(Error::Decode(ref e)) if e == "Cannot decode 'A' as base64 in column 1: invalid base64 length 1" => (),
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+    let mut csv = Csv::from_string("zz,a").binary_column(0, Encoding::Hex);
+    match csv.next().unwrap().unwrap().decode::<(Option<Vec<u8>>, String)>() {
+        Err(Error::Decode(ref e)) if e == "Cannot decode 'zz' as hex in column 0: invalid hex digit at 0" => (),
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+
+    let mut writer = Writer::from_writer(Vec::new()).binary_column(1, Encoding::Base64);
+    writer.write([&b"a"[..], &[0, 1, 2]]).unwrap();
+    writer.write_nullable(vec![Some(&b"b"[..]), None]).unwrap();
+    writer.write_row(&RecordBuilder::new().field("c").binary(&[0xff], Encoding::Hex).build()).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), b"a,AAEC\nb,\nc,ZmY=\n");
+}
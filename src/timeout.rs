@@ -0,0 +1,115 @@
+//! Read timeouts for slow streams
+//!
+//! `TimeoutReader` reads its source on a background thread so that a read
+//! waiting longer than the timeout fails with an `io::ErrorKind::TimedOut`
+//! error instead of blocking forever. It suits any `Read`, e.g. pipes or
+//! child process outputs; sockets may rather use their own read timeout.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use quick_csv::Csv;
+//!
+//! let csv = Csv::from_reader_timeout(&b"a,b\nc,d"[..], Duration::from_secs(1));
+//! assert_eq!(csv.count(), 2);
+//! ```
+
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use Csv;
+
+/// Size of the chunks read by the background thread
+const CHUNK: usize = 64 * 1024;
+
+/// Reader failing with a `TimedOut` error when its source stalls
+///
+/// Once a read timed out, later reads wait for the same data again: no data
+/// is lost, the source keeps being read in the background.
+pub struct TimeoutReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    /// chunk being read and position within it
+    chunk: Vec<u8>,
+    pos: usize,
+    timeout: Duration,
+    eof: bool,
+}
+
+impl TimeoutReader {
+
+    /// Starts reading `reader` on a background thread
+    ///
+    /// At most one chunk is read ahead. The thread ends with the source or
+    /// when the `TimeoutReader` is dropped and the pending read completes.
+    pub fn new<R: Read + Send + 'static>(mut reader: R, timeout: Duration) -> TimeoutReader {
+        let (sender, chunks): (SyncSender<io::Result<Vec<u8>>>, _) = mpsc::sync_channel(1);
+        thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK];
+            let result = match reader.read(&mut chunk) {
+                Ok(n) => {
+                    chunk.truncate(n);
+                    Ok(chunk)
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let end = result.as_ref().map_or(true, |c| c.is_empty());
+            if sender.send(result).is_err() || end {
+                break;
+            }
+        });
+        TimeoutReader {
+            chunks,
+            chunk: Vec::new(),
+            pos: 0,
+            timeout,
+            eof: false,
+        }
+    }
+
+    /// Gets the timeout of every read
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+}
+
+impl Read for TimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() && !self.eof {
+            match self.chunks.recv_timeout(self.timeout) {
+                Ok(Ok(chunk)) => {
+                    self.eof = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.pos = 0;
+                },
+                Ok(Err(e)) => return Err(e),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "csv source read timed out"));
+                },
+                Err(RecvTimeoutError::Disconnected) => self.eof = true,
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Csv<BufReader<TimeoutReader>> {
+
+    /// Creates a Csv from a reader whose every read must complete within `timeout`
+    ///
+    /// A stalled source results in an `Error::Io` of kind `TimedOut`, after
+    /// which the Csv stops as with any other error.
+    pub fn from_reader_timeout<R>(reader: R, timeout: Duration) -> Csv<BufReader<TimeoutReader>>
+        where R: Read + Send + 'static
+    {
+        Csv::from_reader(BufReader::new(TimeoutReader::new(reader, timeout)))
+    }
+
+}
@@ -0,0 +1,170 @@
+//! Top rows by a column
+//!
+//! Finds the `k` rows with the largest or smallest values of a column in a
+//! single pass, keeping only `k` rows in a heap instead of sorting them all.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::top::Order;
+//!
+//! let mut csv = Csv::from_string("host,latency\na,12\nb,250\nc,9\nd,80").has_header(true);
+//! let rows = csv.top_k("latency", 2, Order::Largest).run().unwrap();
+//! assert_eq!(rows.iter().map(|r| r.to_string()).collect::<Vec<_>>(), vec!["b,250", "d,80"]);
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+
+use error::{Error, Result};
+use transform::column_index;
+use {Csv, Row};
+
+/// Entries allocated upfront at most, the heap growing past them as needed
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Which rows are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Rows with the largest values
+    Largest,
+    /// Rows with the smallest values
+    Smallest,
+}
+
+/// How values are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Parsed as `f64`, empty values being skipped
+    Numeric,
+    /// Compared as bytes
+    Lexicographic,
+}
+
+/// Value of the key column of a row
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::Number(_), Value::Bytes(_)) => Ordering::Less,
+            (Value::Bytes(_), Value::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+/// A kept row, greater entries being the first to be dropped
+struct Entry {
+    value: Value,
+    /// position of the row, earlier rows winning ties
+    position: usize,
+    order: Order,
+    row: Row,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> Ordering {
+        let value = match self.order {
+            Order::Largest => other.value.cmp(&self.value),
+            Order::Smallest => self.value.cmp(&other.value),
+        };
+        value.then(self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+/// Top rows search over the remaining rows of a csv
+///
+/// Created with `Csv::top_k`.
+pub struct TopK<'a, B: 'a + BufRead> {
+    csv: &'a mut Csv<B>,
+    column: String,
+    k: usize,
+    order: Order,
+    key: Key,
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Finds the `k` remaining rows with the largest or smallest `column`
+    pub fn top_k(&mut self, column: &str, k: usize, order: Order) -> TopK<'_, B> {
+        TopK {
+            csv: self,
+            column: column.to_string(),
+            k,
+            order,
+            key: Key::Numeric,
+        }
+    }
+
+}
+
+impl<'a, B: BufRead> TopK<'a, B> {
+
+    /// Sets how values are compared
+    ///
+    /// Note: default = `Key::Numeric`
+    pub fn key(mut self, key: Key) -> TopK<'a, B> {
+        self.key = key;
+        self
+    }
+
+    /// Reads all remaining rows and returns the top ones, best first
+    ///
+    /// Rows with equal values keep their order. Fails with
+    /// `Error::UnknownColumn` if no header is `column`, and with
+    /// `Error::Decode` if a numeric value does not parse. Rows without the
+    /// column are skipped.
+    pub fn run(self) -> Result<Vec<Row>> {
+        let TopK { csv, column, k, order, key } = self;
+        let i = column_index(csv, &column)?;
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let mut heap = BinaryHeap::with_capacity(k.min(INITIAL_CAPACITY));
+        for (position, row) in csv.enumerate() {
+            let row = row?;
+            let value = match key {
+                Key::Numeric => match row.columns()?.get(i) {
+                    Some("") | None => continue,
+                    Some(v) => Value::Number(v.trim().parse().map_err(|_| Error::Decode(format!(
+                        "Cannot parse '{}' as a number for column '{}'", v, column)))?),
+                },
+                Key::Lexicographic => match row.bytes_columns().get(i) {
+                    Some(v) => Value::Bytes(v.to_vec()),
+                    None => continue,
+                },
+            };
+            let entry = Entry { value, position, order, row };
+            if heap.len() < k {
+                heap.push(entry);
+            } else if heap.peek().is_some_and(|worst| entry < *worst) {
+                heap.pop();
+                heap.push(entry);
+            }
+        }
+        Ok(heap.into_sorted_vec().into_iter().map(|e| e.row).collect())
+    }
+
+}
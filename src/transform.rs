@@ -0,0 +1,202 @@
+//! Reshaping transforms
+//!
+//! Reads a csv with headers and writes the reshaped csv through a `Writer`.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::transform;
+//! use quick_csv::writer::Writer;
+//!
+//! let mut csv = Csv::from_string("city,2023,2024\nParis,1,2\nLyon,3,4").has_header(true);
+//! let mut writer = Writer::from_writer(Vec::new());
+//! transform::unpivot(&mut csv, &mut writer, &["city"], "year", "value").unwrap();
+//! assert_eq!(writer.into_inner().unwrap(),
+//!            &b"city,year,value\nParis,2023,1\nParis,2024,2\nLyon,2023,3\nLyon,2024,4\n"[..]);
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use error::{Error, Result};
+use spill::Record;
+use writer::Writer;
+use Csv;
+
+/// Gets the position of the column named `name`, reading headers if needed
+pub(crate) fn column_index<B: BufRead>(csv: &mut Csv<B>, name: &str) -> Result<usize> {
+    csv.headers();
+    csv.column_index(name).ok_or_else(|| Error::UnknownColumn(name.to_string()))
+}
+
+fn column_indices<B: BufRead>(csv: &mut Csv<B>, names: &[&str]) -> Result<Vec<usize>> {
+    names.iter().map(|n| column_index(csv, n)).collect()
+}
+
+/// Writes the headers, if any, and all remaining rows
+///
+/// This is the plain reader to writer pipeline, rows being written as
+/// transformed by the reader options.
+///
+/// Returns the number of rows written, excluding the header.
+pub fn copy<B, W>(csv: &mut Csv<B>, writer: &mut Writer<W>) -> Result<usize>
+    where B: BufRead, W: Write
+{
+    let headers = csv.headers();
+    if !headers.is_empty() {
+        writer.write(headers)?;
+    }
+    let mut count = 0;
+    for row in csv {
+        writer.write_row(&row?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Rewrites all remaining rows into a normalized RFC 4180 form
+///
+/// Columns are delimited with `,`, quoted only when needed with doubled
+/// quote escapes, empty columns being never quoted, and rows are
+/// terminated with `\r\n`. The UTF-8 BOM is dropped. Equivalent inputs
+/// thus give the same bytes, whatever their delimiter, quoting or line
+/// endings.
+///
+/// Headers are normalized like any other reader option; read without
+/// headers to keep the header row as is.
+///
+/// Returns the underlying writer.
+pub fn canonicalize<B, W>(csv: &mut Csv<B>, writer: W) -> Result<W>
+    where B: BufRead, W: Write
+{
+    let mut writer = Writer::from_writer(writer).crlf(true);
+    let headers = csv.headers();
+    if !headers.is_empty() {
+        writer.write(headers)?;
+    }
+    for row in csv {
+        // unlike `write_row`, quoted empty columns are written empty
+        writer.write(row?.bytes_columns())?;
+    }
+    writer.into_inner()
+}
+
+/// Converts wide rows into long ones (melt)
+///
+/// Every row is written once per column not in `ids`, as the `ids` columns
+/// followed by the column name (in a `variable` column) and its value (in
+/// a `value` column). Rows are transformed as they are read.
+///
+/// Returns the number of rows written, excluding the header.
+pub fn unpivot<B, W>(csv: &mut Csv<B>, writer: &mut Writer<W>,
+                     ids: &[&str], variable: &str, value: &str) -> Result<usize>
+    where B: BufRead, W: Write
+{
+    let id_cols = column_indices(csv, ids)?;
+    let headers = csv.headers();
+    let value_cols = (0..headers.len()).filter(|i| !id_cols.contains(i)).collect::<Vec<_>>();
+    writer.write(ids.iter().cloned().chain(vec![variable, value]))?;
+
+    let mut count = 0;
+    for row in csv {
+        let row = row?;
+        let cols = row.bytes_columns();
+        for &v in &value_cols {
+            let key = id_cols.iter().map(|&i| cols.get(i).unwrap_or(b""));
+            writer.write(key.chain(vec![headers[v].as_bytes(), cols.get(v).unwrap_or(b"")]))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Values of the `ids` columns of a group and its pivoted values
+type Group = (Vec<Vec<u8>>, Vec<Option<Vec<u8>>>);
+
+/// Converts long rows into wide ones
+///
+/// Rows are grouped by their `ids` columns. Each distinct value of the
+/// `columns` column becomes a new column, filled with the `values` column
+/// of the group (last one wins, empty if missing). Groups and new columns
+/// are written in order of first appearance.
+///
+/// As the header depends on all rows, the whole result is kept in memory
+/// before being written.
+///
+/// Returns the number of rows written, excluding the header.
+pub fn pivot<B, W>(csv: &mut Csv<B>, writer: &mut Writer<W>,
+                   ids: &[&str], columns: &str, values: &str) -> Result<usize>
+    where B: BufRead, W: Write
+{
+    let id_cols = column_indices(csv, ids)?;
+    let column = column_index(csv, columns)?;
+    let value = column_index(csv, values)?;
+
+    let mut names: Vec<Vec<u8>> = Vec::new();
+    let mut name_index = HashMap::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut group_index = HashMap::new();
+    let mut budget = csv.budget();
+    for row in csv {
+        let row = row?;
+        let cols = row.bytes_columns();
+        let name = cols.get(column).unwrap_or(b"");
+        if !name_index.contains_key(name) {
+            budget.charge(2 * name.len())?;
+            name_index.insert(name.to_vec(), names.len());
+            names.push(name.to_vec());
+        }
+        let n = name_index[name];
+        let key = id_cols.iter().map(|&i| cols.get(i).unwrap_or(b"").to_vec()).collect::<Vec<_>>();
+        if !group_index.contains_key(&key) {
+            budget.charge(2 * key.iter().map(|k| k.len()).sum::<usize>())?;
+            group_index.insert(key.clone(), groups.len());
+            groups.push((key.clone(), Vec::new()));
+        }
+        let group = &mut groups[group_index[&key]].1;
+        if group.len() <= n {
+            group.resize(n + 1, None);
+        }
+        let value = cols.get(value).unwrap_or(b"");
+        budget.charge(value.len().saturating_sub(group[n].as_ref().map_or(0, |v| v.len())))?;
+        group[n] = Some(value.to_vec());
+    }
+
+    writer.write(ids.iter().map(|id| id.as_bytes()).chain(names.iter().map(|n| &n[..])))?;
+    for (key, group) in &groups {
+        let values = (0..names.len())
+            .map(|n| group.get(n).and_then(|v| v.as_ref()).map_or(&b""[..], |v| &v[..]));
+        writer.write(key.iter().map(|k| &k[..]).chain(values))?;
+    }
+    Ok(groups.len())
+}
+
+/// Sorts rows by the `keys` columns, comparing their bytes
+///
+/// Rows with equal keys keep their order. Rows are kept in memory within
+/// the `memory_budget`, then spilled to disk if `spill_to` is set.
+///
+/// Returns the number of rows written, excluding the header.
+pub fn sort<B, W>(csv: &mut Csv<B>, writer: &mut Writer<W>, keys: &[&str]) -> Result<usize>
+    where B: BufRead, W: Write
+{
+    let key_cols = column_indices(csv, keys)?;
+    writer.write(csv.headers())?;
+
+    let mut sorter = csv.sorter(|a: &Record, b: &Record| {
+        key_cols.iter().map(|&i| a.get(i).cmp(&b.get(i)))
+            .find(|&o| o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    for row in csv {
+        sorter.push(row?.bytes_columns().map(|c| c.to_vec()).collect())?;
+    }
+    let mut count = 0;
+    for record in sorter.finish()? {
+        writer.write(record?)?;
+        count += 1;
+    }
+    Ok(count)
+}
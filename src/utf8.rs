@@ -0,0 +1,103 @@
+//! UTF-8 validation of the source chunk by chunk, for `Utf8Validation::PerChunk`
+//!
+//! Every chunk of the reader buffer is validated once as a whole, invalid
+//! sequences being remembered by offset until the records holding them are
+//! read.
+
+use std::collections::VecDeque;
+use std::str;
+
+/// Validation state of the bytes read so far
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Utf8Chunks {
+    /// offset up to which the source is validated
+    validated: u64,
+    /// start of a character split between chunks, and its offset
+    carry: Vec<u8>,
+    carry_offset: u64,
+    /// offsets of invalid sequences, in order, not yet checked
+    invalid: VecDeque<u64>,
+    /// start of the last range checked
+    checked: u64,
+}
+
+/// Length of the character starting with `b`, a valid leading byte
+fn char_len(b: u8) -> usize {
+    match b {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        _ => 2,
+    }
+}
+
+impl Utf8Chunks {
+
+    /// Validates the bytes of `chunk`, found at `offset` in the source, not
+    /// validated yet
+    pub fn feed(&mut self, chunk: &[u8], offset: u64) {
+        let end = offset + chunk.len() as u64;
+        if end <= self.validated {
+            return;
+        }
+        if self.validated < offset {
+            // skipped bytes, a BOM or a line ending
+            self.validated = offset;
+            self.carry.clear();
+        }
+        let mut bytes = &chunk[(self.validated - offset) as usize..];
+        let mut pos = self.validated;
+        while !self.carry.is_empty() {
+            if self.carry.len() == char_len(self.carry[0]) {
+                if str::from_utf8(&self.carry).is_err() {
+                    self.invalid.push_back(self.carry_offset);
+                }
+                self.carry.clear();
+            } else if bytes.is_empty() {
+                self.validated = end;
+                return;
+            } else if bytes[0] & 0xc0 == 0x80 {
+                self.carry.push(bytes[0]);
+                bytes = &bytes[1..];
+                pos += 1;
+            } else {
+                // truncated character, the byte starts a new one
+                self.invalid.push_back(self.carry_offset);
+                self.carry.clear();
+            }
+        }
+        while let Err(e) = str::from_utf8(bytes) {
+            let valid = e.valid_up_to();
+            match e.error_len() {
+                Some(len) => {
+                    self.invalid.push_back(pos + valid as u64);
+                    bytes = &bytes[valid + len..];
+                    pos += (valid + len) as u64;
+                },
+                None => {
+                    self.carry = bytes[valid..].to_vec();
+                    self.carry_offset = pos + valid as u64;
+                    break;
+                },
+            }
+        }
+        self.validated = end;
+    }
+
+    /// Whether the source bytes from `start` to `end` are valid, earlier
+    /// bytes being never checked again
+    ///
+    /// Returns `None` if the range was not fed, or precedes the last range
+    /// checked, e.g. after a seek.
+    pub fn check(&mut self, start: u64, end: u64) -> Option<bool> {
+        if end > self.validated || start < self.checked {
+            return None;
+        }
+        self.checked = start;
+        while self.invalid.front().is_some_and(|&o| o < start) {
+            self.invalid.pop_front();
+        }
+        let carried = !self.carry.is_empty() && self.carry_offset < end;
+        Some(!carried && self.invalid.front().is_none_or(|&o| o >= end))
+    }
+
+}
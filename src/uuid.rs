@@ -0,0 +1,112 @@
+//! UUID columns
+//!
+//! Parses columns into `uuid::Uuid`s, whatever their form: hyphenated
+//! (`67e55044-10b1-426f-9247-bb680e5fe0c8`), simple (without hyphens),
+//! braced (`{...}`) or URN (`urn:uuid:...`), and formats them back in any
+//! of these forms. Requires the `uuid` feature.
+//!
+//! `Uuid` implements `FromStr`, so `CsvRecord` fields and `Row::get_as`
+//! parse it as is. `Row::decode` needs the `UuidField` wrapper.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::uuid::{UuidField, UuidFormat};
+//! use quick_csv::writer::RecordBuilder;
+//!
+//! let row = quick_csv::Csv::from_string("{67e55044-10b1-426f-9247-bb680e5fe0c8},x").next().unwrap().unwrap();
+//! let (id, _) = row.decode::<(UuidField, String)>().unwrap();
+//! let row = RecordBuilder::new().uuid(&id, UuidFormat::Simple).build();
+//! assert_eq!(row.to_string(), "67e5504410b1426f9247bb680e5fe0c8");
+//! ```
+
+use std::ops::Deref;
+
+use rustc_serialize::{Decodable, Decoder};
+use uuid_crate::Uuid;
+
+use error::{Error, Result};
+use writer::RecordBuilder;
+use Row;
+
+/// How UUIDs are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UuidFormat {
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`
+    #[default]
+    Hyphenated,
+    /// `67e5504410b1426f9247bb680e5fe0c8`
+    Simple,
+    /// `{67e55044-10b1-426f-9247-bb680e5fe0c8}`
+    Braced,
+    /// `urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8`
+    Urn,
+}
+
+impl UuidFormat {
+
+    /// Formats `uuid`, in lowercase
+    pub fn format(&self, uuid: &Uuid) -> String {
+        match *self {
+            UuidFormat::Hyphenated => uuid.hyphenated().to_string(),
+            UuidFormat::Simple => uuid.simple().to_string(),
+            UuidFormat::Braced => uuid.braced().to_string(),
+            UuidFormat::Urn => uuid.urn().to_string(),
+        }
+    }
+
+}
+
+/// Parses a UUID in any form, surrounding spaces aside
+pub fn parse(col: &str) -> Result<Uuid> {
+    Uuid::parse_str(col.trim()).map_err(|e| Error::Decode(format!("Cannot parse '{}' as a UUID: {}", col, e)))
+}
+
+/// A `Uuid` decoded by `Row::decode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UuidField(pub Uuid);
+
+impl Decodable for UuidField {
+    fn decode<D: Decoder>(d: &mut D) -> ::std::result::Result<UuidField, D::Error> {
+        let col = d.read_str()?;
+        Uuid::parse_str(col.trim()).map(UuidField)
+            .map_err(|e| d.error(&format!("Cannot parse '{}' as a UUID: {}", col, e)))
+    }
+}
+
+impl Deref for UuidField {
+    type Target = Uuid;
+
+    fn deref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl From<UuidField> for Uuid {
+    fn from(field: UuidField) -> Uuid {
+        field.0
+    }
+}
+
+impl Row {
+
+    /// Parses the `i`th column as a UUID, in any form
+    pub fn get_uuid(&self, i: usize) -> Result<Uuid> {
+        let col = self.columns()?.get(i).ok_or_else(|| Error::Decode(format!(
+            "No column {}, the row has {} columns", i, self.len())))?;
+        parse(col).map_err(|e| match e {
+            Error::Decode(msg) => Error::Decode(format!("Column {}: {}", i, msg)),
+            e => e,
+        })
+    }
+
+}
+
+impl RecordBuilder {
+
+    /// Appends a UUID written in `format`
+    pub fn uuid(self, uuid: &Uuid, format: UuidFormat) -> RecordBuilder {
+        self.field(format.format(uuid))
+    }
+
+}
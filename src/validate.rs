@@ -0,0 +1,303 @@
+//! Validation report
+//!
+//! Checks an entire csv and reports every issue found, instead of failing
+//! at the first one like the `Csv` iterator does.
+//!
+//! A record with a quote error is reported and skipped up to the end of
+//! its line, validation goes on with the next line.
+//!
+//! `Csv::scan_utf8` locates invalid UTF-8 sequences more precisely, down
+//! to their byte offsets.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//! use quick_csv::validate::IssueKind;
+//!
+//! let report = Csv::from_string("a,b\nc,d\"\ne\nf,g").validate().unwrap();
+//! assert_eq!(report.rows, 4);
+//! assert_eq!(report.issues.len(), 2);
+//! assert_eq!(report.issues[0].line, 2);
+//! assert_eq!(report.issues[0].kind, IssueKind::UnexpectedQuote);
+//! assert_eq!(report.issues[1].kind, IssueKind::ColumnCount { expected: 2, found: 1 });
+//! ```
+
+use std::io::BufRead;
+
+use columns::{column_span, BytesColumns};
+use date;
+use error::{Error, Result};
+use parser::Splitter;
+use {ColumnPolicy, Csv, EmptyRecord};
+
+/// Kind of a validation issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// A quote within a column not starting with a quote
+    UnexpectedQuote,
+    /// A quote within a quoted column, neither escaped nor closing it
+    UnescapedQuote,
+    /// A quoted column still open at the end of the file
+    UnclosedQuote,
+    /// A column count not allowed by the column policy
+    ColumnCount {
+        /// column count of the first record
+        expected: usize,
+        /// column count of this record
+        found: usize,
+    },
+    /// A column which is not valid UTF-8
+    InvalidUtf8 {
+        /// position of the first invalid column
+        column: usize,
+    },
+    /// A column not matching its `Csv::date_format`
+    InvalidDate {
+        /// position of the first invalid column
+        column: usize,
+    },
+}
+
+/// An issue found in a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// record number, starting at 1 and including the header
+    pub record: usize,
+    /// line number of the record start, starting at 1 on the first validated line
+    pub line: usize,
+    /// byte offset of the record start in the source
+    pub offset: u64,
+    /// what is wrong with the record
+    pub kind: IssueKind,
+}
+
+/// Result of a validation pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// number of records, excluding the header
+    pub rows: usize,
+    /// all issues, in file order
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+
+    /// Whether no issue was found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn add(&mut self, start: Start, kind: Option<IssueKind>, header: bool) {
+        if !(header && start.record == 1) {
+            self.rows += 1;
+        }
+        if let Some(kind) = kind {
+            self.issues.push(Issue {
+                record: start.record,
+                line: start.line,
+                offset: start.offset,
+                kind,
+            });
+        }
+    }
+
+}
+
+/// Start of the record being validated
+#[derive(Debug, Clone, Copy)]
+struct Start {
+    record: usize,
+    line: usize,
+    offset: u64,
+}
+
+/// Reads records line by line, going on after quote errors
+struct Scanner {
+    splitter: Splitter,
+    /// content and column ends of the last record, as split
+    buf: Vec<u8>,
+    cols: Vec<usize>,
+    /// all lines of the last record, as read
+    raw: Vec<u8>,
+    line: Vec<u8>,
+    offset: u64,
+    line_no: usize,
+    record: usize,
+}
+
+impl Scanner {
+
+    fn new<B: BufRead>(csv: &Csv<B>, raw: bool) -> Scanner {
+        Scanner {
+            splitter: Splitter::new(csv.delimiter).raw(raw),
+            buf: Vec::new(),
+            cols: Vec::new(),
+            raw: Vec::new(),
+            line: Vec::new(),
+            offset: csv.position,
+            line_no: 0,
+            record: csv.current_line,
+        }
+    }
+
+    /// Reads the next record, with its quote issue if any
+    ///
+    /// A record with a quote error ends with its line.
+    fn next<R: BufRead>(&mut self, reader: &mut R) -> Result<Option<(Start, Option<IssueKind>)>> {
+        self.splitter.reset();
+        self.buf.clear();
+        self.cols.clear();
+        self.raw.clear();
+        let mut start = None;
+        loop {
+            self.line.clear();
+            let n = reader.read_until(b'\n', &mut self.line)?;
+            if n == 0 {
+                let unclosed = if self.splitter.in_quotes() { Some(IssueKind::UnclosedQuote) } else { None };
+                return Ok(start.map(|start| (start, unclosed)));
+            }
+            self.line_no += 1;
+            if start.is_none() {
+                self.record += 1;
+                start = Some(Start { record: self.record, line: self.line_no, offset: self.offset });
+            }
+            self.offset += n as u64;
+            self.raw.extend_from_slice(&self.line);
+            let kind = match self.splitter.split(&self.line, &mut self.buf, 0, &mut self.cols) {
+                Ok((_, false)) => continue,
+                Ok((_, true)) => None,
+                Err(Error::UnexpextedQuote) => Some(IssueKind::UnexpectedQuote),
+                Err(_) => Some(IssueKind::UnescapedQuote),
+            };
+            return Ok(start.map(|start| (start, kind)));
+        }
+    }
+
+}
+
+/// An invalid UTF-8 sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utf8Issue {
+    /// record number, starting at 1 and including the header
+    pub record: usize,
+    /// line number of the record start, starting at 1 on the first scanned line
+    pub line: usize,
+    /// position of the column, unknown in records with quote errors
+    pub column: Option<usize>,
+    /// byte offset of the sequence in the source
+    pub offset: u64,
+    /// length of the sequence, in bytes
+    pub len: usize,
+}
+
+/// Finds the start and length of all invalid UTF-8 sequences in `bytes`
+fn invalid_sequences(bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut sequences = Vec::new();
+    let mut pos = 0;
+    while let Err(e) = ::std::str::from_utf8(&bytes[pos..]) {
+        let start = pos + e.valid_up_to();
+        let len = e.error_len().unwrap_or(bytes.len() - start);
+        sequences.push((start, len));
+        pos = start + len;
+    }
+    sequences
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Reads all remaining records, reporting all their issues
+    ///
+    /// Records are checked with the reader delimiter, column policy, empty
+    /// record definition, UTF-8 assumption and date formats. Only IO errors
+    /// fail the validation.
+    pub fn validate(mut self) -> Result<Report> {
+        let mut report = Report::default();
+        let mut scanner = Scanner::new(&self, false);
+        let mut expected = self.len;
+        let header = self.has_header && self.headers.is_none();
+        while let Some((start, quote)) = scanner.next(&mut self.reader)? {
+            let is_header = header && start.record == 1;
+            let kind = quote.or_else(|| self.check(&mut scanner.buf, &mut scanner.cols, &mut expected, !is_header));
+            if is_header && kind.is_none() {
+                // date formats may refer to columns by name
+                let names = BytesColumns::new(&scanner.buf, &scanner.cols)
+                    .map(|c| String::from_utf8_lossy(c).into_owned())
+                    .collect();
+                self.set_headers(names);
+            }
+            report.add(start, kind, header);
+        }
+        Ok(report)
+    }
+
+    /// Locates all invalid UTF-8 sequences of the remaining records
+    ///
+    /// Unlike `validate`, every sequence is reported, with its exact byte
+    /// offset. Sequences are located in columns, except in records with quote
+    /// errors which are scanned as a whole.
+    pub fn scan_utf8(mut self) -> Result<Vec<Utf8Issue>> {
+        let mut issues = Vec::new();
+        let mut scanner = Scanner::new(&self, true);
+        while let Some((start, quote)) = scanner.next(&mut self.reader)? {
+            let issue = |column, pos: usize, len| Utf8Issue {
+                record: start.record,
+                line: start.line,
+                column,
+                offset: start.offset + pos as u64,
+                len,
+            };
+            if quote.is_some() {
+                issues.extend(invalid_sequences(&scanner.raw).into_iter()
+                              .map(|(pos, len)| issue(None, pos, len)));
+                continue;
+            }
+            scanner.cols.push(scanner.buf.len());
+            for i in 0..scanner.cols.len() {
+                // raw columns are at the same offsets as in the source
+                let (col_start, col_end) = column_span(&scanner.cols, i);
+                issues.extend(invalid_sequences(&scanner.buf[col_start..col_end]).into_iter()
+                              .map(|(pos, len)| issue(Some(i), col_start + pos, len)));
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Checks a complete record, `expected` being the column count of the first one
+    ///
+    /// Dates are only checked with `dates`, i.e. not in the header.
+    fn check(&self, buf: &mut Vec<u8>, cols: &mut Vec<usize>,
+             expected: &mut Option<usize>, dates: bool) -> Option<IssueKind> {
+        if buf.ends_with(b"\r") {
+            buf.pop();
+        }
+        if !(buf.is_empty() && cols.is_empty() && self.empty_record == EmptyRecord::NoColumns) {
+            cols.push(buf.len());
+            let found = cols.len();
+            let expected = *expected.get_or_insert(found);
+            let mismatch = match self.column_policy {
+                ColumnPolicy::Strict => found != expected,
+                ColumnPolicy::Flexible => false,
+                ColumnPolicy::Pad => found > expected,
+                ColumnPolicy::Truncate => found < expected,
+            };
+            if mismatch {
+                return Some(IssueKind::ColumnCount { expected, found });
+            }
+        }
+        if !self.assume_utf8 {
+            let invalid = BytesColumns::new(buf, cols).position(|c| ::std::str::from_utf8(c).is_err());
+            if let Some(column) = invalid {
+                return Some(IssueKind::InvalidUtf8 { column });
+            }
+        }
+        let options = self.decode_options.as_ref().filter(|o| dates && o.has_date_formats())?;
+        BytesColumns::new(buf, cols).enumerate()
+            .position(|(i, c)| match (options.date_format(i), ::std::str::from_utf8(c)) {
+                (Some(format), Ok(c)) => !c.is_empty() && date::parse(c, format).is_err(),
+                _ => false,
+            })
+            .map(|column| IssueKind::InvalidDate { column })
+    }
+
+}
@@ -0,0 +1,135 @@
+//! Column display widths
+//!
+//! Measures the maximum and average display width of every column in a
+//! single pass over the remaining rows, e.g. to align columns when printing
+//! them as a table or to size the columns of a fixed-width export.
+//!
+//! Widths are counted in terminal cells: East Asian wide and fullwidth
+//! characters take 2 cells, combining marks and control characters none.
+//!
+//! # Example
+//!
+//! ```
+//! use quick_csv::Csv;
+//!
+//! let mut csv = Csv::from_string("name,city\nbob,東京\nalice,Paris").has_header(true);
+//! let widths = csv.widths().unwrap();
+//! assert_eq!((widths.columns[0].max, widths.columns[1].max), (5, 5));
+//! assert_eq!(widths.columns[1].avg(), 4.5);
+//! assert_eq!(widths.pad(1, "東京"), "東京 ");
+//! ```
+
+use std::io::BufRead;
+
+use error::Result;
+use Csv;
+
+/// Character ranges taking 2 cells
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F), (0x2E80, 0x303E), (0x3041, 0x33FF), (0x3400, 0x4DBF), (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF), (0xAC00, 0xD7A3), (0xF900, 0xFAFF), (0xFE30, 0xFE4F), (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6), (0x1F300, 0x1F64F), (0x1F900, 0x1F9FF), (0x20000, 0x2FFFD), (0x30000, 0x3FFFD),
+];
+
+/// Character ranges taking no cell, control characters aside
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036F), (0x1AB0, 0x1AFF), (0x1DC0, 0x1DFF), (0x200B, 0x200F), (0x20D0, 0x20FF),
+    (0xFE00, 0xFE0F), (0xFE20, 0xFE2F), (0xFEFF, 0xFEFF),
+];
+
+/// Gets the number of terminal cells taken by a character
+pub fn char_width(c: char) -> usize {
+    let c = c as u32;
+    let within = |ranges: &[(u32, u32)]| ranges.iter().any(|&(start, end)| start <= c && c <= end);
+    if c < 0x20 || (0x7F..0xA0).contains(&c) || within(ZERO) {
+        0
+    } else if within(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Gets the number of terminal cells taken by a string
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// Widths of a column
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnWidth {
+    /// header name, if any
+    pub name: Option<String>,
+    /// widest value
+    pub max: usize,
+    /// sum of the widths of all values
+    pub total: usize,
+    /// number of values, rows without this column excluded
+    pub count: usize,
+}
+
+impl ColumnWidth {
+
+    /// Gets the average width of values, 0 without value
+    pub fn avg(&self) -> f64 {
+        if self.count == 0 { 0. } else { self.total as f64 / self.count as f64 }
+    }
+
+    /// Gets the width needed by both values and the header
+    pub fn header_max(&self) -> usize {
+        self.name.as_ref().map_or(self.max, |n| self.max.max(display_width(n)))
+    }
+
+}
+
+/// Widths of all columns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Widths {
+    /// number of rows, excluding the header
+    pub rows: usize,
+    /// widths by column position
+    pub columns: Vec<ColumnWidth>,
+}
+
+impl Widths {
+
+    /// Pads `value` with spaces to the width of the `i`th column, header included
+    ///
+    /// Values already wider, or of unknown columns, are kept as is.
+    pub fn pad(&self, i: usize, value: &str) -> String {
+        let width = self.columns.get(i).map_or(0, ColumnWidth::header_max);
+        let mut padded = value.to_string();
+        padded.extend(::std::iter::repeat_n(' ', width.saturating_sub(display_width(value))));
+        padded
+    }
+
+}
+
+impl<B: BufRead> Csv<B> {
+
+    /// Measures the display widths of the remaining rows
+    pub fn widths(&mut self) -> Result<Widths> {
+        let mut widths = Widths {
+            rows: 0,
+            columns: self.headers().into_iter()
+                .map(|h| ColumnWidth { name: Some(h), ..ColumnWidth::default() })
+                .collect(),
+        };
+        for row in &mut *self {
+            let row = row?;
+            for (i, col) in row.columns()?.enumerate() {
+                if widths.columns.len() <= i {
+                    widths.columns.push(ColumnWidth::default());
+                }
+                let width = display_width(col);
+                let column = &mut widths.columns[i];
+                column.max = column.max.max(width);
+                column.total += width;
+                column.count += 1;
+            }
+            widths.rows += 1;
+        }
+        Ok(widths)
+    }
+
+}
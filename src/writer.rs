@@ -0,0 +1,481 @@
+//! Csv writer module
+//!
+//! Writes rows, quoting columns only when needed
+//!
+//! A column is quoted exactly when it contains the delimiter, a quote, CR
+//! or LF, so that reading written rows back returns the same columns. Two
+//! edge cases are quoted as well: a row with a single empty column, which
+//! would otherwise be an empty line, and a first column starting with a
+//! UTF-8 BOM, which readers would strip.
+//!
+//! Output is buffered, call `flush` or use `flush_every` to control when
+//! data reaches the underlying writer.
+//!
+//! Rows of typed values, e.g. for reports, are built with `RecordBuilder`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use binary::Encoding;
+use dialect::{Backslash, Dialect};
+use error::{Error, Result};
+use {Row, Terminator, UTF8_BOM};
+
+/// Defines how quotes are escaped within quoted columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// Quotes are doubled: `"a ""quoted"" word"` (RFC 4180)
+    Double,
+    /// Quotes and backslashes are prefixed with a backslash: `"a \"quoted\" word"`
+    Backslash,
+}
+
+/// Csv writer
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::writer::Writer;
+///
+/// let mut writer = Writer::from_writer(Vec::new());
+/// writer.write(["a", "b,c"]).unwrap();
+/// writer.write(["d", "e\"f"]).unwrap();
+/// assert_eq!(writer.into_inner().unwrap(), b"a,\"b,c\"\nd,\"e\"\"f\"\n");
+/// ```
+pub struct Writer<W: Write> {
+    /// buffered writer
+    writer: BufWriter<W>,
+    /// delimiter
+    delimiter: u8,
+    /// byte surrounding quoted columns
+    quote: u8,
+    /// quote escaping style
+    escape: Escape,
+    /// line ending of rows
+    terminator: Terminator,
+    /// written for NULL columns
+    null: Option<Vec<u8>>,
+    /// flushes every `flush_every` rows, never if 0
+    flush_every: usize,
+    /// rows written since last flush
+    unflushed: usize,
+    /// whether a row was already written
+    started: bool,
+    /// quotes every column
+    quote_all: bool,
+    /// starts with a UTF-8 BOM
+    bom: bool,
+    /// starts with a `sep=` line
+    sep_line: bool,
+    /// escapes columns with backslashes instead of quoting them
+    backslash: Backslash,
+    /// encodings of binary columns, by position
+    binary: Vec<Option<Encoding>>,
+}
+
+impl<W: Write> Writer<W> {
+
+    /// Creates a Writer from a generic writer
+    ///
+    /// Note: default delimiter = ',', default escape = `Escape::Double`,
+    /// rows are terminated with "\n"
+    pub fn from_writer(writer: W) -> Writer<W> {
+        Writer::with_capacity(writer, 8 * 1024)
+    }
+
+    /// Creates a Writer from a generic writer with a specific buffer capacity
+    pub fn with_capacity(writer: W, capacity: usize) -> Writer<W> {
+        Writer {
+            writer: BufWriter::with_capacity(capacity, writer),
+            delimiter: b',',
+            quote: b'"',
+            escape: Escape::Double,
+            terminator: Terminator::Lf,
+            null: None,
+            flush_every: 0,
+            unflushed: 0,
+            started: false,
+            quote_all: false,
+            bom: false,
+            sep_line: false,
+            backslash: Backslash::Plain,
+            binary: Vec::new(),
+        }
+    }
+
+    /// Sets all the options of a dialect
+    ///
+    /// Rows written are read back identically with the same dialect.
+    pub fn dialect(mut self, dialect: Dialect) -> Writer<W> {
+        self.delimiter = dialect.delimiter;
+        self.quote = dialect.quote;
+        self.escape = dialect.escape;
+        self.backslash = dialect.backslash;
+        self.terminator = dialect.terminator;
+        self.null = dialect.null.map(|n| n.as_bytes().to_vec());
+        self.quote_all = dialect.quote_all;
+        self.bom = dialect.bom;
+        self.sep_line = dialect.sep_line;
+        self
+    }
+
+    /// Sets a new delimiter
+    pub fn delimiter(mut self, delimiter: u8) -> Writer<W> {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the byte surrounding quoted columns
+    ///
+    /// Note: default = `"`
+    pub fn quote(mut self, quote: u8) -> Writer<W> {
+        self.quote = quote;
+        self
+    }
+
+    /// Sets how quotes are escaped
+    pub fn escape(mut self, escape: Escape) -> Writer<W> {
+        self.escape = escape;
+        self
+    }
+
+    /// Terminates rows with "\r\n", as expected by Excel
+    pub fn crlf(mut self, crlf: bool) -> Writer<W> {
+        self.terminator = if crlf { Terminator::CrLf } else { Terminator::Lf };
+        self
+    }
+
+    /// Sets the line ending of rows, `\r\n` for `Terminator::Any`
+    ///
+    /// Note: default = `Terminator::Lf`
+    pub fn terminator(mut self, terminator: Terminator) -> Writer<W> {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Writes NULL columns as `token`, unquoted
+    ///
+    /// Columns equal to the token are quoted, to be read back as strings by
+    /// `Csv::null_token`.
+    ///
+    /// Note: default = NULL columns are empty, or `\N` with backslash escapes
+    pub fn null_token<S: Into<String>>(mut self, token: S) -> Writer<W> {
+        self.null = Some(token.into().into_bytes());
+        self
+    }
+
+    /// Quotes every column, needed or not
+    ///
+    /// Note: default = `false`, columns are quoted only when needed
+    pub fn quote_all(mut self, quote_all: bool) -> Writer<W> {
+        self.quote_all = quote_all;
+        self
+    }
+
+    /// Escapes columns with backslashes instead of quoting them, e.g. for
+    /// MySQL `LOAD DATA` or the PostgreSQL `COPY` text format
+    ///
+    /// Backslashes, LF, CR and tabs are written `\\`, `\n`, `\r` and `\t`,
+    /// NUL `\0` with `Backslash::MySql`, backspace, form feed and vertical
+    /// tab `\b`, `\f` and `\v` with `Backslash::Postgres`. The delimiter is
+    /// prefixed with a backslash, and NULL columns of `write_nullable` or
+    /// `write_row` are written `\N`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_csv::dialect::Backslash;
+    /// use quick_csv::writer::Writer;
+    ///
+    /// let mut writer = Writer::from_writer(Vec::new()).delimiter(b'\t').backslash_escapes(Backslash::MySql);
+    /// writer.write_nullable([Some("a\tb"), None, Some("\"c\\")]).unwrap();
+    /// assert_eq!(writer.into_inner().unwrap(), b"a\\tb\t\\N\t\"c\\\\\n");
+    /// ```
+    ///
+    /// Note: default = `Backslash::Plain`, columns are quoted when needed
+    pub fn backslash_escapes(mut self, backslash: Backslash) -> Writer<W> {
+        self.backslash = backslash;
+        self
+    }
+
+    /// Starts the output with a UTF-8 BOM, for Excel to detect the encoding
+    ///
+    /// Note: default = `false`
+    pub fn bom(mut self, bom: bool) -> Writer<W> {
+        self.bom = bom;
+        self
+    }
+
+    /// Starts the output with a `sep=` line of the delimiter, for Excel to
+    /// split columns whatever the locale
+    ///
+    /// Note: default = `false`
+    pub fn sep_line(mut self, sep_line: bool) -> Writer<W> {
+        self.sep_line = sep_line;
+        self
+    }
+
+    /// Encodes the `i`th column of every row written with `encoding`, for
+    /// binary content
+    ///
+    /// Note: default = columns are written as is
+    pub fn binary_column(mut self, i: usize, encoding: Encoding) -> Writer<W> {
+        if self.binary.len() <= i {
+            self.binary.resize(i + 1, None);
+        }
+        self.binary[i] = Some(encoding);
+        self
+    }
+
+    /// Flushes the buffer every `rows` rows, bounding data loss on crash
+    ///
+    /// Note: default = 0, buffer is only flushed when full
+    pub fn flush_every(mut self, rows: usize) -> Writer<W> {
+        self.flush_every = rows;
+        self
+    }
+
+    /// Writes a new row out of its columns
+    pub fn write<I, S>(&mut self, columns: I) -> Result<()>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<[u8]>
+    {
+        self.write_nullable(columns.into_iter().map(Some))
+    }
+
+    /// Writes a new row out of columns possibly NULL
+    ///
+    /// NULL columns are written as the token of `null_token`, else `\N` with
+    /// backslash escapes, else empty.
+    pub fn write_nullable<I, S>(&mut self, columns: I) -> Result<()>
+        where I: IntoIterator<Item = Option<S>>,
+              S: AsRef<[u8]>
+    {
+        self.write_columns(columns.into_iter().map(|col| (col, false)), false)
+    }
+
+    /// Writes a new row out of columns possibly NULL, and whether to quote
+    /// them if empty
+    ///
+    /// `raw` columns are source bytes, already quoted and escaped, written
+    /// as is.
+    fn write_columns<I, S>(&mut self, columns: I, raw: bool) -> Result<()>
+        where I: IntoIterator<Item = (Option<S>, bool)>,
+              S: AsRef<[u8]>
+    {
+        if !self.started && self.bom {
+            self.writer.write_all(UTF8_BOM)?;
+        }
+        if !self.started && self.sep_line {
+            self.writer.write_all(b"sep=")?;
+            self.writer.write_all(&[self.delimiter])?;
+            self.writer.write_all(self.terminator.as_bytes())?;
+        }
+        let mut columns = columns.into_iter().peekable();
+        let mut i = 0;
+        while let Some((col, quoted)) = columns.next() {
+            if i > 0 {
+                self.writer.write_all(&[self.delimiter])?;
+            }
+            if let (true, Some(col)) = (raw, col.as_ref()) {
+                self.writer.write_all(col.as_ref())?;
+                i += 1;
+                continue;
+            }
+            let encoded = match (col.as_ref(), self.binary.get(i)) {
+                (Some(col), Some(Some(encoding))) => Some(encoding.encode(col.as_ref())),
+                _ => None,
+            };
+            let col = encoded.as_ref().map(String::as_bytes).or_else(|| col.as_ref().map(AsRef::as_ref));
+            match (col, self.null.as_deref()) {
+                (None, Some(null)) => self.writer.write_all(null)?,
+                (None, None) if self.backslash != Backslash::Plain => self.writer.write_all(b"\\N")?,
+                (Some(col), _) if self.backslash != Backslash::Plain => {
+                    write_escaped(&mut self.writer, col, self.delimiter, self.backslash)?
+                },
+                _ if i == 0 && col.is_none_or(<[u8]>::is_empty) && columns.peek().is_none() => {
+                    // a lone empty column would be an empty line
+                    self.writer.write_all(&[self.quote, self.quote])?
+                },
+                _ => {
+                    let col = col.unwrap_or(&[]);
+                    let bom = !self.started && i == 0 && !(self.bom || self.sep_line) && col.starts_with(UTF8_BOM);
+                    // a column equal to the NULL token is quoted to be read as a string
+                    let null = self.null.as_deref() == Some(col);
+                    let quoted = quoted && col.is_empty();
+                    write_column(&mut self.writer, col, self.delimiter, self.quote, self.escape,
+                                 bom || null || quoted || self.quote_all)?
+                },
+            }
+            i += 1;
+        }
+        self.started = true;
+        self.writer.write_all(self.terminator.as_bytes())?;
+        self.unflushed += 1;
+        if self.unflushed == self.flush_every {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes a `Row`, its NULL and quoted empty columns included
+    ///
+    /// Columns of raw rows are written as read, as with `Row::write_csv`.
+    pub fn write_row(&mut self, row: &Row) -> Result<()> {
+        self.write_columns(row.bytes_fields().map(|(i, col)| {
+            (if row.is_null(i) { None } else { Some(col) }, row.is_quoted(i))
+        }), row.raw)
+    }
+
+    /// Flushes buffered data into the underlying writer
+    pub fn flush(&mut self) -> Result<()> {
+        self.unflushed = 0;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Gets the buffer capacity
+    pub fn capacity(&self) -> usize {
+        self.writer.capacity()
+    }
+
+    /// Gets a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Flushes the buffer and unwraps this `Writer`, returning the underlying writer
+    pub fn into_inner(self) -> Result<W> {
+        self.writer.into_inner().map_err(|e| Error::Io(e.into_error()))
+    }
+
+}
+
+/// Writes a column, quoted if needed or if `force_quotes`
+pub(crate) fn write_column<W: Write>(writer: &mut W, col: &[u8], delimiter: u8, quote: u8,
+                                     escape: Escape, force_quotes: bool) -> io::Result<()> {
+    let needs_quotes = force_quotes || col.iter().any(|&b| {
+        b == delimiter || b == quote || b == b'\n' || b == b'\r'
+            || (b == b'\\' && escape == Escape::Backslash)
+    });
+    if !needs_quotes {
+        return writer.write_all(col);
+    }
+
+    writer.write_all(&[quote])?;
+    let mut start = 0;
+    for (i, &b) in col.iter().enumerate() {
+        let prefix = match escape {
+            Escape::Double if b == quote => quote,
+            Escape::Backslash if b == quote || b == b'\\' => b'\\',
+            _ => continue,
+        };
+        writer.write_all(&col[start..i])?;
+        writer.write_all(&[prefix])?;
+        start = i;
+    }
+    writer.write_all(&col[start..])?;
+    writer.write_all(&[quote])
+}
+
+/// Writes a column escaped with backslashes
+pub(crate) fn write_escaped<W: Write>(writer: &mut W, col: &[u8], delimiter: u8, backslash: Backslash) -> io::Result<()> {
+    let escaped_delimiter = [b'\\', delimiter];
+    let mut start = 0;
+    for (i, &b) in col.iter().enumerate() {
+        let escaped: &[u8] = match (backslash, b) {
+            (_, b'\\') => b"\\\\",
+            (_, b'\n') => b"\\n",
+            (_, b'\r') => b"\\r",
+            (_, b'\t') => b"\\t",
+            (Backslash::MySql, 0) => b"\\0",
+            (Backslash::Postgres, 8) => b"\\b",
+            (Backslash::Postgres, 12) => b"\\f",
+            (Backslash::Postgres, 11) => b"\\v",
+            _ if b == delimiter => &escaped_delimiter,
+            _ => continue,
+        };
+        writer.write_all(&col[start..i])?;
+        writer.write_all(escaped)?;
+        start = i + 1;
+    }
+    writer.write_all(&col[start..])
+}
+
+impl Writer<File> {
+    /// Creates a writer to a file path
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Writer<File>> {
+        Ok(Writer::from_writer(File::create(path)?))
+    }
+}
+
+/// Builds a row out of typed values
+///
+/// Values are formatted with `Display`, `None` being an empty column, and
+/// quoted when written as needed.
+///
+/// # Example
+///
+/// ```rust
+/// use quick_csv::writer::{RecordBuilder, Writer};
+///
+/// let mut writer = Writer::from_writer(Vec::new());
+/// RecordBuilder::new()
+///     .field("total, all")
+///     .field(42)
+///     .option(None::<f64>)
+///     .float(2.0 / 3.0, 2)
+///     .write_to(&mut writer)
+///     .unwrap();
+/// assert_eq!(writer.into_inner().unwrap(), b"\"total, all\",42,,0.67\n");
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordBuilder {
+    row: Row,
+}
+
+impl RecordBuilder {
+
+    /// Creates a builder without column
+    pub fn new() -> RecordBuilder {
+        RecordBuilder::default()
+    }
+
+    /// Appends a column formatted with `Display`
+    pub fn field<T: fmt::Display>(mut self, value: T) -> RecordBuilder {
+        self.row.push(value.to_string());
+        self
+    }
+
+    /// Appends a column, empty if `None`
+    pub fn option<T: fmt::Display>(self, value: Option<T>) -> RecordBuilder {
+        match value {
+            Some(value) => self.field(value),
+            None => self.field(""),
+        }
+    }
+
+    /// Appends a number with `precision` decimals
+    pub fn float(self, value: f64, precision: usize) -> RecordBuilder {
+        self.field(format_args!("{:.*}", precision, value))
+    }
+
+    /// Appends a column as is
+    pub fn bytes<S: AsRef<[u8]>>(mut self, value: S) -> RecordBuilder {
+        self.row.push(value);
+        self
+    }
+
+    /// Gets the row built
+    pub fn build(self) -> Row {
+        self.row
+    }
+
+    /// Writes the row built
+    pub fn write_to<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+        writer.write_row(&self.row)
+    }
+
+}